@@ -0,0 +1,32 @@
+use evm_rs_emulator::Runner;
+use std::time::Instant;
+
+const RUNS: u32 = 20;
+
+fn main() {
+    // Loop 10000 times over an MLOAD / MSTORE pair on a 1KB memory window:
+    //
+    //     PUSH2 0x2710
+    //     JUMPDEST
+    //     DUP1 PUSH2 0x03ff AND
+    //     DUP1 MLOAD SWAP1 MSTORE
+    //     PUSH1 0x01 SWAP1 SUB
+    //     DUP1 PUSH1 0x03 JUMPI
+    //     STOP
+    let bytecode = hex::decode("6127105b806103ff1680519052600190038060035700").unwrap();
+
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner
+            .interpret(bytecode.clone(), None, true)
+            .expect("Benchmark bytecode failed");
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "mload/mstore loop: {:?} per run ({} runs)",
+        elapsed / RUNS,
+        RUNS
+    );
+}
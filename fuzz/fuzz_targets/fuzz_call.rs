@@ -0,0 +1,12 @@
+#![no_main]
+
+use evm_rs_emulator::fuzz_call;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte is the size of the code, the rest of the input is the calldata
+    if let Some((&code_size, input)) = data.split_first() {
+        let (code, calldata) = input.split_at((code_size as usize).min(input.len()));
+        fuzz_call(code, calldata);
+    }
+});
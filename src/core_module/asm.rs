@@ -0,0 +1,204 @@
+use std::fmt;
+
+use super::op_codes::metadata::op_code;
+
+/* -------------------------------------------------------------------------- */
+/*                              AssembleError enum                            */
+/* -------------------------------------------------------------------------- */
+
+/// An error found while assembling a source, with its 1-based line number.
+#[derive(Debug, PartialEq)]
+pub enum AssembleError {
+    UnknownMnemonic { line: usize, mnemonic: String },
+    MissingOperand { line: usize },
+    UnexpectedOperand { line: usize },
+    InvalidOperand { line: usize, operand: String },
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssembleError::UnknownMnemonic { line, mnemonic } => {
+                write!(f, "Line {}: unknown mnemonic {}", line, mnemonic)
+            }
+            AssembleError::MissingOperand { line } => {
+                write!(f, "Line {}: missing PUSH operand", line)
+            }
+            AssembleError::UnexpectedOperand { line } => {
+                write!(f, "Line {}: unexpected operand", line)
+            }
+            AssembleError::InvalidOperand { line, operand } => {
+                write!(f, "Line {}: invalid operand {}", line, operand)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AssembleError {}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Functions                                 */
+/* -------------------------------------------------------------------------- */
+
+/// Assembles a mnemonic source into bytecode.
+///
+/// The source has one instruction per line (e.g. `PUSH1 0x01`, `ADD`, `JUMPDEST`).
+/// PUSH operands are hex numbers, left padded to the PUSH size. Blank lines and
+/// comments starting with `;` or `//` are ignored.
+///
+/// # Arguments
+///
+/// * `source` - The source to assemble.
+///
+/// # Errors
+///
+/// Returns an `AssembleError` if a mnemonic is unknown or if an operand is missing,
+/// unexpected, not hex or too large for its PUSH.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::asm::assemble;
+///
+/// let bytecode = assemble("PUSH1 0x01\nPUSH1 0x02\nADD").unwrap();
+/// assert_eq!(bytecode, vec![0x60, 0x01, 0x60, 0x02, 0x01]);
+/// ```
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssembleError> {
+    let mut bytecode = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+
+        // Strip the comments
+        let line = line.split(';').next().unwrap_or_default();
+        let line = line.split("//").next().unwrap_or_default();
+
+        let mut tokens = line.split_whitespace();
+        let mnemonic = match tokens.next() {
+            Some(mnemonic) => mnemonic,
+            None => continue,
+        };
+        let operand = tokens.next();
+
+        let opcode = op_code(mnemonic).ok_or_else(|| AssembleError::UnknownMnemonic {
+            line: line_number,
+            mnemonic: mnemonic.to_string(),
+        })?;
+        bytecode.push(opcode);
+
+        // Only PUSH1 to PUSH32 take an operand
+        let push_size = if (0x60..=0x7f).contains(&opcode) {
+            (opcode - 0x5f) as usize
+        } else {
+            0
+        };
+
+        if tokens.next().is_some() || (push_size == 0 && operand.is_some()) {
+            return Err(AssembleError::UnexpectedOperand { line: line_number });
+        }
+
+        if push_size > 0 {
+            let operand = operand.ok_or(AssembleError::MissingOperand { line: line_number })?;
+            bytecode.extend(parse_operand(operand, push_size, line_number)?);
+        }
+    }
+
+    Ok(bytecode)
+}
+
+/// Parses a hex PUSH operand into exactly `size` big-endian bytes.
+fn parse_operand(operand: &str, size: usize, line: usize) -> Result<Vec<u8>, AssembleError> {
+    let invalid = || AssembleError::InvalidOperand {
+        line,
+        operand: operand.to_string(),
+    };
+
+    let digits = operand
+        .strip_prefix("0x")
+        .or_else(|| operand.strip_prefix("0X"))
+        .unwrap_or(operand);
+
+    // Accept odd lengths like 0x1
+    let digits = if digits.len() % 2 == 1 {
+        format!("0{}", digits)
+    } else {
+        digits.to_string()
+    };
+
+    let bytes = hex::decode(&digits).map_err(|_| invalid())?;
+    if bytes.is_empty() || bytes.len() > size {
+        return Err(invalid());
+    }
+
+    let mut padded = vec![0u8; size - bytes.len()];
+    padded.extend(bytes);
+    Ok(padded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_module::utils::bytes::_hex_string_to_bytes;
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            ; Push and pop
+            PUSH1 0x01
+            PUSH1 0x02
+            PUSH1 0x03
+            POP
+            POP
+
+            // Store 4 at 0x40
+            PUSH1 0x04
+            PUSH1 0x40
+            MSTORE
+            STOP
+        ";
+
+        assert_eq!(
+            assemble(source).unwrap(),
+            _hex_string_to_bytes("6001600260035050600460405200")
+        );
+
+        // Operands are left padded
+        assert_eq!(
+            assemble("push2 0x1\njumpdest").unwrap(),
+            vec![0x61, 0x00, 0x01, 0x5b]
+        );
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        assert_eq!(
+            assemble("ADD\nFOO").unwrap_err(),
+            AssembleError::UnknownMnemonic {
+                line: 2,
+                mnemonic: "FOO".to_string()
+            }
+        );
+        assert_eq!(
+            assemble("PUSH1").unwrap_err(),
+            AssembleError::MissingOperand { line: 1 }
+        );
+        assert_eq!(
+            assemble("ADD 0x01").unwrap_err(),
+            AssembleError::UnexpectedOperand { line: 1 }
+        );
+        assert_eq!(
+            assemble("PUSH1 0x0102").unwrap_err(),
+            AssembleError::InvalidOperand {
+                line: 1,
+                operand: "0x0102".to_string()
+            }
+        );
+        assert_eq!(
+            assemble("PUSH1 0xzz").unwrap_err(),
+            AssembleError::InvalidOperand {
+                line: 1,
+                operand: "0xzz".to_string()
+            }
+        );
+    }
+}
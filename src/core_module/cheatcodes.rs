@@ -0,0 +1,190 @@
+use super::runner::Runner;
+use super::utils::bytes::{pad_left, u256_to_usize, u256_to_word};
+use super::utils::errors::ExecutionError;
+#[cfg(feature = "alloy")]
+use super::utils::primitives::U256Compat;
+use super::utils::primitives::{keccak256, U256};
+
+/* -------------------------------------------------------------------------- */
+/*                                 Cheatcodes                                 */
+/* -------------------------------------------------------------------------- */
+
+/// The address whose calls are intercepted as cheatcodes, the HEVM address used by Foundry:
+/// `0x7109709ECfa91a80626fF3989D68f67F5b1DD12D`.
+pub const CHEATCODE_ADDRESS: [u8; 20] = [
+    0x71, 0x09, 0x70, 0x9e, 0xcf, 0xa9, 0x1a, 0x80, 0x62, 0x6f, 0xf3, 0x98, 0x9d, 0x68, 0xf6, 0x7f,
+    0x5b, 0x1d, 0xd1, 0x2d,
+];
+
+/// Returns the 4-byte selector of a function signature.
+fn selector(signature: &str) -> [u8; 4] {
+    keccak256(signature)[..4].try_into().unwrap()
+}
+
+/// Reads the `index`-th 32-byte argument of a cheatcode call as a `uint256`.
+fn uint_argument(calldata: &[u8], index: usize) -> Result<U256, ExecutionError> {
+    let start = 4 + 32 * index;
+    let word = calldata
+        .get(start..start + 32)
+        .ok_or(ExecutionError::RevertWithoutData)?;
+
+    Ok(U256::from_big_endian(word))
+}
+
+/// Runs a call made to `CHEATCODE_ADDRESS` instead of executing code. The supported
+/// cheatcodes are:
+///
+/// * `warp(uint256)` - Sets the block timestamp returned by TIMESTAMP.
+/// * `roll(uint256)` - Sets the block number returned by NUMBER.
+/// * `snapshot()` - Saves the state and returns the id of the snapshot as a `uint256`.
+/// * `revertTo(uint256)` - Restores the state saved by the given snapshot and returns `true`,
+///   or `false` if there is no such snapshot. The snapshot can be reverted to again.
+///
+/// # Arguments
+///
+/// * `runner` - The runner making the call.
+/// * `calldata` - The ABI encoded cheatcode call.
+///
+/// # Errors
+///
+/// Returns `ExecutionError::RevertWithoutData` if the cheatcode is unknown or if its
+/// arguments are malformed, so the call fails like a reverted call.
+pub fn apply(runner: &mut Runner, calldata: &[u8]) -> Result<(), ExecutionError> {
+    // Most cheatcodes don't return anything
+    runner.returndata.heap = Vec::new();
+
+    let called = calldata.get(..4).ok_or(ExecutionError::RevertWithoutData)?;
+
+    if called == selector("warp(uint256)") {
+        runner.block.timestamp = Some(uint_argument(calldata, 0)?);
+    } else if called == selector("roll(uint256)") {
+        runner.block.number = uint_argument(calldata, 0)?;
+    } else if called == selector("snapshot()") {
+        let id = U256::from(runner.snapshots.len());
+        runner.snapshots.push(runner.state.clone());
+        runner.returndata.heap = u256_to_word(id).to_vec();
+    } else if called == selector("revertTo(uint256)") {
+        let id = uint_argument(calldata, 0)?;
+        let snapshot = u256_to_usize(id)
+            .ok()
+            .and_then(|id| runner.snapshots.get(id).cloned());
+
+        let reverted = snapshot.is_some();
+        if let Some(state) = snapshot {
+            // The snapshot doesn't change whether the current call is static
+            let static_mode = runner.state.static_mode;
+            runner.state = state;
+            runner.state.static_mode = static_mode;
+        }
+        runner.returndata.heap = pad_left(&[reverted as u8]).to_vec();
+    } else {
+        return Err(ExecutionError::RevertWithoutData);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_module::utils::bytes::_hex_string_to_bytes;
+
+    /// Calls the cheatcode address with the given calldata, then runs `then`.
+    fn call_cheatcode(calldata: &[u8], then: &str) -> String {
+        // Store the calldata in memory, word by word
+        let mut bytecode = String::new();
+        for (index, word) in calldata.chunks(32).enumerate() {
+            let mut padded = word.to_vec();
+            padded.resize(32, 0);
+            bytecode += &format!("7f{}60{:02x}52", hex::encode(padded), index * 32);
+        }
+
+        // CALL(0xffff, CHEATCODE_ADDRESS, 0, 0, calldata size, 0, 0)
+        bytecode += &format!(
+            "5f5f60{:02x}5f5f73{}61fffff1{}",
+            calldata.len(),
+            hex::encode(CHEATCODE_ADDRESS),
+            then
+        );
+        bytecode
+    }
+
+    #[test]
+    fn test_warp_and_roll() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // warp(1234) then TIMESTAMP
+        let mut calldata = selector("warp(uint256)").to_vec();
+        calldata.extend(pad_left(&1234u64.to_be_bytes()));
+        let bytecode = _hex_string_to_bytes(&call_cheatcode(&calldata, "42"));
+        runner.interpret(bytecode, None, true).unwrap();
+
+        assert_eq!(
+            runner.stack.pop().unwrap(),
+            pad_left(&1234u64.to_be_bytes())
+        );
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x01]));
+        assert_eq!(runner.block.timestamp, Some(U256::from(1234)));
+
+        // roll(99) then NUMBER
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let mut calldata = selector("roll(uint256)").to_vec();
+        calldata.extend(pad_left(&[99]));
+        let bytecode = _hex_string_to_bytes(&call_cheatcode(&calldata, "43"));
+        runner.interpret(bytecode, None, true).unwrap();
+
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[99]));
+        assert_eq!(runner.block.number, U256::from(99));
+    }
+
+    #[test]
+    fn test_snapshot_and_revert_to() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // SSTORE(0, 10), snapshot() then push the returned id with RETURNDATACOPY and MLOAD
+        let mut bytecode = String::from("600a5f55");
+        bytecode += &call_cheatcode(&selector("snapshot()"), "60205f5f3e5f51");
+
+        // SSTORE(0, 20), revertTo(0) then SLOAD(0)
+        bytecode += "60145f55";
+        let mut calldata = selector("revertTo(uint256)").to_vec();
+        calldata.extend(pad_left(&[0x00]));
+        bytecode += &call_cheatcode(&calldata, "5f54");
+
+        runner
+            .interpret(_hex_string_to_bytes(&bytecode), None, true)
+            .unwrap();
+
+        // The original value is restored
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x0a]));
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x01]));
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x00]));
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x01]));
+        assert_eq!(runner.returndata.heap, pad_left(&[0x01]));
+        assert_eq!(runner.snapshots.len(), 1);
+
+        // Reverting to an unknown snapshot returns false and keeps the state
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let mut calldata = selector("revertTo(uint256)").to_vec();
+        calldata.extend(pad_left(&[0x05]));
+        let bytecode = String::from("601e5f55") + &call_cheatcode(&calldata, "5f54");
+        runner
+            .interpret(_hex_string_to_bytes(&bytecode), None, true)
+            .unwrap();
+
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x1e]));
+        assert_eq!(runner.returndata.heap, pad_left(&[0x00]));
+    }
+
+    #[test]
+    fn test_unknown_cheatcode() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        let calldata = selector("unknown()").to_vec();
+        let bytecode = _hex_string_to_bytes(&call_cheatcode(&calldata, ""));
+        runner.interpret(bytecode, None, true).unwrap();
+
+        // The call failed
+        assert_eq!(runner.stack.pop().unwrap(), [0u8; 32]);
+    }
+}
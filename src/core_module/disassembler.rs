@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+
+use super::op_codes::metadata::op_name;
+
+/* -------------------------------------------------------------------------- */
+/*                             Instruction struct                             */
+/* -------------------------------------------------------------------------- */
+
+/// Represents a single decoded instruction of a bytecode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    /// The offset of the instruction in the bytecode.
+    pub pc: usize,
+    /// The opcode of the instruction.
+    pub opcode: u8,
+    /// The PUSH data of the instruction (empty for other opcodes).
+    pub push_data: Vec<u8>,
+    /// Whether the PUSH data goes past the end of the bytecode.
+    pub truncated: bool,
+}
+
+/// Implementation of the Instruction struct.
+impl Instruction {
+    /// Returns the mnemonic of the instruction opcode.
+    pub fn name(&self) -> &'static str {
+        opcode_name(self.opcode)
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                           BytecodeAnalysis struct                          */
+/* -------------------------------------------------------------------------- */
+
+/// Represents a static profile of a bytecode.
+#[derive(Debug, PartialEq)]
+pub struct BytecodeAnalysis {
+    /// The size of the bytecode in bytes.
+    pub code_size: usize,
+    /// The number of instructions per opcode.
+    pub opcode_counts: BTreeMap<u8, usize>,
+    /// The number of JUMPDEST instructions (JUMPDEST bytes inside PUSH data are not counted).
+    pub jumpdests: usize,
+    /// Whether the last PUSH goes past the end of the bytecode.
+    pub truncated_push: bool,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Functions                                 */
+/* -------------------------------------------------------------------------- */
+
+/// Decodes a bytecode into its instructions, skipping the PUSH data.
+///
+/// # Arguments
+///
+/// * `bytecode` - The bytecode to decode.
+///
+/// # Returns
+///
+/// Returns the list of instructions in bytecode order.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::disassembler::disassemble;
+///
+/// // PUSH1 0x01 STOP
+/// let instructions = disassemble(&[0x60, 0x01, 0x00]);
+/// assert_eq!(instructions.len(), 2);
+/// assert_eq!(instructions[0].name(), "PUSH1");
+/// assert_eq!(instructions[0].push_data, vec![0x01]);
+/// assert_eq!(instructions[1].pc, 2);
+/// ```
+pub fn disassemble(bytecode: &[u8]) -> Vec<Instruction> {
+    scan_instructions(bytecode)
+        .into_iter()
+        .map(|(pc, opcode, operand_len)| {
+            let data_end = (pc + 1 + operand_len).min(bytecode.len());
+
+            Instruction {
+                pc,
+                opcode,
+                push_data: bytecode[pc + 1..data_end].to_vec(),
+                truncated: pc + 1 + operand_len > bytecode.len(),
+            }
+        })
+        .collect()
+}
+
+/// Walks a bytecode instruction by instruction, skipping the PUSH data, so that the bytes of
+/// a PUSH operand (e.g. `0x5b`) are never taken for instructions.
+///
+/// # Arguments
+///
+/// * `code` - The bytecode to walk.
+///
+/// # Returns
+///
+/// Returns the offset, opcode and operand length of each instruction, in bytecode order. The
+/// operand length of a truncated PUSH goes past the end of the bytecode.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::disassembler::scan_instructions;
+///
+/// // PUSH2 0x5b5b JUMPDEST
+/// let instructions = scan_instructions(&[0x61, 0x5b, 0x5b, 0x5b]);
+/// assert_eq!(instructions, vec![(0, 0x61, 2), (3, 0x5b, 0)]);
+/// ```
+pub fn scan_instructions(code: &[u8]) -> Vec<(usize, u8, usize)> {
+    let mut instructions = Vec::new();
+
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+
+        // The PUSH1 to PUSH32 operands
+        let operand_len = if (0x60..=0x7f).contains(&opcode) {
+            (opcode - 0x5f) as usize
+        } else {
+            0
+        };
+        instructions.push((pc, opcode, operand_len));
+
+        pc += 1 + operand_len;
+    }
+
+    instructions
+}
+
+/// Profiles a bytecode without executing it.
+///
+/// # Arguments
+///
+/// * `bytecode` - The bytecode to profile.
+///
+/// # Returns
+///
+/// Returns the code size, the count of each opcode, the number of JUMPDESTs and whether
+/// the bytecode ends with a truncated PUSH.
+pub fn analyze(bytecode: &[u8]) -> BytecodeAnalysis {
+    let instructions = disassemble(bytecode);
+
+    let mut opcode_counts = BTreeMap::new();
+    for instruction in &instructions {
+        *opcode_counts.entry(instruction.opcode).or_insert(0) += 1;
+    }
+
+    BytecodeAnalysis {
+        code_size: bytecode.len(),
+        jumpdests: opcode_counts.get(&0x5b).copied().unwrap_or(0),
+        opcode_counts,
+        truncated_push: instructions.iter().any(|instruction| instruction.truncated),
+    }
+}
+
+/// Returns the mnemonic of an opcode, or `INVALID` for unassigned opcodes.
+///
+/// # Arguments
+///
+/// * `opcode` - The opcode to name.
+pub fn opcode_name(opcode: u8) -> &'static str {
+    op_name(opcode).unwrap_or("INVALID")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_module::utils::bytes::_hex_string_to_bytes;
+
+    #[test]
+    fn test_disassemble() {
+        // PUSH2 0x5b5b JUMPDEST PUSH0 STOP
+        let instructions = disassemble(&_hex_string_to_bytes("615b5b5b5f00"));
+
+        let names: Vec<&str> = instructions.iter().map(|i| i.name()).collect();
+        assert_eq!(names, vec!["PUSH2", "JUMPDEST", "PUSH0", "STOP"]);
+        assert_eq!(instructions[0].push_data, vec![0x5b, 0x5b]);
+        assert_eq!(instructions[1].pc, 3);
+        assert!(instructions.iter().all(|i| !i.truncated));
+    }
+
+    #[test]
+    fn test_analyze() {
+        // PUSH1 0x01 PUSH1 0x02 ADD JUMPDEST PUSH1 0x5b JUMPDEST POP PUSH2 0xff (truncated)
+        let analysis = analyze(&_hex_string_to_bytes("60016002015b605b5b5061ff"));
+
+        assert_eq!(analysis.code_size, 12);
+        assert_eq!(analysis.opcode_counts[&0x60], 3);
+        assert_eq!(analysis.opcode_counts[&0x01], 1);
+        assert_eq!(analysis.opcode_counts[&0x5b], 2);
+        assert_eq!(analysis.opcode_counts[&0x50], 1);
+        assert_eq!(analysis.opcode_counts[&0x61], 1);
+        assert_eq!(analysis.opcode_counts.len(), 5);
+        assert_eq!(analysis.jumpdests, 2);
+        assert!(analysis.truncated_push);
+
+        assert!(!analyze(&_hex_string_to_bytes("6001")).truncated_push);
+    }
+
+    #[test]
+    fn test_push_data_jumpdest_bytes() {
+        use crate::core_module::runner::Runner;
+
+        // PUSH2 0x5b5b JUMPDEST STOP
+        let bytecode = _hex_string_to_bytes("615b5b5b00");
+
+        let pcs: Vec<usize> = scan_instructions(&bytecode)
+            .iter()
+            .map(|(pc, _, _)| *pc)
+            .collect();
+        assert_eq!(pcs, vec![0, 3, 4]);
+
+        let instructions = disassemble(&bytecode);
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[0].push_data, vec![0x5b, 0x5b]);
+        assert_eq!(instructions[1].name(), "JUMPDEST");
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.load_bytecode(bytecode);
+        assert_eq!(runner.jumpdests, vec![false, false, false, true, false]);
+    }
+
+    #[test]
+    fn test_opcode_name() {
+        assert_eq!(opcode_name(0x00), "STOP");
+        assert_eq!(opcode_name(0x7f), "PUSH32");
+        assert_eq!(opcode_name(0x9f), "SWAP16");
+        assert_eq!(opcode_name(0xfe), "INVALID");
+        assert_eq!(opcode_name(0x0c), "INVALID");
+    }
+}
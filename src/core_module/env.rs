@@ -0,0 +1,423 @@
+use super::utils::bytes::pad_left;
+use super::utils::primitives::U256;
+
+/* -------------------------------------------------------------------------- */
+/*                                Hardfork enum                               */
+/* -------------------------------------------------------------------------- */
+
+/// The Ethereum hardforks, in activation order.
+/// Comparing two hardforks tells which one is activated first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Hardfork {
+    Frontier,
+    Homestead,
+    /// EIP-150 gas repricing and all but one 64th call gas, named `EIP150` in the state tests.
+    TangerineWhistle,
+    /// EIP-160 EXP repricing and EIP-161 empty accounts, named `EIP158` in the state tests.
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    Paris,
+    Shanghai,
+    Cancun,
+}
+
+/// The default hardfork is the latest one supported by the emulator.
+impl Default for Hardfork {
+    fn default() -> Self {
+        Hardfork::Cancun
+    }
+}
+
+/// Implementation of the Hardfork enum.
+impl Hardfork {
+    /// Returns `true` if this hardfork includes the rules of `other`.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The hardfork to compare with.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::env::Hardfork;
+    ///
+    /// assert!(Hardfork::Cancun.is_enabled(Hardfork::London));
+    /// assert!(!Hardfork::Berlin.is_enabled(Hardfork::London));
+    /// ```
+    pub fn is_enabled(&self, other: Hardfork) -> bool {
+        *self >= other
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                             GasSchedule struct                             */
+/* -------------------------------------------------------------------------- */
+
+/// The gas costs of the opcodes whose price changed across hardforks.
+///
+/// Before Berlin, the costs are flat and the cold costs are zero. Since Berlin (EIP-2929), the
+/// flat costs are the warm access costs, and a cold slot or account costs the cold cost instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GasSchedule {
+    /// The cost of SLOAD (of a warm slot since Berlin).
+    pub sload: u64,
+    /// The cost of BALANCE (of a warm account since Berlin).
+    pub balance: u64,
+    /// The cost of EXTCODESIZE (of a warm account since Berlin).
+    pub extcodesize: u64,
+    /// The base cost of EXTCODECOPY (of a warm account since Berlin).
+    pub extcodecopy: u64,
+    /// The cost of EXTCODEHASH (of a warm account since Berlin).
+    pub extcodehash: u64,
+    /// The base cost of the CALL family (of a warm account since Berlin).
+    pub call: u64,
+    /// The base cost of SELFDESTRUCT.
+    pub selfdestruct: u64,
+    /// The cost of SLOAD on a cold slot, zero before Berlin.
+    pub cold_sload: u64,
+    /// The cost of accessing a cold account, zero before Berlin.
+    pub cold_account_access: u64,
+    /// The cost of EXP per byte of the exponent, raised by EIP-160.
+    pub exp_byte: u64,
+}
+
+/// Implementation of the GasSchedule struct.
+impl GasSchedule {
+    /// Returns the gas schedule of a hardfork.
+    ///
+    /// # Arguments
+    ///
+    /// * `hardfork` - The hardfork whose costs are returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::env::{GasSchedule, Hardfork};
+    ///
+    /// assert_eq!(GasSchedule::for_hardfork(Hardfork::Istanbul).sload, 800);
+    /// assert_eq!(GasSchedule::for_hardfork(Hardfork::Berlin).cold_sload, 2100);
+    /// ```
+    pub fn for_hardfork(hardfork: Hardfork) -> Self {
+        match hardfork {
+            Hardfork::Frontier | Hardfork::Homestead => Self {
+                sload: 50,
+                balance: 20,
+                extcodesize: 20,
+                extcodecopy: 20,
+                extcodehash: 400,
+                call: 40,
+                selfdestruct: 0,
+                cold_sload: 0,
+                cold_account_access: 0,
+                exp_byte: 10,
+            },
+            // EIP-150 repricing
+            Hardfork::TangerineWhistle => Self {
+                sload: 200,
+                balance: 400,
+                extcodesize: 700,
+                extcodecopy: 700,
+                extcodehash: 400,
+                call: 700,
+                selfdestruct: 5000,
+                cold_sload: 0,
+                cold_account_access: 0,
+                exp_byte: 10,
+            },
+            // EIP-160 EXP repricing
+            Hardfork::SpuriousDragon
+            | Hardfork::Byzantium
+            | Hardfork::Constantinople
+            | Hardfork::Petersburg => Self {
+                sload: 200,
+                balance: 400,
+                extcodesize: 700,
+                extcodecopy: 700,
+                extcodehash: 400,
+                call: 700,
+                selfdestruct: 5000,
+                cold_sload: 0,
+                cold_account_access: 0,
+                exp_byte: 50,
+            },
+            // EIP-1884 repricing
+            Hardfork::Istanbul => Self {
+                sload: 800,
+                balance: 700,
+                extcodesize: 700,
+                extcodecopy: 700,
+                extcodehash: 700,
+                call: 700,
+                selfdestruct: 5000,
+                cold_sload: 0,
+                cold_account_access: 0,
+                exp_byte: 50,
+            },
+            // EIP-2929 warm and cold accesses
+            Hardfork::Berlin
+            | Hardfork::London
+            | Hardfork::Paris
+            | Hardfork::Shanghai
+            | Hardfork::Cancun => Self {
+                sload: 100,
+                balance: 100,
+                extcodesize: 100,
+                extcodecopy: 100,
+                extcodehash: 100,
+                call: 100,
+                selfdestruct: 5000,
+                cold_sload: 2100,
+                cold_account_access: 2600,
+                exp_byte: 50,
+            },
+        }
+    }
+
+    /// Returns the cost of an SLOAD.
+    ///
+    /// # Arguments
+    ///
+    /// * `cold` - Whether the slot is accessed for the first time.
+    pub fn sload_cost(&self, cold: bool) -> u64 {
+        if cold && self.cold_sload > 0 {
+            self.cold_sload
+        } else {
+            self.sload
+        }
+    }
+
+    /// Returns the per-word cost of the copy opcodes: 3 gas per (started) 32-byte word.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The number of bytes copied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::env::{GasSchedule, Hardfork};
+    ///
+    /// let schedule = GasSchedule::for_hardfork(Hardfork::Cancun);
+    /// assert_eq!(schedule.copy_cost(0), 0);
+    /// assert_eq!(schedule.copy_cost(33), 6);
+    /// ```
+    pub fn copy_cost(&self, size: usize) -> u64 {
+        3 * size.div_ceil(32) as u64
+    }
+
+    /// Returns the cost of an opcode accessing an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `cost` - The flat (or warm) cost of the opcode.
+    /// * `cold` - Whether the account is accessed for the first time.
+    pub fn account_access_cost(&self, cost: u64, cold: bool) -> u64 {
+        if cold && self.cold_account_access > 0 {
+            self.cold_account_access
+        } else {
+            cost
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                               BlockEnv struct                              */
+/* -------------------------------------------------------------------------- */
+
+/// The block environment read by the block opcodes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockEnv {
+    /// The chain id returned by CHAINID.
+    pub chain_id: u64,
+    /// The block number returned by NUMBER.
+    pub number: U256,
+    /// The block timestamp returned by TIMESTAMP, the current time if `None`.
+    pub timestamp: Option<U256>,
+    /// The base fee per gas returned by BASEFEE.
+    pub basefee: u64,
+    /// The block beneficiary returned by COINBASE, which receives the priority fees.
+    pub coinbase: [u8; 20],
+    /// The block difficulty returned by DIFFICULTY before Paris.
+    pub difficulty: u64,
+    /// The beacon chain randomness returned by PREVRANDAO since Paris (EIP-4399).
+    pub prevrandao: [u8; 32],
+}
+
+/// The default block environment is on mainnet.
+impl Default for BlockEnv {
+    fn default() -> Self {
+        Self {
+            chain_id: 1,
+            number: U256::from(0xffffffffu64),
+            timestamp: None,
+            basefee: 10,
+            coinbase: [0xc0; 20],
+            // The difficulty of the mainnet genesis block
+            difficulty: 0x400000000,
+            prevrandao: pad_left(&[0x45; 8]),
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                TxEnv struct                                */
+/* -------------------------------------------------------------------------- */
+
+/// The EIP-1559 fee parameters of the transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxEnv {
+    /// The maximum total fee per gas the sender is willing to pay.
+    pub max_fee_per_gas: u64,
+    /// The maximum fee per gas paid to the block beneficiary on top of the base fee.
+    pub max_priority_fee_per_gas: u64,
+}
+
+/// The default transaction pays the default base fee without any tip.
+impl Default for TxEnv {
+    fn default() -> Self {
+        Self {
+            max_fee_per_gas: 10,
+            max_priority_fee_per_gas: 0,
+        }
+    }
+}
+
+/// Implementation of the TxEnv struct.
+impl TxEnv {
+    /// Returns the gas price actually paid by the transaction (read by GASPRICE):
+    /// `min(max_fee_per_gas, basefee + max_priority_fee_per_gas)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `basefee` - The base fee per gas of the block.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::env::TxEnv;
+    ///
+    /// let tx = TxEnv { max_fee_per_gas: 150, max_priority_fee_per_gas: 20 };
+    /// assert_eq!(tx.effective_gas_price(100), 120);
+    /// assert_eq!(tx.effective_gas_price(140), 150);
+    /// ```
+    pub fn effective_gas_price(&self, basefee: u64) -> u64 {
+        self.max_fee_per_gas
+            .min(basefee.saturating_add(self.max_priority_fee_per_gas))
+    }
+
+    /// Returns the fee per gas paid to the block beneficiary, i.e. the part of the effective
+    /// gas price above the base fee.
+    ///
+    /// # Arguments
+    ///
+    /// * `basefee` - The base fee per gas of the block.
+    pub fn priority_fee_per_gas(&self, basefee: u64) -> u64 {
+        self.effective_gas_price(basefee).saturating_sub(basefee)
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                               EvmConfig struct                             */
+/* -------------------------------------------------------------------------- */
+
+/// The environment defaults of a run, passed once to `Runner::with_config`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvmConfig {
+    /// The block environment.
+    pub block: BlockEnv,
+    /// The transaction fee parameters.
+    pub tx: TxEnv,
+    /// The hardfork whose rules are followed.
+    pub hardfork: Hardfork,
+    /// The gas available to the top-level call.
+    pub gas_limit: u64,
+    /// The RPC url of the chain to fork, if any.
+    pub fork_url: Option<String>,
+}
+
+/// The default config matches the defaults of `Runner::new`.
+impl Default for EvmConfig {
+    fn default() -> Self {
+        Self {
+            block: BlockEnv::default(),
+            tx: TxEnv::default(),
+            hardfork: Hardfork::default(),
+            gas_limit: 30_000_000,
+            fork_url: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hardfork_order() {
+        assert!(Hardfork::Frontier < Hardfork::Homestead);
+        assert!(Hardfork::Homestead < Hardfork::TangerineWhistle);
+        assert!(Hardfork::SpuriousDragon < Hardfork::Byzantium);
+        assert!(Hardfork::Berlin < Hardfork::London);
+        assert!(Hardfork::Shanghai < Hardfork::Cancun);
+        assert_eq!(Hardfork::default(), Hardfork::Cancun);
+
+        assert!(Hardfork::London.is_enabled(Hardfork::London));
+        assert!(!Hardfork::London.is_enabled(Hardfork::Paris));
+    }
+
+    #[test]
+    fn test_gas_schedule() {
+        let istanbul = GasSchedule::for_hardfork(Hardfork::Istanbul);
+        let berlin = GasSchedule::for_hardfork(Hardfork::Berlin);
+
+        // Flat costs before Berlin
+        assert_eq!(istanbul.sload_cost(true), 800);
+        assert_eq!(istanbul.sload_cost(false), 800);
+        assert_eq!(istanbul.account_access_cost(istanbul.balance, true), 700);
+
+        // Warm and cold costs since Berlin
+        assert_eq!(berlin.sload_cost(true), 2100);
+        assert_eq!(berlin.sload_cost(false), 100);
+        assert_eq!(berlin.account_access_cost(berlin.balance, true), 2600);
+        assert_eq!(berlin.account_access_cost(berlin.balance, false), 100);
+
+        assert_eq!(GasSchedule::for_hardfork(Hardfork::Homestead).sload, 50);
+        assert_eq!(GasSchedule::for_hardfork(Hardfork::Byzantium).sload, 200);
+
+        // EIP-150 reprices the state accesses before EIP-160 reprices EXP
+        let tangerine_whistle = GasSchedule::for_hardfork(Hardfork::TangerineWhistle);
+        let spurious_dragon = GasSchedule::for_hardfork(Hardfork::SpuriousDragon);
+        assert_eq!(
+            (tangerine_whistle.call, tangerine_whistle.exp_byte),
+            (700, 10)
+        );
+        assert_eq!((spurious_dragon.call, spurious_dragon.exp_byte), (700, 50));
+        assert_eq!(
+            spurious_dragon,
+            GasSchedule::for_hardfork(Hardfork::Byzantium)
+        );
+        assert_eq!(
+            GasSchedule::for_hardfork(Hardfork::Cancun),
+            GasSchedule::for_hardfork(Hardfork::Berlin)
+        );
+    }
+
+    #[test]
+    fn test_priority_fee_per_gas() {
+        let tx = TxEnv {
+            max_fee_per_gas: 150,
+            max_priority_fee_per_gas: 20,
+        };
+
+        assert_eq!(tx.priority_fee_per_gas(100), 20);
+        // Capped by the max fee
+        assert_eq!(tx.priority_fee_per_gas(140), 10);
+        // Below the base fee, nothing is left for the coinbase
+        assert_eq!(tx.priority_fee_per_gas(200), 0);
+    }
+}
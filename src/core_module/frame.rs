@@ -0,0 +1,312 @@
+use super::utils::debug::to_hex_address;
+use super::utils::primitives::U256;
+
+use super::memory::Memory;
+use super::stack::Stack;
+use super::state::StateCheckpoint;
+
+use ethers::abi::Abi;
+
+/* -------------------------------------------------------------------------- */
+/*                                CallKind enum                               */
+/* -------------------------------------------------------------------------- */
+
+/// The kind of a call, which tells how its result is handed back to the caller.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallKind {
+    /// A call made with `Runner::call`, whose result is returned to the Rust caller.
+    External { delegate: bool },
+    /// A CALL opcode, writing the return data at the given memory range.
+    Call {
+        returndata_offset: U256,
+        returndata_size: U256,
+    },
+    /// A STATICCALL opcode, writing the return data at the given memory range.
+    StaticCall {
+        returndata_offset: U256,
+        returndata_size: U256,
+    },
+    /// A DELEGATECALL opcode, writing the return data at the given memory range.
+    DelegateCall {
+        returndata_offset: U256,
+        returndata_size: U256,
+    },
+    /// A CREATE opcode running the constructor of the contract at `address`.
+    Create { address: [u8; 20], value: [u8; 32] },
+    /// A CREATE2 opcode running the constructor of the contract at `address`.
+    Create2 { address: [u8; 20], value: [u8; 32] },
+}
+
+/// Implementation of the CallKind enum.
+impl CallKind {
+    /// Returns `true` if the callee runs in the context of the caller.
+    pub fn is_delegate(&self) -> bool {
+        matches!(
+            self,
+            CallKind::External { delegate: true } | CallKind::DelegateCall { .. }
+        )
+    }
+
+    /// Returns `true` if the callee can't modify the state.
+    pub fn is_static(&self) -> bool {
+        matches!(self, CallKind::StaticCall { .. })
+    }
+
+    /// Returns the type of the call, as shown in a call tree.
+    pub fn call_type(&self) -> CallType {
+        match self {
+            CallKind::External { delegate: false } | CallKind::Call { .. } => CallType::Call,
+            CallKind::External { delegate: true } | CallKind::DelegateCall { .. } => {
+                CallType::DelegateCall
+            }
+            CallKind::StaticCall { .. } => CallType::StaticCall,
+            CallKind::Create { .. } => CallType::Create,
+            CallKind::Create2 { .. } => CallType::Create2,
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                CallType enum                               */
+/* -------------------------------------------------------------------------- */
+
+/// The opcode family of a call in a call tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallType {
+    Call,
+    DelegateCall,
+    StaticCall,
+    Create,
+    Create2,
+}
+
+/* -------------------------------------------------------------------------- */
+/*                               CallNode struct                              */
+/* -------------------------------------------------------------------------- */
+
+/// A call of the call tree built during an execution, with the calls it made as children.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallNode {
+    /// The type of the call.
+    pub call_type: CallType,
+    /// The called address (the code address for a DELEGATECALL, the new contract for a CREATE).
+    pub address: [u8; 20],
+    /// The call data, or the init code for a CREATE.
+    pub input: Vec<u8>,
+    /// The return data (the runtime code for a CREATE), or the revert data if the call failed.
+    pub output: Vec<u8>,
+    /// Whether the call succeeded, and for a CREATE whether the runtime code was deployed.
+    pub success: bool,
+    /// The gas consumed by the call and its children.
+    pub gas_used: u64,
+    /// The calls made by this call, in execution order.
+    pub children: Vec<CallNode>,
+}
+
+/// Implementation of the CallNode struct.
+impl CallNode {
+    /// Returns the runtime code deployed by a successful CREATE or CREATE2, or `None` for the
+    /// other calls.
+    pub fn deployed_code(&self) -> Option<&[u8]> {
+        match self.call_type {
+            CallType::Create | CallType::Create2 if self.success => Some(&self.output),
+            _ => None,
+        }
+    }
+
+    /// Returns the label of the called function: its signature if the ABI has a function
+    /// with the selector of the call data, or the selector itself otherwise.
+    /// Creations and calls without a selector have no label.
+    ///
+    /// # Arguments
+    ///
+    /// * `abi` - The ABI of the called contracts, if known.
+    pub fn label(&self, abi: Option<&Abi>) -> Option<String> {
+        if matches!(self.call_type, CallType::Create | CallType::Create2) {
+            return None;
+        }
+
+        let selector = decode_selector(&self.input)?;
+        abi.and_then(|abi| function_signature(abi, selector))
+            .or_else(|| Some(format!("0x{}", hex::encode(selector))))
+    }
+
+    /// Formats the call and its children as an indented tree, one call per line, e.g.
+    /// `CALL 0x... transfer(address,uint256) ✅`.
+    ///
+    /// # Arguments
+    ///
+    /// * `abi` - The ABI used to label the calls, if known.
+    pub fn format_tree(&self, abi: Option<&Abi>) -> String {
+        let mut tree = String::new();
+        self.write_tree(abi, 0, &mut tree);
+        tree
+    }
+
+    fn write_tree(&self, abi: Option<&Abi>, depth: usize, tree: &mut String) {
+        let call_type = match self.call_type {
+            CallType::Call => "CALL",
+            CallType::DelegateCall => "DELEGATECALL",
+            CallType::StaticCall => "STATICCALL",
+            CallType::Create => "CREATE",
+            CallType::Create2 => "CREATE2",
+        };
+
+        tree.push_str(&"  ".repeat(depth));
+        tree.push_str(&format!("{} {}", call_type, to_hex_address(self.address)));
+        if let Some(label) = self.label(abi) {
+            tree.push_str(&format!(" {}", label));
+        }
+        tree.push_str(if self.success { " ✅\n" } else { " ❌\n" });
+
+        for child in &self.children {
+            child.write_tree(abi, depth + 1, tree);
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Selectors                                 */
+/* -------------------------------------------------------------------------- */
+
+/// Returns the 4-byte function selector of a call data, or `None` if the call data is
+/// shorter than 4 bytes.
+///
+/// # Arguments
+///
+/// * `calldata` - The call data.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::decode_selector;
+///
+/// assert_eq!(decode_selector(&[0xa9, 0x05, 0x9c, 0xbb, 0x00]), Some([0xa9, 0x05, 0x9c, 0xbb]));
+/// assert_eq!(decode_selector(&[0xa9, 0x05]), None);
+/// ```
+pub fn decode_selector(calldata: &[u8]) -> Option<[u8; 4]> {
+    calldata.get(..4)?.try_into().ok()
+}
+
+/// Returns the signature of the ABI function with the given selector, e.g.
+/// `transfer(address,uint256)`, or `None` if the ABI has no such function.
+///
+/// # Arguments
+///
+/// * `abi` - The ABI to search.
+/// * `selector` - The function selector.
+///
+/// # Examples
+///
+/// ```
+/// use ethers::abi::AbiParser;
+/// use evm_rs_emulator::function_signature;
+///
+/// let abi = AbiParser::default()
+///     .parse(&["function transfer(address to, uint256 amount) returns (bool)"])
+///     .unwrap();
+///
+/// assert_eq!(
+///     function_signature(&abi, [0xa9, 0x05, 0x9c, 0xbb]),
+///     Some("transfer(address,uint256)".to_string())
+/// );
+/// ```
+pub fn function_signature(abi: &Abi, selector: [u8; 4]) -> Option<String> {
+    abi.functions()
+        .find(|function| function.short_signature() == selector)
+        .map(|function| {
+            let inputs: Vec<String> = function
+                .inputs
+                .iter()
+                .map(|input| input.kind.to_string())
+                .collect();
+            format!("{}({})", function.name, inputs.join(","))
+        })
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                Frame struct                                */
+/* -------------------------------------------------------------------------- */
+
+/// The execution context of a caller, saved while its callee runs.
+///
+/// Calls don't recurse on the native stack: the caller context is pushed on the runner
+/// `frames` and the interpreter loop carries on with the callee code, so the call depth is
+/// only bounded by the EVM limit.
+#[derive(Debug)]
+pub struct Frame {
+    /// The kind of the call made by this caller.
+    pub kind: CallKind,
+    /// The call tree node of the call made by this caller, completed once it returns.
+    pub call: CallNode,
+    /// The gas forwarded to the callee.
+    pub gas_at_call: u64,
+    /// The gas withheld from the callee, given back to the caller once it returns.
+    pub gas_reserved: u64,
+    /// The gas refund counter to revert to if the call fails.
+    pub refund_checkpoint: u64,
+    /// The length of the execution trace when the call was made.
+    pub trace_start: usize,
+    /// The transient storage checkpoint to revert to if the call fails.
+    pub transient_checkpoint: usize,
+    /// The accounts and logs to revert to if the call fails.
+    pub state_checkpoint: StateCheckpoint,
+    pub caller: [u8; 20],
+    pub callvalue: [u8; 32],
+    pub address: [u8; 20],
+    pub calldata: Memory,
+    pub returndata: Memory,
+    pub memory: Memory,
+    pub stack: Stack,
+    pub pc: usize,
+    pub debug_level: Option<u8>,
+    pub static_mode: bool,
+    pub bytecode: Vec<u8>,
+    pub jumpdests: Vec<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_module::asm::assemble;
+    use crate::core_module::runner::Runner;
+    use crate::core_module::utils::environment::init_account;
+    use ethers::abi::AbiParser;
+
+    #[test]
+    fn test_label_transfer_call() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let token = [0xb2; 20];
+        init_account(token, &mut runner).unwrap();
+        runner.state.put_code_at(token, vec![0x00]).unwrap();
+
+        // CALL transfer(0xcc..cc, 100) on the token
+        let bytecode = assemble(&format!(
+            "PUSH4 0xa9059cbb\nPUSH1 0xe0\nSHL\nPUSH0\nMSTORE\n\
+             PUSH20 0x{}\nPUSH1 0x04\nMSTORE\n\
+             PUSH1 0x64\nPUSH1 0x24\nMSTORE\n\
+             PUSH0\nPUSH0\nPUSH1 0x44\nPUSH0\nPUSH0\nPUSH20 0x{}\nGAS\nCALL",
+            hex::encode([0xcc; 20]),
+            hex::encode(token)
+        ))
+        .unwrap();
+        runner.interpret(bytecode, None, true).unwrap();
+
+        let abi = AbiParser::default()
+            .parse(&["function transfer(address to, uint256 amount) returns (bool)"])
+            .unwrap();
+        let call = &runner.call_tree[0];
+
+        assert_eq!(decode_selector(&call.input), Some([0xa9, 0x05, 0x9c, 0xbb]));
+        assert_eq!(
+            call.format_tree(Some(&abi)),
+            format!(
+                "CALL {} transfer(address,uint256) ✅\n",
+                to_hex_address(token)
+            )
+        );
+
+        // Without ABI, the selector is shown
+        assert_eq!(call.label(None), Some("0xa9059cbb".to_string()));
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::{HashSet, VecDeque};
+
+use super::op_codes::metadata::stack_io;
+use super::runner::Runner;
+use super::state::EvmState;
+use super::utils::bytes::{bytes32_to_address, word_to_usize};
+
+/* -------------------------------------------------------------------------- */
+/*                              StateChange enum                              */
+/* -------------------------------------------------------------------------- */
+
+/// The state value overwritten by a step, depending on the executed opcode.
+#[derive(Debug, Clone)]
+pub enum StateChange {
+    /// The opcode doesn't change the state.
+    None,
+    /// SSTORE: the previous value of the storage slot (`None` if it was unset), and whether
+    /// the slot was cold (EIP-2929).
+    Storage {
+        address: [u8; 20],
+        slot: [u8; 32],
+        previous: Option<[u8; 32]>,
+        cold: bool,
+    },
+    /// TSTORE: the previous value of the transient storage slot (`None` if it was unset).
+    TransientStorage {
+        address: [u8; 20],
+        slot: [u8; 32],
+        previous: Option<[u8; 32]>,
+    },
+    /// LOG0 to LOG4: the number of logs before the step.
+    Logs(usize),
+    /// SLOAD: the slot accessed by the step, if it was cold (EIP-2929).
+    StorageAccess(Option<([u8; 20], [u8; 32])>),
+    /// BALANCE and EXTCODE* family: the account accessed by the step, if it was cold (EIP-2929).
+    AccountAccess(Option<[u8; 20]>),
+    /// CALL, CREATE and SELFDESTRUCT families: the whole state and access sets before the step.
+    Snapshot {
+        state: Box<EvmState>,
+        accessed_addresses: HashSet<[u8; 20]>,
+        accessed_storage_keys: HashSet<([u8; 20], [u8; 32])>,
+    },
+}
+
+/* -------------------------------------------------------------------------- */
+/*                               StepDiff struct                              */
+/* -------------------------------------------------------------------------- */
+
+/// The reversible changes made by one executed opcode.
+#[derive(Debug, Clone)]
+pub struct StepDiff {
+    /// The program counter before the step.
+    pub pc: usize,
+    /// The remaining gas before the step.
+    pub gas: u64,
+    /// The memory expansion and opcode gas spent before the step.
+    pub memory_gas: u64,
+    pub execution_gas: u64,
+    /// The number of bottom stack items the step can't touch.
+    pub stack_kept: usize,
+    /// The stack items above `stack_kept` before the step, i.e. the inputs of the opcode.
+    pub stack_removed: Vec<[u8; 32]>,
+    /// The memory size before the step.
+    pub memory_len: usize,
+    /// The offset of the memory bytes the step may overwrite.
+    pub memory_offset: usize,
+    /// The memory bytes the step may overwrite, within the memory size before the step.
+    pub memory_overwritten: Vec<u8>,
+    /// The return data before the step, for the opcodes that replace it.
+    pub returndata: Option<Vec<u8>>,
+    /// The state value overwritten by the step.
+    pub state: StateChange,
+}
+
+/// Implementation of the StepDiff struct.
+impl StepDiff {
+    /// Records everything the opcode about to run at the runner pc may change: its stack
+    /// inputs and the memory range it writes, read from its stack inputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `runner` - The runner before the step.
+    /// * `opcode` - The opcode about to be executed.
+    pub(crate) fn before(runner: &Runner, opcode: u8) -> Self {
+        // Peek the slot written by SSTORE and TSTORE (or the slot or account accessed)
+        let slot = runner.stack.as_slice().last().copied().unwrap_or([0u8; 32]);
+
+        let state = match opcode {
+            0x55 => StateChange::Storage {
+                address: runner.address,
+                slot,
+                previous: runner
+                    .state
+                    .accounts
+                    .get(&runner.address)
+                    .and_then(|account| account.storage.get(&slot).copied()),
+                cold: !runner
+                    .accessed_storage_keys
+                    .contains(&(runner.address, slot)),
+            },
+            0x5d => StateChange::TransientStorage {
+                address: runner.address,
+                slot,
+                previous: runner
+                    .state
+                    .transient_storage
+                    .get(&(runner.address, slot))
+                    .copied(),
+            },
+            0xa0..=0xa4 => StateChange::Logs(runner.state.logs.len()),
+            0x54 => StateChange::StorageAccess(
+                Some((runner.address, slot))
+                    .filter(|key| !runner.accessed_storage_keys.contains(key)),
+            ),
+            0x31 | 0x3b | 0x3c | 0x3f => StateChange::AccountAccess(
+                Some(bytes32_to_address(&slot))
+                    .filter(|address| !runner.accessed_addresses.contains(address)),
+            ),
+            0xf0 | 0xf1 | 0xf2 | 0xf4 | 0xf5 | 0xfa | 0xff => StateChange::Snapshot {
+                state: Box::new(runner.state.clone()),
+                accessed_addresses: runner.accessed_addresses.clone(),
+                accessed_storage_keys: runner.accessed_storage_keys.clone(),
+            },
+            _ => StateChange::None,
+        };
+
+        let returndata = match opcode {
+            0xf0..=0xf5 | 0xfa | 0xfd => Some(runner.returndata.heap.clone()),
+            _ => None,
+        };
+
+        // An opcode only pops or overwrites its stack inputs
+        let stack = runner.stack.as_slice();
+        let stack_in = stack_io(opcode).map_or(0, |(stack_in, _)| stack_in);
+        let stack_kept = stack.len().saturating_sub(stack_in);
+
+        // The memory past its size before the step is truncated on undo
+        let (offset, size) = memory_written(opcode, stack);
+        let memory_len = runner.memory.heap.len();
+        let start = offset.min(memory_len);
+        let end = offset.saturating_add(size).min(memory_len);
+
+        Self {
+            pc: runner.pc,
+            gas: runner.gas,
+            memory_gas: runner.memory_gas,
+            execution_gas: runner.execution_gas,
+            stack_kept,
+            stack_removed: stack[stack_kept..].to_vec(),
+            memory_len,
+            memory_offset: start,
+            memory_overwritten: runner.memory.heap[start..end].to_vec(),
+            returndata,
+            state,
+        }
+    }
+
+    /// Restores the runner to its state before the step.
+    ///
+    /// # Arguments
+    ///
+    /// * `runner` - The runner after the step.
+    pub(crate) fn undo(self, runner: &mut Runner) {
+        runner.pc = self.pc;
+        runner.gas = self.gas;
+        runner.memory_gas = self.memory_gas;
+        runner.execution_gas = self.execution_gas;
+
+        // Restore the stack
+        runner.stack.stack.truncate(self.stack_kept);
+        runner.stack.stack.extend(self.stack_removed);
+
+        // Restore the memory
+        runner.memory.heap.truncate(self.memory_len);
+        let end = self.memory_offset + self.memory_overwritten.len();
+        runner.memory.heap[self.memory_offset..end].copy_from_slice(&self.memory_overwritten);
+
+        if let Some(returndata) = self.returndata {
+            runner.returndata.heap = returndata;
+        }
+
+        // Restore the state
+        match self.state {
+            StateChange::None => {}
+            StateChange::Storage {
+                address,
+                slot,
+                previous,
+                cold,
+            } => {
+                if let Some(account) = runner.state.accounts.get_mut(&address) {
+                    match previous {
+                        Some(value) => account.storage.insert(slot, value),
+                        None => account.storage.remove(&slot),
+                    };
+                }
+                if cold {
+                    runner.accessed_storage_keys.remove(&(address, slot));
+                }
+            }
+            StateChange::TransientStorage {
+                address,
+                slot,
+                previous,
+            } => {
+                match previous {
+                    Some(value) => runner
+                        .state
+                        .transient_storage
+                        .insert((address, slot), value),
+                    None => runner.state.transient_storage.remove(&(address, slot)),
+                };
+            }
+            StateChange::Logs(len) => runner.state.logs.truncate(len),
+            StateChange::StorageAccess(cold) => {
+                if let Some(key) = cold {
+                    runner.accessed_storage_keys.remove(&key);
+                }
+            }
+            StateChange::AccountAccess(cold) => {
+                if let Some(address) = cold {
+                    runner.accessed_addresses.remove(&address);
+                }
+            }
+            StateChange::Snapshot {
+                state,
+                accessed_addresses,
+                accessed_storage_keys,
+            } => {
+                runner.state = *state;
+                runner.accessed_addresses = accessed_addresses;
+                runner.accessed_storage_keys = accessed_storage_keys;
+            }
+        }
+    }
+}
+
+/// Returns the memory range an opcode writes, read from its stack inputs. The range is empty
+/// for the opcodes that don't write memory, and ends at `usize::MAX` if it is out of bounds.
+///
+/// # Arguments
+///
+/// * `opcode` - The opcode about to be executed.
+/// * `stack` - The stack before the step.
+fn memory_written(opcode: u8, stack: &[[u8; 32]]) -> (usize, usize) {
+    // The stack input at the given depth, 0 being the top
+    let input = |depth: usize| {
+        stack
+            .len()
+            .checked_sub(depth + 1)
+            .and_then(|index| word_to_usize(&stack[index]).ok())
+            .unwrap_or(usize::MAX)
+    };
+
+    match opcode {
+        // MSTORE, MSTORE8
+        0x52 => (input(0), 32),
+        0x53 => (input(0), 1),
+        // CALLDATACOPY, CODECOPY, RETURNDATACOPY, MCOPY
+        0x37 | 0x39 | 0x3e | 0x5e => (input(0), input(2)),
+        // EXTCODECOPY
+        0x3c => (input(1), input(3)),
+        // CALL, CALLCODE and DELEGATECALL, STATICCALL return data
+        0xf1 | 0xf2 => (input(5), input(6)),
+        0xf4 | 0xfa => (input(4), input(5)),
+        _ => (0, 0),
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                             StepJournal struct                             */
+/* -------------------------------------------------------------------------- */
+
+/// A bounded ring buffer of step diffs. Once full, the oldest diff is dropped.
+#[derive(Debug)]
+pub struct StepJournal {
+    capacity: usize,
+    diffs: VecDeque<StepDiff>,
+}
+
+/// Implementation of the StepJournal struct.
+impl StepJournal {
+    /// Creates an empty journal keeping at most `capacity` diffs.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            diffs: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Records the diff of the last executed step, dropping the oldest one if the journal is full.
+    pub fn push(&mut self, diff: StepDiff) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.diffs.len() == self.capacity {
+            self.diffs.pop_front();
+        }
+        self.diffs.push_back(diff);
+    }
+
+    /// Removes and returns the diff of the last executed step.
+    pub fn pop(&mut self) -> Option<StepDiff> {
+        self.diffs.pop_back()
+    }
+
+    /// Drops every recorded diff.
+    pub fn clear(&mut self) {
+        self.diffs.clear();
+    }
+
+    /// Returns the number of steps that can be undone.
+    pub fn len(&self) -> usize {
+        self.diffs.len()
+    }
+
+    /// Returns `true` if no step can be undone.
+    pub fn is_empty(&self) -> bool {
+        self.diffs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_capacity() {
+        let runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let mut journal = StepJournal::new(2);
+
+        for pc in 0..3 {
+            let mut diff = StepDiff::before(&runner, 0x00);
+            diff.pc = pc;
+            journal.push(diff);
+        }
+
+        // The oldest diff was dropped
+        assert_eq!(journal.len(), 2);
+        assert_eq!(journal.pop().unwrap().pc, 2);
+        assert_eq!(journal.pop().unwrap().pc, 1);
+        assert!(journal.pop().is_none());
+        assert!(journal.is_empty());
+    }
+}
@@ -1,10 +1,41 @@
 use super::utils::errors::ExecutionError;
-use std::ptr;
+
+/// The maximum size of the memory (32 MiB). Expanding the memory to this size would cost
+/// billions of gas, so a larger access can never be paid for and runs out of gas.
+pub const MAX_MEMORY_SIZE: usize = 32 * 1024 * 1024;
+
+/// Returns the gas cost of expanding the memory from `current_size` to `new_size` bytes.
+/// A memory of `w` words costs `3 * w + w * w / 512`, the expansion pays the difference.
+///
+/// # Arguments
+///
+/// * `current_size` - The current size of the memory, in bytes.
+/// * `new_size` - The size of the memory after the expansion, in bytes.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::memory_expansion_cost;
+///
+/// assert_eq!(memory_expansion_cost(0, 32), 3);
+/// assert_eq!(memory_expansion_cost(0, 1024), 98);
+/// assert_eq!(memory_expansion_cost(64, 32), 0);
+/// ```
+pub fn memory_expansion_cost(current_size: usize, new_size: usize) -> u64 {
+    let cost = |size: usize| {
+        let words = size.div_ceil(32) as u64;
+        3 * words + words * words / 512
+    };
+
+    cost(new_size).saturating_sub(cost(current_size))
+}
 
 /// Represents the memory of the EVM.
 #[derive(Debug)]
 pub struct Memory {
     pub heap: Vec<u8>,
+    /// The maximum size reached by the memory
+    pub max_size: usize,
 }
 
 impl Memory {
@@ -18,12 +49,15 @@ impl Memory {
     ///
     /// A new instance of `Memory`.
     pub fn new(data: Option<Vec<u8>>) -> Self {
+        let heap = if data.is_some() {
+            data.unwrap()
+        } else {
+            vec![0; 0]
+        };
+
         Self {
-            heap: if data.is_some() {
-                data.unwrap()
-            } else {
-                vec![0; 0]
-            },
+            max_size: heap.len(),
+            heap,
         }
     }
 
@@ -34,9 +68,26 @@ impl Memory {
     /// * `size` - The size to extend the memory by.
     pub fn extend(&mut self, size: usize) {
         self.heap.extend(vec![0; size]);
+
+        if self.heap.len() > self.max_size {
+            self.max_size = self.heap.len();
+        }
+    }
+
+    /// Returns the end of an access to the memory, checked against `MAX_MEMORY_SIZE`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError::OutOfGas` if the access ends beyond `MAX_MEMORY_SIZE`.
+    fn checked_end(address: usize, size: usize) -> Result<usize, ExecutionError> {
+        address
+            .checked_add(size)
+            .filter(|end| *end <= MAX_MEMORY_SIZE)
+            .ok_or(ExecutionError::OutOfGas)
     }
 
     /// Reads bytes from memory starting at the specified address.
+    /// A read of zero bytes doesn't expand the memory.
     ///
     /// # Arguments
     ///
@@ -46,25 +97,18 @@ impl Memory {
     /// # Returns
     ///
     /// A `Result` containing the bytes read or an `ExecutionError` if the read operation failed.
-    pub unsafe fn read(&mut self, address: usize, size: usize) -> Result<Vec<u8>, ExecutionError> {
-        // Increase memory heap to the nearest multiple of 32 if address is out of bounds
-        if address + size > self.heap.len() {
-            // Calculate the nearest multiple of 32
-            let nearest_multiple = if address % 32 == 0 {
-                address + 32
-            } else {
-                (address + 32) + (32 - (address + 32) % 32)
-            };
-
-            // Extend memory heap
-            self.extend(nearest_multiple - self.heap.len());
+    pub fn read(&mut self, address: usize, size: usize) -> Result<Vec<u8>, ExecutionError> {
+        if size == 0 {
+            return Ok(Vec::new());
         }
 
-        let ptr = self.heap.as_ptr().add(address);
-        let mut data = vec![0; size];
-        ptr::copy(ptr, data.as_mut_ptr(), size);
+        // Increase memory heap to the nearest multiple of 32 if the read is out of bounds
+        let end = Self::checked_end(address, size)?;
+        if end > self.heap.len() {
+            self.extend(end.div_ceil(32) * 32 - self.heap.len());
+        }
 
-        Ok(data)
+        Ok(self.heap[address..end].to_vec())
     }
 
     /// Writes bytes to memory starting at the specified address.
@@ -77,7 +121,12 @@ impl Memory {
     /// # Returns
     ///
     /// A `Result` indicating whether the write operation was successful or an `ExecutionError` if it failed.
-    pub unsafe fn write(&mut self, address: usize, data: Vec<u8>) -> Result<(), ExecutionError> {
+    pub fn write(&mut self, address: usize, data: Vec<u8>) -> Result<(), ExecutionError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        Self::checked_end(address, data.len())?;
+
         // check if memory should be extended
         if address + data.len() > self.heap.len() {
             // Calculate the nearest multiple of 32
@@ -91,8 +140,7 @@ impl Memory {
             self.extend(nearest_multiple - self.heap.len());
         }
 
-        let ptr = self.heap.as_mut_ptr().add(address);
-        ptr::copy(data.as_ptr(), ptr, data.len());
+        self.heap[address..address + data.len()].copy_from_slice(&data);
 
         Ok(())
     }
@@ -106,7 +154,9 @@ impl Memory {
     /// # Returns
     ///
     /// A `Result` containing the 32 bytes read or an `ExecutionError` if the read operation failed.
-    pub unsafe fn mload(&mut self, address: usize) -> Result<[u8; 32], ExecutionError> {
+    pub fn mload(&mut self, address: usize) -> Result<[u8; 32], ExecutionError> {
+        Self::checked_end(address, 32)?;
+
         // Increase memory heap to the nearest multiple of 32 if address is out of bounds
         if address + 32 > self.heap.len() {
             // Calculate the nearest multiple of 32
@@ -120,8 +170,9 @@ impl Memory {
             self.extend(nearest_multiple - self.heap.len());
         }
 
-        let ptr = self.heap.as_ptr().add(address);
-        Ok(ptr::read(ptr as *const [u8; 32]))
+        let mut word = [0u8; 32];
+        word.copy_from_slice(&self.heap[address..address + 32]);
+        Ok(word)
     }
 
     /// Writes 32 bytes to memory starting at the specified address.
@@ -134,14 +185,15 @@ impl Memory {
     /// # Returns
     ///
     /// A `Result` indicating whether the write operation was successful or an `ExecutionError` if it failed.
-    pub unsafe fn mstore(&mut self, address: usize, data: [u8; 32]) -> Result<(), ExecutionError> {
+    pub fn mstore(&mut self, address: usize, data: [u8; 32]) -> Result<(), ExecutionError> {
+        Self::checked_end(address, 32)?;
+
         // Check if memory should be extended
         if address + 32 > self.heap.len() {
             self.extend(address + 32 - self.heap.len());
         }
 
-        let ptr = self.heap.as_mut_ptr().add(address);
-        ptr::write(ptr as *mut [u8; 32], data);
+        self.heap[address..address + 32].copy_from_slice(&data);
 
         Ok(())
     }
@@ -160,6 +212,7 @@ impl Clone for Memory {
     fn clone(&self) -> Self {
         Memory {
             heap: self.heap.clone(),
+            max_size: self.max_size,
         }
     }
 }
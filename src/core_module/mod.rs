@@ -1,6 +1,17 @@
+pub mod asm;
+pub mod cheatcodes;
+pub mod disassembler;
+pub mod env;
+pub mod frame;
+pub mod journal;
 pub mod memory;
 pub mod op_codes;
+pub mod provider;
+pub mod result;
 pub mod runner;
 pub mod stack;
 pub mod state;
+#[cfg(feature = "state-tests")]
+pub mod state_test;
+pub mod trace;
 pub mod utils;
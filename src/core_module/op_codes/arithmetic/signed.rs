@@ -3,8 +3,9 @@ use crate::core_module::utils;
 use crate::core_module::utils::errors::ExecutionError;
 
 // Primitive types
-use ethers::types::I256;
-use ethers::types::U256;
+#[cfg(feature = "alloy")]
+use crate::core_module::utils::primitives::U256Compat;
+use crate::core_module::utils::primitives::{signed_div, signed_rem, U256};
 
 // Colored output
 use colored::*;
@@ -22,18 +23,19 @@ use colored::*;
 ///
 /// * Their is not enough items on the stack
 pub fn sdiv(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(5)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
-    let a = I256::from_raw(U256::from_big_endian(&pop1));
-    let b = I256::from_raw(U256::from_big_endian(&pop2));
+    let a = U256::from_big_endian(&pop1);
+    let b = U256::from_big_endian(&pop2);
 
-    let result = a.checked_div(b);
+    // Division by zero returns 0 and INT_MIN / -1 wraps around to INT_MIN
+    let result = signed_div(a, b);
 
     let mut result_bytes = [0u8; 32];
-    result
-        .unwrap_or(I256::from(0))
-        .to_big_endian(&mut result_bytes);
+    result.to_big_endian(&mut result_bytes);
 
     let result = runner.stack.push(result_bytes);
 
@@ -63,18 +65,19 @@ pub fn sdiv(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn smodulo(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(5)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
-    let a = I256::from_raw(U256::from_big_endian(&pop1));
-    let b = I256::from_raw(U256::from_big_endian(&pop2));
+    let a = U256::from_big_endian(&pop1);
+    let b = U256::from_big_endian(&pop2);
 
-    let result = a.checked_rem(b);
+    // The remainder has the sign of the dividend and a remainder by zero returns 0
+    let result = signed_rem(a, b);
 
     let mut result_bytes = [0u8; 32];
-    result
-        .unwrap_or(I256::from(0))
-        .to_big_endian(&mut result_bytes);
+    result.to_big_endian(&mut result_bytes);
 
     let result = runner.stack.push(result_bytes);
 
@@ -90,3 +93,95 @@ pub fn smodulo(runner: &mut Runner) -> Result<(), ExecutionError> {
     // Increment PC
     runner.increment_pc(1)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_word(value: i64) -> [u8; 32] {
+        let magnitude = U256::from(value.unsigned_abs());
+        let value = if value < 0 {
+            (!magnitude).overflowing_add(U256::one()).0
+        } else {
+            magnitude
+        };
+
+        let mut bytes = [0u8; 32];
+        value.to_big_endian(&mut bytes);
+        bytes
+    }
+
+    fn int_min() -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[0] = 0x80;
+        bytes
+    }
+
+    #[test]
+    fn test_sdiv() {
+        let mut runner = Runner::_default(3);
+        let _ = runner.stack.push(signed_word(3));
+        let _ = runner.stack.push(signed_word(-9));
+
+        sdiv(&mut runner).unwrap();
+
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, signed_word(-3));
+
+        // Division by zero
+        let _ = runner.stack.push(signed_word(0));
+        let _ = runner.stack.push(signed_word(-9));
+
+        sdiv(&mut runner).unwrap();
+
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, [0u8; 32]);
+        assert_eq!(runner.stack.stack.len(), 0);
+    }
+
+    #[test]
+    fn test_sdiv_overflow() {
+        let mut runner = Runner::_default(3);
+        let _ = runner.stack.push(signed_word(-1));
+        let _ = runner.stack.push(int_min());
+
+        sdiv(&mut runner).unwrap();
+
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, int_min());
+        assert_eq!(runner.stack.stack.len(), 0);
+    }
+
+    #[test]
+    fn test_smodulo() {
+        let mut runner = Runner::_default(3);
+
+        // -8 SMOD 3 == -2
+        let _ = runner.stack.push(signed_word(3));
+        let _ = runner.stack.push(signed_word(-8));
+
+        smodulo(&mut runner).unwrap();
+
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, signed_word(-2));
+
+        // 8 SMOD -3 == 2
+        let _ = runner.stack.push(signed_word(-3));
+        let _ = runner.stack.push(signed_word(8));
+
+        smodulo(&mut runner).unwrap();
+
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, signed_word(2));
+
+        // INT_MIN SMOD -1 == 0
+        let _ = runner.stack.push(signed_word(-1));
+        let _ = runner.stack.push(int_min());
+
+        smodulo(&mut runner).unwrap();
+
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, [0u8; 32]);
+        assert_eq!(runner.stack.stack.len(), 0);
+    }
+}
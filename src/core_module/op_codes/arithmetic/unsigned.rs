@@ -1,9 +1,12 @@
 use crate::core_module::runner::Runner;
 use crate::core_module::utils;
+use crate::core_module::utils::bytes::{u256_to_word, word_to_u256};
 use crate::core_module::utils::errors::ExecutionError;
 
 // Primitive types
-use ethers::types::U256;
+#[cfg(feature = "alloy")]
+use crate::core_module::utils::primitives::U256Compat;
+use crate::core_module::utils::primitives::{narrow_u512, U256, U512};
 
 // Colored output
 use colored::*;
@@ -20,16 +23,17 @@ use colored::*;
 ///
 /// * Their is not enough items on the stack
 pub fn add(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
-    let a = U256::from_big_endian(&pop1);
-    let b = U256::from_big_endian(&pop2);
+    let a = word_to_u256(&pop1);
+    let b = word_to_u256(&pop2);
 
     let (result, _) = a.overflowing_add(b);
 
-    let mut result_bytes = [0u8; 32];
-    result.to_big_endian(&mut result_bytes);
+    let result_bytes = u256_to_word(result);
 
     let result = runner.stack.push(result_bytes);
 
@@ -58,16 +62,17 @@ pub fn add(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn mul(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(5)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
-    let a = U256::from_big_endian(&pop1);
-    let b = U256::from_big_endian(&pop2);
+    let a = word_to_u256(&pop1);
+    let b = word_to_u256(&pop2);
 
     let (result, _) = a.overflowing_mul(b);
 
-    let mut result_bytes = [0u8; 32];
-    result.to_big_endian(&mut result_bytes);
+    let result_bytes = u256_to_word(result);
 
     let result = runner.stack.push(result_bytes);
 
@@ -96,16 +101,17 @@ pub fn mul(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn sub(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
-    let a = U256::from_big_endian(&pop1);
-    let b = U256::from_big_endian(&pop2);
+    let a = word_to_u256(&pop1);
+    let b = word_to_u256(&pop2);
 
     let (result, _) = a.overflowing_sub(b);
 
-    let mut result_bytes = [0u8; 32];
-    result.to_big_endian(&mut result_bytes);
+    let result_bytes = u256_to_word(result);
 
     let result = runner.stack.push(result_bytes);
 
@@ -134,6 +140,8 @@ pub fn sub(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn modulo(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(5)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
@@ -174,6 +182,8 @@ pub fn modulo(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn div(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(5)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
@@ -203,6 +213,8 @@ pub fn div(runner: &mut Runner) -> Result<(), ExecutionError> {
 }
 
 /// Computes the addition modulo of the top three elements of the stack and pushes the result onto the stack.
+/// The addition is computed in 512-bit precision, so it doesn't wrap before the modulo.
+/// A zero modulus gives 0.
 ///
 /// # Arguments
 ///
@@ -214,6 +226,8 @@ pub fn div(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn addmod(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(8)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
     let pop3 = runner.stack.pop()?;
@@ -222,13 +236,14 @@ pub fn addmod(runner: &mut Runner) -> Result<(), ExecutionError> {
     let b = U256::from_big_endian(&pop2);
     let c = U256::from_big_endian(&pop3);
 
-    let (result, _) = a.overflowing_add(b);
-    let result = result.checked_rem(c);
+    let result = if c.is_zero() {
+        U256::zero()
+    } else {
+        mod_u512(U512::from(a) + U512::from(b), c)
+    };
 
     let mut result_bytes = [0u8; 32];
-    result
-        .unwrap_or(U256::from(0))
-        .to_big_endian(&mut result_bytes);
+    result.to_big_endian(&mut result_bytes);
 
     let result = runner.stack.push(result_bytes);
 
@@ -246,6 +261,8 @@ pub fn addmod(runner: &mut Runner) -> Result<(), ExecutionError> {
 }
 
 /// Computes the multiplication modulo of the top three elements of the stack and pushes the result onto the stack.
+/// The multiplication is computed in 512-bit precision, so it doesn't wrap before the modulo.
+/// A zero modulus gives 0.
 ///
 /// # Arguments
 ///
@@ -257,6 +274,8 @@ pub fn addmod(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn mulmod(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(8)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
     let pop3 = runner.stack.pop()?;
@@ -265,13 +284,14 @@ pub fn mulmod(runner: &mut Runner) -> Result<(), ExecutionError> {
     let b = U256::from_big_endian(&pop2);
     let c = U256::from_big_endian(&pop3);
 
-    let (result, _) = a.overflowing_mul(b);
-    let result = result.checked_rem(c);
+    let result = if c.is_zero() {
+        U256::zero()
+    } else {
+        mod_u512(a.full_mul(b), c)
+    };
 
     let mut result_bytes = [0u8; 32];
-    result
-        .unwrap_or(U256::from(0))
-        .to_big_endian(&mut result_bytes);
+    result.to_big_endian(&mut result_bytes);
 
     let result = runner.stack.push(result_bytes);
 
@@ -288,6 +308,14 @@ pub fn mulmod(runner: &mut Runner) -> Result<(), ExecutionError> {
     runner.increment_pc(1)
 }
 
+/// Reduces a 512-bit value modulo a non-zero 256-bit modulus.
+fn mod_u512(value: U512, modulus: U256) -> U256 {
+    let result = value % U512::from(modulus);
+
+    // The remainder is lower than the modulus, so it fits in 256 bits
+    narrow_u512(result).expect("The remainder fits in 256 bits")
+}
+
 /// Computes the exponentiation of the top two elements of the stack and pushes the result onto the stack.
 ///
 /// # Arguments
@@ -306,6 +334,10 @@ pub fn exp(runner: &mut Runner) -> Result<(), ExecutionError> {
     let a = U256::from_big_endian(&pop1);
     let b = U256::from_big_endian(&pop2);
 
+    // Charge the base cost plus the cost of each byte of the exponent
+    let exponent_bytes = b.bits().div_ceil(8) as u64;
+    runner.decrement_gas(10 + runner.gas_schedule().exp_byte * exponent_bytes)?;
+
     let (result, _) = a.overflowing_pow(b);
 
     let mut result_bytes = [0u8; 32];
@@ -325,3 +357,122 @@ pub fn exp(runner: &mut Runner) -> Result<(), ExecutionError> {
     // Increment PC
     runner.increment_pc(1)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(value: U256) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        value.to_big_endian(&mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn test_addmod() {
+        let mut runner = Runner::_default(3);
+
+        // (MAX + 2) % 7 with MAX + 2 overflowing 256 bits
+        let _ = runner.stack.push(word(U256::from(7)));
+        let _ = runner.stack.push(word(U256::from(2)));
+        let _ = runner.stack.push(word(U256::MAX));
+        addmod(&mut runner).unwrap();
+
+        // MAX + 2 = 2^256 + 1 = 3 (mod 7) since 2^256 = 2 (mod 7), the wrapped sum would give 1
+        assert_eq!(runner.stack.pop().unwrap(), word(U256::from(3)));
+
+        // Zero modulus
+        let _ = runner.stack.push(word(U256::zero()));
+        let _ = runner.stack.push(word(U256::from(2)));
+        let _ = runner.stack.push(word(U256::from(3)));
+        addmod(&mut runner).unwrap();
+        assert_eq!(runner.stack.pop().unwrap(), [0u8; 32]);
+    }
+
+    #[test]
+    fn test_mulmod() {
+        // (MAX * MAX) % 12 with MAX * MAX overflowing 256 bits
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None).with_stack(vec![
+            word(U256::from(12)),
+            word(U256::MAX),
+            word(U256::MAX),
+        ]);
+        mulmod(&mut runner).unwrap();
+
+        // MAX = 2^256 - 1 = 3 (mod 12), so MAX * MAX = 9 (mod 12)
+        assert_eq!(runner.stack.pop().unwrap(), word(U256::from(9)));
+
+        // Zero modulus
+        let _ = runner.stack.push(word(U256::zero()));
+        let _ = runner.stack.push(word(U256::from(2)));
+        let _ = runner.stack.push(word(U256::from(3)));
+        mulmod(&mut runner).unwrap();
+        assert_eq!(runner.stack.pop().unwrap(), [0u8; 32]);
+    }
+
+    #[test]
+    fn test_exp() {
+        let mut runner = Runner::_default(3);
+        let mut exp_of = |a: U256, b: U256| {
+            let _ = runner.stack.push(word(b));
+            let _ = runner.stack.push(word(a));
+            exp(&mut runner).unwrap();
+            runner.stack.pop().unwrap()
+        };
+
+        // Wraps modulo 2^256
+        assert_eq!(exp_of(U256::from(2), U256::from(256)), [0u8; 32]);
+        assert_eq!(
+            exp_of(U256::from(2), U256::from(255)),
+            word(U256::one() << 255)
+        );
+        assert_eq!(exp_of(U256::MAX, U256::from(2)), word(U256::one()));
+
+        // Zero base
+        assert_eq!(exp_of(U256::zero(), U256::zero()), word(U256::one()));
+        assert_eq!(exp_of(U256::zero(), U256::from(5)), [0u8; 32]);
+    }
+
+    #[test]
+    fn test_mixed_arithmetic_gas() {
+        let bytecode = crate::core_module::asm::assemble(
+            "PUSH1 0x02\nPUSH1 0x03\nADD\nPUSH1 0x04\nMUL\nPUSH1 0x06\nSWAP1\nSUB\n\
+             PUSH1 0x07\nSWAP1\nDIV\nPUSH1 0x03\nPUSH1 0x05\nPUSH1 0x04\nADDMOD\n\
+             PUSH1 0x03\nPUSH1 0x05\nPUSH1 0x04\nMULMOD\nPUSH2 0x0100\nPUSH1 0x02\nEXP\n\
+             LT\nISZERO\nNOT\nPUSH1 0x01\nAND\nPUSH1 0x01\nSHL",
+        )
+        .unwrap();
+
+        // 15 pushes and 2 swaps at 3 gas each
+        let stack_ops = 17 * 3;
+        // ADD, SUB, LT, ISZERO, NOT, AND and SHL at 3 gas each
+        let very_low = 7 * 3;
+        // MUL and DIV at 5 gas each
+        let low = 2 * 5;
+        // ADDMOD and MULMOD at 8 gas each
+        let mid = 2 * 8;
+        // EXP with a 2 byte exponent
+        let exp_cost = 10 + 2 * 50;
+
+        let mut runner = Runner::_default(3);
+        runner
+            .interpret_with_gas_limit(bytecode.clone(), 1_000_000, None)
+            .unwrap();
+        assert_eq!(
+            runner.gas_used(),
+            stack_ops + very_low + low + mid + exp_cost
+        );
+        assert_eq!(runner.stack.pop().unwrap(), word(U256::from(2)));
+
+        // Before EIP-160 each byte of the exponent cost 10 gas
+        let mut runner = Runner::_default(3);
+        runner.hardfork = crate::core_module::env::Hardfork::Homestead;
+        runner
+            .interpret_with_gas_limit(bytecode, 1_000_000, None)
+            .unwrap();
+        assert_eq!(
+            runner.gas_used(),
+            stack_ops + very_low + low + mid + 10 + 2 * 10
+        );
+    }
+}
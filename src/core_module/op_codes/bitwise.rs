@@ -1,10 +1,13 @@
 use crate::core_module::runner::Runner;
+use crate::core_module::state::EMPTY_CODE_HASH;
 use crate::core_module::utils;
+use crate::core_module::utils::bytes::u256_to_usize;
 use crate::core_module::utils::errors::ExecutionError;
 
 // Primitive types
-use ethers::types::U256;
-use ethers::utils::keccak256;
+#[cfg(feature = "alloy")]
+use crate::core_module::utils::primitives::U256Compat;
+use crate::core_module::utils::primitives::{keccak256, U256};
 
 // Colored output
 use colored::*;
@@ -21,6 +24,8 @@ use colored::*;
 ///
 /// * Their is not enough items on the stack
 pub fn not(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
 
     let a = U256::from_big_endian(&pop1);
@@ -57,6 +62,8 @@ pub fn not(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn xor(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
@@ -95,6 +102,8 @@ pub fn xor(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn or(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
@@ -133,6 +142,8 @@ pub fn or(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn and(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
@@ -171,13 +182,20 @@ pub fn and(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn shl(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
     let a = U256::from_big_endian(&pop1);
     let b = U256::from_big_endian(&pop2);
 
-    let result = b << a;
+    // Shifting by 256 bits or more clears every bit
+    let result = if a >= U256::from(256) {
+        U256::zero()
+    } else {
+        b << a.as_usize()
+    };
 
     let mut result_bytes = [0u8; 32];
     result.to_big_endian(&mut result_bytes);
@@ -209,13 +227,20 @@ pub fn shl(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn shr(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
     let a = U256::from_big_endian(&pop1);
     let b = U256::from_big_endian(&pop2);
 
-    let result = b >> a;
+    // Shifting by 256 bits or more clears every bit
+    let result = if a >= U256::from(256) {
+        U256::zero()
+    } else {
+        b >> a.as_usize()
+    };
 
     let mut result_bytes = [0u8; 32];
     result.to_big_endian(&mut result_bytes);
@@ -235,9 +260,55 @@ pub fn shr(runner: &mut Runner) -> Result<(), ExecutionError> {
     runner.increment_pc(1)
 }
 
+/// Performs an arithmetic (signed) shift right operation on the top two items of the stack
+///
+/// # Arguments
+///
+/// * `runner` - A mutable reference to the `Runner`
+///
+/// # Errors
+///
+/// returns an `ExecutionError` if:
+///
+/// * Their is not enough items on the stack
+pub fn sar(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
+    let pop1 = runner.stack.pop()?;
+    let pop2 = runner.stack.pop()?;
+
+    let a = U256::from_big_endian(&pop1);
+    let b = U256::from_big_endian(&pop2);
+    let negative = b.bit(255);
+
+    // Shifting by 256 bits or more leaves only the sign bits
+    let result = match (a >= U256::from(256), negative) {
+        (true, false) => U256::zero(),
+        (true, true) => U256::MAX,
+        (false, false) => b >> a.as_usize(),
+        (false, true) => !(!b >> a.as_usize()),
+    };
+
+    let mut result_bytes = [0u8; 32];
+    result.to_big_endian(&mut result_bytes);
+
+    runner.stack.push(result_bytes)?;
+
+    if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
+        let hex: String = utils::debug::to_hex_string(result_bytes);
+        runner.print_debug(&format!("{:<14} 👉 [ {} ]", "SAR".bright_blue(), hex));
+    }
+
+    // Increment PC
+    runner.increment_pc(1)
+}
+
 /// Performs a SHA256 hash on some data in memory
 /// The first item on the stack is the offset of the data in memory
 ///
+/// Hashing zero bytes doesn't read (nor expand) the memory, whatever the offset, and pushes
+/// the empty keccak256 hash.
+///
 /// # Arguments
 ///
 /// * `runner` - A mutable reference to the `Runner`
@@ -251,16 +322,19 @@ pub fn sha(runner: &mut Runner) -> Result<(), ExecutionError> {
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
-    let offset = U256::from_big_endian(&pop1).as_usize();
-    let size = U256::from_big_endian(&pop2).as_usize();
+    let offset = U256::from_big_endian(&pop1);
+    let size = U256::from_big_endian(&pop2);
 
-    let data_to_hash = unsafe { runner.memory.read(offset, size) };
+    let bytes = if size.is_zero() {
+        EMPTY_CODE_HASH
+    } else {
+        let size = u256_to_usize(size)?;
+        let offset = u256_to_usize(offset)?;
+        runner.charge_memory_expansion(offset, size)?;
 
-    if data_to_hash.is_err() {
-        return Err(data_to_hash.unwrap_err());
-    }
-
-    let bytes = keccak256(&data_to_hash?);
+        let data_to_hash = runner.memory.read(offset, size)?;
+        keccak256(&data_to_hash)
+    };
 
     runner.stack.push(bytes)?;
 
@@ -371,6 +445,55 @@ mod tests {
         assert_eq!(runner.stack.stack.len(), 0);
     }
 
+    #[test]
+    fn test_shifts_by_256_or_more() {
+        let shift = |op: fn(&mut Runner) -> Result<(), ExecutionError>,
+                     value: [u8; 32],
+                     amount: [u8; 32]| {
+            let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+            runner.stack.push(value).unwrap();
+            runner.stack.push(amount).unwrap();
+            op(&mut runner).unwrap();
+            runner.stack.pop().unwrap()
+        };
+        let by_256 = pad_left(&[0x01, 0x00]);
+        let huge = [0xff; 32];
+
+        for amount in [by_256, pad_left(&[0x01, 0x01]), huge] {
+            assert_eq!(shift(shl, [0xff; 32], amount), [0u8; 32]);
+            assert_eq!(shift(shr, [0xff; 32], amount), [0u8; 32]);
+            assert_eq!(shift(sar, [0xff; 32], amount), [0xff; 32]);
+            assert_eq!(shift(sar, pad_left(&[0x7f]), amount), [0u8; 32]);
+        }
+
+        // 255 is still a regular shift
+        let by_255 = pad_left(&[0xff]);
+        assert_eq!(shift(shl, pad_left(&[0x01]), by_255)[0], 0x80);
+        assert_eq!(shift(shr, [0xff; 32], by_255), pad_left(&[0x01]));
+        assert_eq!(shift(sar, [0xff; 32], by_255), [0xff; 32]);
+    }
+
+    #[test]
+    fn test_sar() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        // -16 >> 2 = -4
+        let mut minus_16 = [0xff; 32];
+        minus_16[31] = 0xf0;
+        runner.stack.push(minus_16).unwrap();
+        runner.stack.push(pad_left(&[0x02])).unwrap();
+        sar(&mut runner).unwrap();
+
+        let mut minus_4 = [0xff; 32];
+        minus_4[31] = 0xfc;
+        assert_eq!(runner.stack.pop().unwrap(), minus_4);
+
+        // 16 >> 2 = 4
+        runner.stack.push(pad_left(&[0x10])).unwrap();
+        runner.stack.push(pad_left(&[0x02])).unwrap();
+        sar(&mut runner).unwrap();
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x04]));
+    }
+
     #[test]
     fn test_sha256() {
         let mut runner = Runner::_default(3);
@@ -392,4 +515,21 @@ mod tests {
         assert_eq!(result, expected_output);
         assert_eq!(runner.stack.stack.len(), 0);
     }
+
+    #[test]
+    fn test_sha256_empty_input() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // Hash zero bytes at a huge offset
+        let _ = runner.stack.push(pad_left(&[0x00]));
+        let _ = runner.stack.push([0xff; 32]);
+        let gas = runner.gas;
+        sha(&mut runner).unwrap();
+
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, keccak256([]));
+        assert_eq!(result[..4], [0xc5, 0xd2, 0x46, 0x01]);
+        assert!(runner.memory.heap.is_empty());
+        assert_eq!(runner.gas, gas);
+    }
 }
@@ -3,7 +3,9 @@ use crate::core_module::utils::errors::ExecutionError;
 use crate::core_module::{runner::Runner, utils::bytes::pad_left};
 
 // Primitive types
-use ethers::types::{I256, U256};
+#[cfg(feature = "alloy")]
+use crate::core_module::utils::primitives::U256Compat;
+use crate::core_module::utils::primitives::{signed_cmp, U256};
 
 // Colored output
 use colored::*;
@@ -20,6 +22,8 @@ use colored::*;
 ///
 /// * Their is not enough items on the stack
 pub fn iszero(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
 
     let a = U256::from_big_endian(&pop1);
@@ -55,6 +59,8 @@ pub fn iszero(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn eq(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
@@ -92,13 +98,15 @@ pub fn eq(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn lt(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
     let a = U256::from_big_endian(&pop1);
     let b = U256::from_big_endian(&pop2);
 
-    let bool = a.lt(&b);
+    let bool = signed_cmp(a, b).is_lt();
 
     let result_bytes = pad_left(&[if bool { 1u8 } else { 0u8 }; 1]);
 
@@ -129,13 +137,15 @@ pub fn lt(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn gt(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
     let a = U256::from_big_endian(&pop1);
     let b = U256::from_big_endian(&pop2);
 
-    let bool = a.gt(&b);
+    let bool = signed_cmp(a, b).is_gt();
 
     let result_bytes = pad_left(&[if bool { 1u8 } else { 0u8 }; 1]);
 
@@ -167,13 +177,15 @@ pub fn gt(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn slt(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
-    let a = I256::from_raw(U256::from_big_endian(&pop1));
-    let b = I256::from_raw(U256::from_big_endian(&pop2));
+    let a = U256::from_big_endian(&pop1);
+    let b = U256::from_big_endian(&pop2);
 
-    let bool = a.lt(&b);
+    let bool = signed_cmp(a, b).is_lt();
 
     let result_bytes = pad_left(&[if bool { 1u8 } else { 0u8 }; 1]);
 
@@ -205,13 +217,15 @@ pub fn slt(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn sgt(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let pop1 = runner.stack.pop()?;
     let pop2 = runner.stack.pop()?;
 
-    let a = I256::from_raw(U256::from_big_endian(&pop1));
-    let b = I256::from_raw(U256::from_big_endian(&pop2));
+    let a = U256::from_big_endian(&pop1);
+    let b = U256::from_big_endian(&pop2);
 
-    let bool = a.gt(&b);
+    let bool = signed_cmp(a, b).is_gt();
 
     let result_bytes = pad_left(&[if bool { 1u8 } else { 0u8 }; 1]);
 
@@ -1,13 +1,18 @@
+use crate::core_module::env::Hardfork;
+use crate::core_module::op_codes::metadata::op_name_at;
 use crate::core_module::runner::Runner;
+use crate::core_module::state::EMPTY_CODE_HASH;
 use crate::core_module::utils;
-use crate::core_module::utils::bytes::{bytes32_to_address, pad_left};
+use crate::core_module::utils::address::is_precompile;
+use crate::core_module::utils::bytes::{bytes32_to_address, pad_left, word_to_usize};
 use crate::core_module::utils::environment::get_balance;
 use crate::core_module::utils::errors::ExecutionError;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 // Primitive types
-use ethers::types::U256;
-use ethers::utils::keccak256;
+#[cfg(feature = "alloy")]
+use crate::core_module::utils::primitives::U256Compat;
+use crate::core_module::utils::primitives::{keccak256, U256};
 
 // Colored output
 use colored::*;
@@ -52,6 +57,11 @@ pub fn balance(runner: &mut Runner) -> Result<(), ExecutionError> {
     let address: [u8; 32] = runner.stack.pop()?;
     let address: [u8; 20] = address[12..].try_into().unwrap();
 
+    // Charge the cold or warm account cost of the hardfork
+    let schedule = runner.gas_schedule();
+    let cold = runner.access_address(address);
+    runner.decrement_gas(schedule.account_access_cost(schedule.balance, cold))?;
+
     let balance = get_balance(address, runner)?;
 
     let result = runner.stack.push(pad_left(&balance));
@@ -162,9 +172,10 @@ pub fn callvalue(runner: &mut Runner) -> Result<(), ExecutionError> {
 /// * There is an error pushing the result onto the stack
 pub fn calldataload(runner: &mut Runner) -> Result<(), ExecutionError> {
     let address = runner.stack.pop()?;
-    let address = U256::from_big_endian(&address).as_usize();
+    let address = U256::from_big_endian(&address);
 
-    let calldata = unsafe { runner.calldata.read(address, 32)? };
+    // Zero-filled past the end of the call data
+    let calldata = copy_code_window(&runner.calldata.heap, address, 32);
     let calldata = calldata.as_slice().try_into().unwrap();
 
     let result = runner.stack.push(calldata);
@@ -234,13 +245,19 @@ pub fn calldatasize(runner: &mut Runner) -> Result<(), ExecutionError> {
 /// * There is an error reading the call data
 /// * There is an error writing the call data to memory
 pub fn calldatacopy(runner: &mut Runner) -> Result<(), ExecutionError> {
-    let dest_offset = U256::from_big_endian(&runner.stack.pop()?).as_usize();
-    let _offset = U256::from_big_endian(&runner.stack.pop()?).as_usize();
-    let _size = U256::from_big_endian(&runner.stack.pop()?).as_usize();
+    let dest_offset = U256::from_big_endian(&runner.stack.pop()?);
+    let _offset = U256::from_big_endian(&runner.stack.pop()?);
+    let _size = U256::from_big_endian(&runner.stack.pop()?);
+    let _size = runner.checked_copy_size(_size)?;
+
+    // Charge the base and per-word copy costs
+    runner.decrement_gas(3 + runner.gas_schedule().copy_cost(_size))?;
+
+    let (dest_offset, _size) = runner.memory_range(dest_offset, U256::from(_size))?;
 
-    let calldata = unsafe { runner.calldata.read(_offset, _size)? };
+    let calldata = copy_code_window(&runner.calldata.heap, _offset, _size);
 
-    let result = unsafe { runner.memory.write(dest_offset, calldata) };
+    let result = runner.memory.write(dest_offset, calldata);
 
     if result.is_err() {
         return Err(result.unwrap_err());
@@ -301,26 +318,23 @@ pub fn codesize(runner: &mut Runner) -> Result<(), ExecutionError> {
 /// * There is an error reading the code
 /// * There is an error writing the code to memory
 pub fn codecopy(runner: &mut Runner) -> Result<(), ExecutionError> {
-    let dest_offset = U256::from_big_endian(&runner.stack.pop()?).as_usize();
-    let offset = U256::from_big_endian(&runner.stack.pop()?).as_usize();
-    let size = U256::from_big_endian(&runner.stack.pop()?).as_usize();
+    let dest_offset = U256::from_big_endian(&runner.stack.pop()?);
+    let offset = U256::from_big_endian(&runner.stack.pop()?);
+    let size = U256::from_big_endian(&runner.stack.pop()?);
+    let size = runner.checked_copy_size(size)?;
 
-    let code = runner.state.get_code_at(runner.address);
+    // Charge the base and per-word copy costs
+    runner.decrement_gas(3 + runner.gas_schedule().copy_cost(size))?;
 
-    // Slice the code to the correct size
-    let code = if code.is_err() {
-        vec![]
-    } else {
-        // complete the code with 0s
-        let code = code.unwrap();
-        let mut code_vec = code.to_vec();
-        code_vec.resize(offset + size, 0);
-        let code = code_vec.as_slice();
-        code[offset..offset + size].to_vec()
+    let code = match runner.state.get_code_at(runner.address) {
+        Ok(code) => copy_code_window(code, offset, size),
+        Err(_) => vec![0u8; size],
     };
 
+    let (dest_offset, _) = runner.memory_range(dest_offset, U256::from(size))?;
+
     // Copy the code to memory
-    unsafe { runner.memory.write(dest_offset, code) }?;
+    runner.memory.write(dest_offset, code)?;
 
     if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
         runner.print_debug(&format!("{}", "CODECOPY".bright_blue()));
@@ -340,7 +354,8 @@ pub fn codecopy(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// Returns an `ExecutionError` if there is an error pushing the result onto the stack.
 pub fn gasprice(runner: &mut Runner) -> Result<(), ExecutionError> {
-    let gasprice = pad_left(&[0xff]);
+    let mut gasprice = [0u8; 32];
+    U256::from(runner.tx.effective_gas_price(runner.block.basefee)).to_big_endian(&mut gasprice);
 
     let result = runner.stack.push(gasprice);
 
@@ -369,9 +384,15 @@ pub fn gasprice(runner: &mut Runner) -> Result<(), ExecutionError> {
 pub fn extcodesize(runner: &mut Runner) -> Result<(), ExecutionError> {
     let address = runner.stack.pop()?;
 
+    // Charge the cold or warm account cost of the hardfork
+    let schedule = runner.gas_schedule();
+    let cold = runner.access_address(bytes32_to_address(&address));
+    runner.decrement_gas(schedule.account_access_cost(schedule.extcodesize, cold))?;
+
     let code = runner.state.get_code_at(bytes32_to_address(&address));
 
-    let codesize = if code.is_err() {
+    // Precompiles exist but have no code
+    let codesize = if code.is_err() || is_precompile(bytes32_to_address(&address)) {
         [0u8; 32]
     } else {
         pad_left(&code.unwrap().len().to_be_bytes())
@@ -411,26 +432,30 @@ pub fn extcodesize(runner: &mut Runner) -> Result<(), ExecutionError> {
 /// * There is an error writing the code to memory
 pub fn extcodecopy(runner: &mut Runner) -> Result<(), ExecutionError> {
     let address = runner.stack.pop()?;
-    let dest_offset = U256::from_big_endian(&runner.stack.pop()?).as_usize();
-    let offset = U256::from_big_endian(&runner.stack.pop()?).as_usize();
-    let size = U256::from_big_endian(&runner.stack.pop()?).as_usize();
-
-    let code = runner.state.get_code_at(bytes32_to_address(&address));
-
-    // Slice the code to the correct size
-    let code = if code.is_err() {
-        vec![]
-    } else {
-        // complete the code with 0s
-        let code = code.unwrap();
-        let mut code_vec = code.to_vec();
-        code_vec.resize(32, 0);
-        let code = code_vec.as_slice();
-        code[offset..offset + size].to_vec()
+    let dest_offset = U256::from_big_endian(&runner.stack.pop()?);
+    let offset = U256::from_big_endian(&runner.stack.pop()?);
+    let size = U256::from_big_endian(&runner.stack.pop()?);
+    let size = runner.checked_copy_size(size)?;
+
+    // Charge the cold or warm account cost of the hardfork, and the per-word copy cost
+    let schedule = runner.gas_schedule();
+    let cold = runner.access_address(bytes32_to_address(&address));
+    runner.decrement_gas(
+        schedule.account_access_cost(schedule.extcodecopy, cold) + schedule.copy_cost(size),
+    )?;
+
+    // Unknown accounts and precompiles have an empty code
+    let code = match runner.state.get_code_at(bytes32_to_address(&address)) {
+        Ok(code) if !is_precompile(bytes32_to_address(&address)) => {
+            copy_code_window(code, offset, size)
+        }
+        _ => vec![0u8; size],
     };
 
+    let (dest_offset, _) = runner.memory_range(dest_offset, U256::from(size))?;
+
     // Copy the code to memory
-    unsafe { runner.memory.write(dest_offset, code) }?;
+    runner.memory.write(dest_offset, code)?;
 
     if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
         runner.print_debug(&format!("{}", "EXTCODECOPY".bright_blue()));
@@ -492,13 +517,19 @@ pub fn returndatasize(runner: &mut Runner) -> Result<(), ExecutionError> {
 /// * There is an error reading the return data
 /// * There is an error writing the return data to memory
 pub fn returndatacopy(runner: &mut Runner) -> Result<(), ExecutionError> {
-    let dest_offset = U256::from_big_endian(&runner.stack.pop()?).as_usize();
-    let _offset = U256::from_big_endian(&runner.stack.pop()?).as_usize();
-    let _size = U256::from_big_endian(&runner.stack.pop()?).as_usize();
+    let dest_offset = U256::from_big_endian(&runner.stack.pop()?);
+    let _offset = word_to_usize(&runner.stack.pop()?)?;
+    let _size = U256::from_big_endian(&runner.stack.pop()?);
+    let _size = runner.checked_copy_size(_size)?;
 
-    let returndata = unsafe { runner.returndata.read(_offset, _size)? };
+    // Charge the base and per-word copy costs
+    runner.decrement_gas(3 + runner.gas_schedule().copy_cost(_size))?;
 
-    let result = unsafe { runner.memory.write(dest_offset, returndata) };
+    let (dest_offset, _size) = runner.memory_range(dest_offset, U256::from(_size))?;
+
+    let returndata = runner.returndata.read(_offset, _size)?;
+
+    let result = runner.memory.write(dest_offset, returndata);
 
     if result.is_err() {
         return Err(result.unwrap_err());
@@ -526,8 +557,17 @@ pub fn returndatacopy(runner: &mut Runner) -> Result<(), ExecutionError> {
 pub fn extcodehash(runner: &mut Runner) -> Result<(), ExecutionError> {
     let address = runner.stack.pop()?;
 
-    let code = runner.state.get_code_at(bytes32_to_address(&address))?;
-    let codehash = keccak256(&code);
+    // Charge the cold or warm account cost of the hardfork
+    let schedule = runner.gas_schedule();
+    let cold = runner.access_address(bytes32_to_address(&address));
+    runner.decrement_gas(schedule.account_access_cost(schedule.extcodehash, cold))?;
+
+    // Precompiles exist but have no code
+    let codehash = if is_precompile(bytes32_to_address(&address)) {
+        EMPTY_CODE_HASH
+    } else {
+        keccak256(runner.state.get_code_at(bytes32_to_address(&address))?)
+    };
 
     let result = runner.stack.push(codehash);
 
@@ -594,7 +634,7 @@ pub fn blockhash(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * There is an error pushing the result onto the stack
 pub fn coinbase(runner: &mut Runner) -> Result<(), ExecutionError> {
-    let coinbase = pad_left(&[0xc0u8; 20]);
+    let coinbase = pad_left(&runner.block.coinbase);
 
     let result = runner.stack.push(coinbase);
 
@@ -623,17 +663,20 @@ pub fn coinbase(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * There is an error pushing the result onto the stack
 pub fn timestamp(runner: &mut Runner) -> Result<(), ExecutionError> {
-    // Get the current timestamp
-    let now = SystemTime::now();
-    let since_the_epoch = now.duration_since(UNIX_EPOCH).expect("Time went backwards");
-
-    // Convert the timestamp to seconds
-    let timestamp_secs = since_the_epoch.as_secs();
-
-    // Convert the timestamp to bytes in big-endian order
-    let timestamp_bytes = timestamp_secs.to_be_bytes();
+    // Get the block timestamp, or the current one
+    let timestamp = match runner.block.timestamp {
+        Some(timestamp) => timestamp,
+        None => {
+            let now = SystemTime::now();
+            let since_the_epoch = now.duration_since(UNIX_EPOCH).expect("Time went backwards");
+
+            // Convert the timestamp to seconds
+            U256::from(since_the_epoch.as_secs())
+        }
+    };
 
-    let bytes = pad_left(&timestamp_bytes);
+    let mut bytes = [0u8; 32];
+    timestamp.to_big_endian(&mut bytes);
 
     let result = runner.stack.push(bytes);
 
@@ -662,7 +705,8 @@ pub fn timestamp(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * There is an error pushing the result onto the stack
 pub fn number(runner: &mut Runner) -> Result<(), ExecutionError> {
-    let number = pad_left(&[0xff; 4]);
+    let mut number = [0u8; 32];
+    runner.block.number.to_big_endian(&mut number);
 
     let result = runner.stack.push(number);
 
@@ -679,7 +723,8 @@ pub fn number(runner: &mut Runner) -> Result<(), ExecutionError> {
     runner.increment_pc(1)
 }
 
-/// Push the difficulty of the current block onto the stack.
+/// Push the difficulty of the current block onto the stack, or its prevrandao since Paris
+/// (EIP-4399).
 ///
 /// # Arguments
 ///
@@ -691,7 +736,11 @@ pub fn number(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * There is an error pushing the result onto the stack
 pub fn difficulty(runner: &mut Runner) -> Result<(), ExecutionError> {
-    let difficulty = pad_left(&[0x45; 8]);
+    let difficulty = if runner.hardfork.is_enabled(Hardfork::Paris) {
+        runner.block.prevrandao
+    } else {
+        pad_left(&runner.block.difficulty.to_be_bytes())
+    };
 
     let result = runner.stack.push(difficulty);
 
@@ -701,11 +750,8 @@ pub fn difficulty(runner: &mut Runner) -> Result<(), ExecutionError> {
 
     if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
         let hex: String = utils::debug::to_hex_string(difficulty);
-        runner.print_debug(&format!(
-            "{:<14} 👉 [ {} ]",
-            "DIFFICULTY".bright_blue(),
-            hex
-        ));
+        let name = op_name_at(0x44, runner.hardfork).unwrap_or_default();
+        runner.print_debug(&format!("{:<14} 👉 [ {} ]", name.bright_blue(), hex));
     }
 
     // Increment PC
@@ -753,7 +799,8 @@ pub fn gaslimit(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * There is an error pushing the result onto the stack
 pub fn chainid(runner: &mut Runner) -> Result<(), ExecutionError> {
-    let chainid = pad_left(&[0x01]);
+    let mut chainid = [0u8; 32];
+    U256::from(runner.block.chain_id).to_big_endian(&mut chainid);
 
     let result = runner.stack.push(chainid);
 
@@ -815,7 +862,8 @@ pub fn selfbalance(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * There is an error pushing the result onto the stack
 pub fn basefee(runner: &mut Runner) -> Result<(), ExecutionError> {
-    let basefee = pad_left(&[0x0a]);
+    let mut basefee = [0u8; 32];
+    U256::from(runner.block.basefee).to_big_endian(&mut basefee);
 
     let result = runner.stack.push(basefee);
 
@@ -832,10 +880,34 @@ pub fn basefee(runner: &mut Runner) -> Result<(), ExecutionError> {
     runner.increment_pc(1)
 }
 
+/// Returns the `size` bytes of `code` starting at `offset`, zero-filled past the end of the
+/// code. Only the requested window is allocated, whatever the offset. The call data is read
+/// the same way.
+///
+/// # Arguments
+///
+/// * `code` - The code to copy from.
+/// * `offset` - The offset of the window in the code, which may exceed the code length.
+/// * `size` - The size of the window.
+fn copy_code_window(code: &[u8], offset: U256, size: usize) -> Vec<u8> {
+    let mut window = vec![0u8; size];
+
+    // Copy the part of the code overlapping the window
+    if offset < U256::from(code.len()) {
+        let offset = offset.as_usize();
+        let end = code.len().min(offset.saturating_add(size));
+        window[..end - offset].copy_from_slice(&code[offset..end]);
+    }
+
+    window
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core_module::env::TxEnv;
     use crate::core_module::utils::bytes::{_hex_string_to_bytes, _pad_right, pad_left};
+    use crate::core_module::utils::environment::init_account;
 
     #[test]
     fn test_address() {
@@ -846,6 +918,66 @@ mod tests {
         assert_eq!(result, pad_left(&runner.address));
     }
 
+    #[test]
+    fn test_balance_of_forked_account() {
+        use crate::core_module::provider::MockProvider;
+        use std::sync::Arc;
+
+        let forked = [0x11; 20];
+        let mut provider = MockProvider::default();
+        provider.balances.insert(forked, pad_left(&[0x2a]));
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.state.provider = Some(Arc::new(provider));
+
+        // Cold then warm read of the forked balance
+        let _ = runner.stack.push(pad_left(&forked));
+        balance(&mut runner).unwrap();
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x2a]));
+        assert_eq!(runner.gas_used(), 2600);
+
+        // The account is copied from the fork
+        assert_eq!(runner.state.accounts[&forked].balance, pad_left(&[0x2a]));
+        assert!(runner.state.forked_accounts.contains(&forked));
+
+        let _ = runner.stack.push(pad_left(&forked));
+        balance(&mut runner).unwrap();
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x2a]));
+        assert_eq!(runner.gas_used(), 2600 + 100);
+    }
+
+    #[test]
+    #[ignore = "needs a mainnet node at EVM_RS_FORK_URL"]
+    fn test_balance_on_mainnet_fork() {
+        use crate::core_module::state::EvmState;
+
+        let fork_url = std::env::var("EVM_RS_FORK_URL").unwrap();
+
+        // The WETH contract
+        let weth: [u8; 20] = hex::decode("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let mut runner = Runner::new(
+            [0xaa; 20],
+            None,
+            None,
+            None,
+            None,
+            Some(EvmState::new(Some(fork_url))),
+        );
+
+        let _ = runner.stack.push(pad_left(&weth));
+        balance(&mut runner).unwrap();
+        assert_ne!(runner.stack.pop().unwrap(), [0u8; 32]);
+        assert!(runner.state.forked_accounts.contains(&weth));
+        let cold_gas_used = runner.gas_used();
+
+        let _ = runner.stack.push(pad_left(&weth));
+        balance(&mut runner).unwrap();
+        assert_eq!(runner.gas_used() - cold_gas_used, 100);
+    }
+
     #[test]
     fn test_balance() {
         let mut runner = Runner::_default(3);
@@ -918,6 +1050,18 @@ mod tests {
         assert_eq!(result, _pad_right(&[0xff, 0xff]));
     }
 
+    #[test]
+    fn test_calldataload_past_the_end() {
+        let mut runner = Runner::_default(3);
+        runner.calldata.heap = vec![0xff, 0xff, 0xff, 0xff];
+
+        let _ = runner.stack.push([0xff; 32]);
+        calldataload(&mut runner).unwrap();
+
+        assert_eq!(runner.stack.pop().unwrap(), [0u8; 32]);
+        assert_eq!(runner.calldata.heap.len(), 4);
+    }
+
     #[test]
     fn test_calldatasize() {
         let mut runner = Runner::_default(3);
@@ -939,7 +1083,7 @@ mod tests {
         let _ = runner.stack.push(pad_left(&[0x00]));
         calldatacopy(&mut runner).unwrap();
 
-        let result = unsafe { runner.memory.read(0x00, 0x20).unwrap() };
+        let result = runner.memory.read(0x00, 0x20).unwrap();
         assert_eq!(result, [0xff; 32].to_vec());
 
         let _ = runner.stack.push(pad_left(&[0x10]));
@@ -947,7 +1091,7 @@ mod tests {
         let _ = runner.stack.push(pad_left(&[0x00]));
         calldatacopy(&mut runner).unwrap();
 
-        let result = unsafe { runner.memory.read(0x00, 0x20).unwrap() };
+        let result = runner.memory.read(0x00, 0x20).unwrap();
         assert_eq!(result, [0xff; 32].to_vec());
 
         runner.memory.heap = vec![0x00; 32];
@@ -956,7 +1100,7 @@ mod tests {
         let _ = runner.stack.push(pad_left(&[0x00]));
         calldatacopy(&mut runner).unwrap();
 
-        let result = unsafe { runner.memory.read(0x00, 0x20).unwrap() };
+        let result = runner.memory.read(0x00, 0x20).unwrap();
         assert_eq!(result, _pad_right(&[0xff; 16]).to_vec());
     }
 
@@ -994,7 +1138,7 @@ mod tests {
         let _ = runner.stack.push(pad_left(&[0x00]));
         codecopy(&mut runner).unwrap();
 
-        let result = unsafe { runner.memory.read(0x00, 0x20).unwrap() };
+        let result = runner.memory.read(0x00, 0x20).unwrap();
         assert_eq!(
             result,
             _hex_string_to_bytes(
@@ -1010,17 +1154,139 @@ mod tests {
         let _ = runner.stack.push(pad_left(&[0x00]));
         codecopy(&mut runner).unwrap();
 
-        let result = unsafe { runner.memory.read(0x00, 0x20).unwrap() };
+        let result = runner.memory.read(0x00, 0x20).unwrap();
         assert_eq!(result, _pad_right(&_hex_string_to_bytes("7dffffffff")));
     }
 
+    #[test]
+    fn test_copy_zero_size_out_of_range_dest_offset() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // PUSH1 0x00 PUSH1 0x00 PUSH32 0x80..00, then CALLDATACOPY, CODECOPY and
+        // RETURNDATACOPY: the destination of an empty copy is ignored
+        for opcode in ["37", "39", "3e"] {
+            let interpret_result = runner.interpret(
+                _hex_string_to_bytes(&format!("600060007f80{}{}", "00".repeat(31), opcode)),
+                Some(2),
+                true,
+            );
+            assert!(interpret_result.is_ok());
+            assert_eq!(runner.memory.msize(), 0);
+        }
+    }
+
+    #[test]
+    fn test_codecopy_size_too_large() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // PUSH5 0x0100000000 (4 GiB) PUSH1 0x00 PUSH1 0x00 CODECOPY
+        let result = runner.interpret(_hex_string_to_bytes("6401000000006000600039"), None, true);
+        assert_eq!(result.unwrap_err(), ExecutionError::CopySizeTooLarge);
+        assert!(runner.memory.heap.is_empty());
+
+        // The cap is configurable
+        runner.max_copy_size = 0x10;
+        let _ = runner.stack.push(pad_left(&[0x20]));
+        let _ = runner.stack.push(pad_left(&[0x00]));
+        let _ = runner.stack.push(pad_left(&[0x00]));
+        assert_eq!(
+            codecopy(&mut runner).unwrap_err(),
+            ExecutionError::CopySizeTooLarge
+        );
+    }
+
+    #[test]
+    fn test_codecopy_large_offset() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // PUSH1 0x20 PUSH32 0xff..ff PUSH1 0x00 CODECOPY
+        let result = runner.interpret(
+            _hex_string_to_bytes(&format!("60207f{}600039", "ff".repeat(32))),
+            None,
+            true,
+        );
+        assert!(result.is_ok());
+        let result = runner.memory.read(0x00, 0x20).unwrap();
+        assert_eq!(result, vec![0u8; 32]);
+
+        // The window overlapping the end of the code is zero-filled
+        assert_eq!(
+            copy_code_window(&[1, 2, 3], U256::from(1), 4),
+            vec![2, 3, 0, 0]
+        );
+        assert_eq!(
+            copy_code_window(&[1, 2, 3], U256::from(u64::MAX), 2),
+            vec![0, 0]
+        );
+
+        // Same for EXTCODECOPY on the runner code
+        runner.memory.heap = vec![0xff; 32];
+        let _ = runner.stack.push(pad_left(&[0x20]));
+        let _ = runner.stack.push([0xff; 32]);
+        let _ = runner.stack.push(pad_left(&[0x00]));
+        let _ = runner.stack.push(pad_left(&runner.address));
+        extcodecopy(&mut runner).unwrap();
+        let result = runner.memory.read(0x00, 0x20).unwrap();
+        assert_eq!(result, vec![0u8; 32]);
+    }
+
+    #[test]
+    fn test_copy_word_cost() {
+        let codecopy_cost = |size: u8| {
+            let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+            // Expand the memory first, only the copy is measured
+            runner.stack.push(pad_left(&[0x00])).unwrap();
+            runner.stack.push(pad_left(&[0x20])).unwrap();
+            crate::core_module::op_codes::memory::mstore(&mut runner).unwrap();
+
+            runner.stack.push(pad_left(&[size])).unwrap();
+            runner.stack.push(pad_left(&[0x00])).unwrap();
+            runner.stack.push(pad_left(&[0x00])).unwrap();
+            let gas = runner.gas;
+            codecopy(&mut runner).unwrap();
+            gas - runner.gas
+        };
+
+        // Base cost plus 3 gas per word
+        assert_eq!(codecopy_cost(32), 3 + 3);
+        assert_eq!(codecopy_cost(64), 3 + 6);
+        assert_eq!(codecopy_cost(0), 3);
+    }
+
     #[test]
     fn test_gasprice() {
         let mut runner = Runner::_default(3);
         gasprice(&mut runner).unwrap();
 
         let result = runner.stack.pop().unwrap();
-        assert_eq!(result, pad_left(&[0xff]));
+        assert_eq!(result, pad_left(&[0x0a]));
+    }
+
+    #[test]
+    fn test_gasprice_eip1559() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.block.basefee = 100;
+        runner.tx = TxEnv {
+            max_fee_per_gas: 150,
+            max_priority_fee_per_gas: 20,
+        };
+
+        // GASPRICE PUSH1 0x00
+        runner
+            .interpret(_hex_string_to_bytes("3a6000"), Some(2), true)
+            .unwrap();
+
+        assert_eq!(runner.stack.stack[0], pad_left(&[120]));
+
+        // The coinbase receives the priority fee of the gas used
+        let mut expected = [0u8; 32];
+        U256::from(runner.gas_used() * 20).to_big_endian(&mut expected);
+        assert!(runner.gas_used() > 0);
+        assert_eq!(
+            runner.state.accounts[&runner.block.coinbase].balance,
+            expected
+        );
     }
 
     #[test]
@@ -1062,7 +1328,7 @@ mod tests {
         let _ = runner.stack.dup(4);
         extcodecopy(&mut runner).unwrap();
 
-        let result = unsafe { runner.memory.read(0x00, 0x20).unwrap() };
+        let result = runner.memory.read(0x00, 0x20).unwrap();
         assert_eq!(
             result,
             _pad_right(&_hex_string_to_bytes(
@@ -1079,9 +1345,9 @@ mod tests {
         let _ = runner.stack.dup(4);
         extcodecopy(&mut runner).unwrap();
 
-        let result = unsafe { runner.memory.read(0x00, 0x20).unwrap() };
+        let result = runner.memory.read(0x00, 0x20).unwrap();
         assert_eq!(result, [0u8; 32]);
-        let result = unsafe { runner.memory.read(0x20, 0x20).unwrap() };
+        let result = runner.memory.read(0x20, 0x20).unwrap();
         assert_eq!(
             result,
             _pad_right(&_hex_string_to_bytes("ffffffffffffffffffff"))
@@ -1116,11 +1382,11 @@ mod tests {
         );
         assert!(interpret_result.is_ok());
 
-        let result = unsafe { runner.memory.read(0x00, 0x20).unwrap() };
+        let result = runner.memory.read(0x00, 0x20).unwrap();
         assert_eq!(result, [0xff; 32]);
-        let result = unsafe { runner.memory.read(0x20, 0x20).unwrap() };
+        let result = runner.memory.read(0x20, 0x20).unwrap();
         assert_eq!(result, _pad_right(&[0xff]));
-        let result = unsafe { runner.memory.read(0x40, 0x20).unwrap() };
+        let result = runner.memory.read(0x40, 0x20).unwrap();
         assert_eq!(result, [0x00; 32]);
     }
 
@@ -1145,6 +1411,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_extcode_of_precompile() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let precompile = pad_left(&[0x01]);
+
+        runner.stack.push(precompile).unwrap();
+        extcodesize(&mut runner).unwrap();
+        assert_eq!(runner.stack.pop().unwrap(), [0u8; 32]);
+
+        runner.stack.push(precompile).unwrap();
+        extcodehash(&mut runner).unwrap();
+        assert_eq!(runner.stack.pop().unwrap(), EMPTY_CODE_HASH);
+
+        // Local code at a precompile address is ignored
+        let mut address = [0u8; 20];
+        address[19] = 0x01;
+        init_account(address, &mut runner).unwrap();
+        runner.state.put_code_at(address, vec![0xff; 4]).unwrap();
+
+        for _ in 0..3 {
+            runner.stack.push(pad_left(&[0x04])).unwrap();
+        }
+        runner.stack.push(precompile).unwrap();
+        extcodecopy(&mut runner).unwrap();
+        let result = runner.memory.read(0x04, 0x04).unwrap();
+        assert_eq!(result, vec![0u8; 4]);
+
+        runner.stack.push(precompile).unwrap();
+        extcodesize(&mut runner).unwrap();
+        assert_eq!(runner.stack.pop().unwrap(), [0u8; 32]);
+    }
+
     #[test]
     fn test_blockhash() {
         // TODO: test with a fork
@@ -1188,14 +1486,42 @@ mod tests {
         assert_eq!(result, pad_left(&[0xff; 4]));
     }
 
+    #[test]
+    fn test_number_beyond_u64() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let far_future = U256::from(u64::MAX) * U256::from(3);
+        runner.block.number = U256::from(u32::MAX) + U256::one();
+        runner.block.timestamp = Some(far_future);
+        number(&mut runner).unwrap();
+        timestamp(&mut runner).unwrap();
+
+        let mut expected = [0u8; 32];
+        far_future.to_big_endian(&mut expected);
+        assert_eq!(runner.stack.pop().unwrap(), expected);
+        assert_eq!(
+            runner.stack.pop().unwrap(),
+            pad_left(&[0x01, 0x00, 0x00, 0x00, 0x00])
+        );
+    }
+
     #[test]
     fn test_difficulty() {
         // TODO: test with a fork
         let mut runner = Runner::_default(3);
+        runner.block.prevrandao = [0x45; 32];
+        difficulty(&mut runner).unwrap();
+
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, [0x45; 32]);
+        assert_eq!(op_name_at(0x44, runner.hardfork), Some("PREVRANDAO"));
+
+        // Before the Merge
+        runner.hardfork = Hardfork::London;
         difficulty(&mut runner).unwrap();
 
         let result = runner.stack.pop().unwrap();
-        assert_eq!(result, pad_left(&[0x45; 8]));
+        assert_eq!(result, pad_left(&0x400000000u64.to_be_bytes()));
+        assert_eq!(op_name_at(0x44, runner.hardfork), Some("DIFFICULTY"));
     }
 
     #[test]
@@ -1214,6 +1540,13 @@ mod tests {
 
         let result = runner.stack.pop().unwrap();
         assert_eq!(result, pad_left(&[0x01]));
+
+        // Optimism
+        runner.block.chain_id = 10;
+        chainid(&mut runner).unwrap();
+
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, pad_left(&[0x0a]));
     }
 
     #[test]
@@ -4,7 +4,9 @@ use crate::core_module::utils::bytes::pad_left;
 use crate::core_module::utils::errors::ExecutionError;
 
 // Primitive types
-use ethers::types::U256;
+#[cfg(feature = "alloy")]
+use crate::core_module::utils::primitives::U256Compat;
+use crate::core_module::utils::primitives::U256;
 
 // Colored output
 use colored::*;
@@ -48,19 +50,19 @@ pub fn revert(runner: &mut Runner) -> Result<(), ExecutionError> {
     let offset = U256::from_big_endian(&runner.stack.pop()?);
     let size = U256::from_big_endian(&runner.stack.pop()?);
 
-    let revert_data = unsafe { runner.memory.read(offset.as_usize(), size.as_usize()) };
+    let (offset, size) = runner.memory_range(offset, size)?;
+
+    let revert_data = runner.memory.read(offset, size)?;
 
     // Copy revert data to the returndata
-    runner.returndata.heap = revert_data.as_ref().unwrap().to_owned();
+    runner.returndata.heap = revert_data.clone();
 
     let err;
     let hex;
 
-    if revert_data.is_ok() && revert_data.as_ref().unwrap().len() > 0 {
-        hex = utils::debug::vec_to_hex_string(
-            revert_data.as_ref().unwrap().as_slice().try_into().unwrap(),
-        );
-        err = ExecutionError::Revert(revert_data.unwrap());
+    if !revert_data.is_empty() {
+        hex = utils::debug::vec_to_hex_string(revert_data.clone());
+        err = ExecutionError::Revert(revert_data);
     } else {
         hex = utils::debug::to_hex_string([0u8; 32]);
         err = ExecutionError::RevertWithoutData;
@@ -90,7 +92,7 @@ pub fn jump(runner: &mut Runner) -> Result<(), ExecutionError> {
     jump_address.to_big_endian(&mut bytes);
 
     // Check if the address is out of bounds
-    if jump_address.as_usize() > runner.bytecode.len() {
+    if jump_address >= U256::from(runner.bytecode.len()) {
         return Err(ExecutionError::OutOfBoundsByteCode);
     }
 
@@ -99,14 +101,8 @@ pub fn jump(runner: &mut Runner) -> Result<(), ExecutionError> {
         runner.print_debug(&format!("{:<14} 〰️ [ {} ]", "JUMP".bright_green(), hex));
     }
 
-    // Check if the destination is a JUMPDEST
-    if runner.bytecode[jump_address.as_usize()] != 0x5b {
-        return Err(ExecutionError::InvalidJumpDestination);
-    }
-
-    // Set the program counter to the jump address
-    runner.set_pc(jump_address.as_usize());
-    Ok(())
+    // Set the program counter to the jump address if it is a JUMPDEST (not PUSH data)
+    runner.try_set_pc(jump_address.as_usize())
 }
 
 /// Jump if condition is true to a specific program address
@@ -127,23 +123,18 @@ pub fn jumpi(runner: &mut Runner) -> Result<(), ExecutionError> {
 
     let condition = U256::from_big_endian(&runner.stack.pop()?);
 
-    // Check if the address is out of bounds
-    if jump_address.as_usize() > runner.bytecode.len() {
-        return Err(ExecutionError::OutOfBoundsByteCode);
-    }
-
     // Check if the condition is true
     if !condition.is_zero() {
-        // Set the program counter to the jump address
-        runner.set_pc(jump_address.as_usize());
-    } else {
-        // Increment the program counter
-        let _ = runner.increment_pc(1);
-    }
+        // Check if the address is out of bounds
+        if jump_address >= U256::from(runner.bytecode.len()) {
+            return Err(ExecutionError::OutOfBoundsByteCode);
+        }
 
-    // Check if the destination is a JUMPDEST
-    if runner.bytecode[jump_address.as_usize()] != 0x5b {
-        return Err(ExecutionError::InvalidJumpDestination);
+        // Set the program counter to the jump address if it is a JUMPDEST (not PUSH data)
+        runner.try_set_pc(jump_address.as_usize())?;
+    } else {
+        // Increment the program counter, the destination is not checked
+        runner.increment_pc(1)?;
     }
 
     if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
@@ -169,6 +160,8 @@ pub fn jumpi(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * `runner` - A mutable reference to the `Runner`
 pub fn pc(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(2)?;
+
     let pc = runner.get_pc().to_be_bytes();
     let pc = pad_left(&pc.to_vec());
 
@@ -184,12 +177,15 @@ pub fn pc(runner: &mut Runner) -> Result<(), ExecutionError> {
     runner.increment_pc(1)
 }
 
-/// Push the gas to the stack
+/// Push the remaining gas, after the cost of this instruction, to the stack
 ///
 /// # Arguments
 ///
 /// * `runner` - A mutable reference to the `Runner`
 pub fn gas(runner: &mut Runner) -> Result<(), ExecutionError> {
+    // The pushed value is the gas left after the cost of GAS itself
+    runner.decrement_gas(2)?;
+
     let gas = runner.gas.to_be_bytes();
     let gas = pad_left(&gas.to_vec());
 
@@ -204,8 +200,10 @@ pub fn gas(runner: &mut Runner) -> Result<(), ExecutionError> {
     runner.increment_pc(1)
 }
 
-/// Does nothing but increment the program counter.
+/// Does nothing but charge its base cost and increment the program counter.
 pub fn jumpdest(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(1)?;
+
     // Increment the program counter
     runner.increment_pc(1)
 }
@@ -239,6 +237,129 @@ mod tests {
         assert_eq!(runner.returndata.heap, vec![0xff, 0x01]);
     }
 
+    #[test]
+    fn test_revert_zero_size() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.returndata.heap = vec![0xff; 4];
+
+        // PUSH1 0x2a PUSH1 0x00 MSTORE PUSH1 0x00 PUSH1 0x00 REVERT
+        let interpret_result =
+            runner.interpret(_hex_string_to_bytes("602a60005260006000fd"), Some(2), true);
+
+        assert_eq!(
+            interpret_result.unwrap_err(),
+            ExecutionError::RevertWithoutData
+        );
+        assert!(runner.returndata.heap.is_empty());
+    }
+
+    #[test]
+    fn test_revert_zero_size_out_of_range_offset() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // PUSH1 0x00 PUSH32 0x80..00 REVERT: the offset of an empty range is ignored
+        let interpret_result = runner.interpret(
+            _hex_string_to_bytes(&format!("60007f80{}fd", "00".repeat(31))),
+            Some(2),
+            true,
+        );
+
+        assert_eq!(
+            interpret_result.unwrap_err(),
+            ExecutionError::RevertWithoutData
+        );
+        assert_eq!(runner.memory.msize(), 0);
+    }
+
+    #[test]
+    fn test_revert_stack_underflow() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result = runner.interpret(_hex_string_to_bytes("fd"), Some(2), true);
+
+        assert_eq!(interpret_result.unwrap_err(), ExecutionError::StackTooSmall);
+    }
+
+    #[test]
+    fn test_jump_out_of_bounds() {
+        // Jump right after the end of the bytecode
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result = runner.interpret(_hex_string_to_bytes("600356"), Some(2), true);
+        assert_eq!(
+            interpret_result.unwrap_err(),
+            ExecutionError::OutOfBoundsByteCode
+        );
+
+        // Jump to an address that doesn't fit in a usize
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result = runner.interpret(
+            _hex_string_to_bytes(
+                "60017fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff57",
+            ),
+            Some(2),
+            true,
+        );
+        assert_eq!(
+            interpret_result.unwrap_err(),
+            ExecutionError::OutOfBoundsByteCode
+        );
+    }
+
+    #[test]
+    fn test_jump_to_non_jumpdest() {
+        // PUSH1 0x03 JUMP STOP: pc 3 is an in-range STOP
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result = runner.interpret(_hex_string_to_bytes("60035600"), None, true);
+        assert_eq!(
+            interpret_result.unwrap_err(),
+            ExecutionError::InvalidJumpDestination
+        );
+
+        // PUSH1 0x01 PUSH1 0x05 JUMPI STOP: same with a true condition
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result = runner.interpret(_hex_string_to_bytes("600160055700"), None, true);
+        assert_eq!(
+            interpret_result.unwrap_err(),
+            ExecutionError::InvalidJumpDestination
+        );
+
+        // PUSH1 0x01 PUSH1 0x06 JUMPI: right after the end of the bytecode
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result = runner.interpret(_hex_string_to_bytes("6001600657"), None, true);
+        assert_eq!(
+            interpret_result.unwrap_err(),
+            ExecutionError::OutOfBoundsByteCode
+        );
+    }
+
+    #[test]
+    fn test_jump_into_push_data() {
+        // PUSH1 0x5b PUSH1 0x01 JUMP: pc 1 holds 0x5b but it is PUSH data
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result = runner.interpret(_hex_string_to_bytes("605b600156"), Some(2), true);
+        assert_eq!(
+            interpret_result.unwrap_err(),
+            ExecutionError::InvalidJumpDestination
+        );
+        assert_eq!(runner.pc, 4);
+
+        // PUSH1 0x5b PUSH1 0x00 JUMP: pc 0 is a PUSH1
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result = runner.interpret(_hex_string_to_bytes("605b600056"), Some(2), true);
+        assert_eq!(
+            interpret_result.unwrap_err(),
+            ExecutionError::InvalidJumpDestination
+        );
+
+        // PUSH1 0x01 PUSH1 0x5b PUSH1 0x03 JUMPI: same with a true condition
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result =
+            runner.interpret(_hex_string_to_bytes("6001605b600357"), Some(2), true);
+        assert_eq!(
+            interpret_result.unwrap_err(),
+            ExecutionError::InvalidJumpDestination
+        );
+    }
+
     #[test]
     fn test_jump() {
         let mut runner = Runner::_default(3);
@@ -298,4 +419,45 @@ mod tests {
         let result: [u8; 32] = runner.stack.pop().unwrap();
         assert_eq!(result, pad_left(&runner.gas.to_be_bytes()));
     }
+
+    #[test]
+    fn test_gas_after_own_cost() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        // PUSH1 0x01 PUSH0 POP GAS
+        let interpret_result = runner.interpret(_hex_string_to_bytes("60015f505a"), None, true);
+        assert!(interpret_result.is_ok());
+
+        let result: [u8; 32] = runner.stack.pop().unwrap();
+        let gas_limit = runner.gas_limit;
+        assert_eq!(
+            result,
+            pad_left(&(gas_limit - (3 + 2 + 2 + 2)).to_be_bytes())
+        );
+        assert_eq!(runner.gas_used(), 3 + 2 + 2 + 2);
+    }
+
+    #[test]
+    fn test_jumpdest_cost() {
+        // PUSH1 0x03 JUMPDEST JUMPDEST*n PUSH1 0x01 SWAP1 SUB DUP1 PUSH1 0x02 JUMPI STOP
+        let run_loop = |extra_jumpdests: usize| {
+            let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+            let bytecode = format!("60035b{}600190038060025700", "5b".repeat(extra_jumpdests));
+            let interpret_result = runner.interpret(_hex_string_to_bytes(&bytecode), None, true);
+            assert!(interpret_result.is_ok());
+            assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x00]));
+            runner.gas_used()
+        };
+
+        // Each of the 3 iterations runs 4 more JUMPDESTs, 1 gas each
+        assert_eq!(run_loop(4) - run_loop(0), 3 * 4);
+    }
+
+    #[test]
+    fn test_pc_and_gas_cost() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        // PC GAS
+        let interpret_result = runner.interpret(_hex_string_to_bytes("585a"), None, true);
+        assert!(interpret_result.is_ok());
+        assert_eq!(runner.gas_used(), 2 + 2);
+    }
 }
@@ -4,7 +4,9 @@ use crate::core_module::utils;
 use crate::core_module::utils::errors::ExecutionError;
 
 // Primitive types
-use ethers::types::U256;
+#[cfg(feature = "alloy")]
+use crate::core_module::utils::primitives::U256Compat;
+use crate::core_module::utils::primitives::U256;
 
 // Colored output
 use colored::*;
@@ -26,7 +28,9 @@ pub fn log0(runner: &mut Runner) -> Result<(), ExecutionError> {
     let offset = U256::from_big_endian(&runner.stack.pop()?);
     let size = U256::from_big_endian(&runner.stack.pop()?);
 
-    let log_data = unsafe { runner.memory.read(offset.as_usize(), size.as_usize())? };
+    let (offset, size) = runner.memory_range(offset, size)?;
+
+    let log_data = runner.memory.read(offset, size)?;
 
     let log = Log {
         address: runner.address,
@@ -34,7 +38,7 @@ pub fn log0(runner: &mut Runner) -> Result<(), ExecutionError> {
         data: log_data.clone(),
     };
 
-    runner.state.logs.push(log);
+    runner.state.emit_log(log);
 
     if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
         let hex = utils::debug::vec_to_hex_string(log_data);
@@ -89,7 +93,9 @@ pub fn log1(runner: &mut Runner) -> Result<(), ExecutionError> {
     let mut topic1 = [0u8; 32];
     raw_topic1.to_big_endian(&mut topic1);
 
-    let log_data = unsafe { runner.memory.read(offset.as_usize(), size.as_usize())? };
+    let (offset, size) = runner.memory_range(offset, size)?;
+
+    let log_data = runner.memory.read(offset, size)?;
 
     let log = Log {
         address: runner.address,
@@ -97,7 +103,7 @@ pub fn log1(runner: &mut Runner) -> Result<(), ExecutionError> {
         data: log_data.clone(),
     };
 
-    runner.state.logs.push(log);
+    runner.state.emit_log(log);
 
     if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
         let data_hex = utils::debug::vec_to_hex_string(log_data);
@@ -165,7 +171,9 @@ pub fn log2(runner: &mut Runner) -> Result<(), ExecutionError> {
     let mut topic2 = [0u8; 32];
     raw_topic2.to_big_endian(&mut topic2);
 
-    let log_data = unsafe { runner.memory.read(offset.as_usize(), size.as_usize())? };
+    let (offset, size) = runner.memory_range(offset, size)?;
+
+    let log_data = runner.memory.read(offset, size)?;
 
     let log = Log {
         address: runner.address,
@@ -173,7 +181,7 @@ pub fn log2(runner: &mut Runner) -> Result<(), ExecutionError> {
         data: log_data.clone(),
     };
 
-    runner.state.logs.push(log);
+    runner.state.emit_log(log);
 
     if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
         let data_hex = utils::debug::vec_to_hex_string(log_data);
@@ -254,7 +262,9 @@ pub fn log3(runner: &mut Runner) -> Result<(), ExecutionError> {
     let mut topic3 = [0u8; 32];
     raw_topic3.to_big_endian(&mut topic3);
 
-    let log_data = unsafe { runner.memory.read(offset.as_usize(), size.as_usize())? };
+    let (offset, size) = runner.memory_range(offset, size)?;
+
+    let log_data = runner.memory.read(offset, size)?;
 
     let log = Log {
         address: runner.address,
@@ -262,7 +272,7 @@ pub fn log3(runner: &mut Runner) -> Result<(), ExecutionError> {
         data: log_data.clone(),
     };
 
-    runner.state.logs.push(log);
+    runner.state.emit_log(log);
 
     if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
         let data_hex = utils::debug::vec_to_hex_string(log_data);
@@ -356,7 +366,9 @@ pub fn log4(runner: &mut Runner) -> Result<(), ExecutionError> {
     let mut topic4 = [0u8; 32];
     raw_topic4.to_big_endian(&mut topic4);
 
-    let log_data = unsafe { runner.memory.read(offset.as_usize(), size.as_usize())? };
+    let (offset, size) = runner.memory_range(offset, size)?;
+
+    let log_data = runner.memory.read(offset, size)?;
 
     let log = Log {
         address: runner.address,
@@ -364,7 +376,7 @@ pub fn log4(runner: &mut Runner) -> Result<(), ExecutionError> {
         data: log_data.clone(),
     };
 
-    runner.state.logs.push(log);
+    runner.state.emit_log(log);
 
     if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
         let data_hex = utils::debug::vec_to_hex_string(log_data);
@@ -450,6 +462,23 @@ mod tests {
         assert!(log.data == pad_left(&[0x42]));
     }
 
+    #[test]
+    fn test_log0_zero_size_out_of_range_offset() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // PUSH1 0x00 PUSH32 0x80..00 LOG0: the offset of an empty range is ignored
+        let interpret_result = runner.interpret(
+            _hex_string_to_bytes(&format!("60007f80{}a0", "00".repeat(31))),
+            Some(2),
+            true,
+        );
+        assert!(interpret_result.is_ok());
+
+        let log = runner.state.logs.first().unwrap();
+        assert!(log.data.is_empty());
+        assert_eq!(runner.memory.msize(), 0);
+    }
+
     #[test]
     fn test_log1() {
         let mut runner = Runner::_default(3);
@@ -468,6 +497,23 @@ mod tests {
         assert!(log.data == pad_left(&[0x42]));
     }
 
+    #[test]
+    fn test_log_hex_accessors() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result: Result<(), ExecutionError> =
+            runner.interpret(_hex_string_to_bytes("604260005260ff60206000a1"), None, true);
+        assert!(interpret_result.is_ok());
+
+        let log = runner.state.logs.first().unwrap();
+
+        assert_eq!(
+            log.topic_hex(0).unwrap(),
+            format!("0x{}ff", "00".repeat(31))
+        );
+        assert_eq!(log.topic_hex(1), None);
+        assert_eq!(log.data_hex(), format!("0x{}42", "00".repeat(31)));
+    }
+
     #[test]
     fn test_log2() {
         let mut runner = Runner::_default(3);
@@ -1,9 +1,12 @@
 use crate::core_module::runner::Runner;
 use crate::core_module::utils;
+use crate::core_module::utils::bytes::word_to_usize;
 use crate::core_module::utils::errors::ExecutionError;
 
 // Primitive types
-use ethers::types::U256;
+#[cfg(feature = "alloy")]
+use crate::core_module::utils::primitives::U256Compat;
+use crate::core_module::utils::primitives::U256;
 
 // Colored output
 use colored::*;
@@ -16,10 +19,13 @@ use colored::*;
 ///
 /// # Errors
 ///
-/// Returns an `ExecutionError` if the stack is empty or if there is an error pushing the result onto the stack.
+/// Returns an `ExecutionError` if the stack is empty, if the address doesn't fit in a usize
+/// or if there is an error pushing the result onto the stack.
 pub fn mload(runner: &mut Runner) -> Result<(), ExecutionError> {
-    let address = U256::from_big_endian(&runner.stack.pop()?);
-    let word = unsafe { runner.memory.mload(address.as_usize())? };
+    let address = word_to_usize(&runner.stack.pop()?)?;
+    runner.charge_memory_expansion(address, 32)?;
+
+    let word = runner.memory.mload(address)?;
     let result = runner.stack.push(word);
 
     if result.is_err() {
@@ -44,12 +50,13 @@ pub fn mload(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// # Errors
 ///
-/// Returns an `ExecutionError` if the memory address is out of bounds.
+/// Returns an `ExecutionError` if the memory address is out of bounds or doesn't fit in a usize.
 pub fn mstore(runner: &mut Runner) -> Result<(), ExecutionError> {
-    let address = U256::from_big_endian(&runner.stack.pop()?);
+    let address = word_to_usize(&runner.stack.pop()?)?;
     let data = runner.stack.pop()?;
+    runner.charge_memory_expansion(address, 32)?;
 
-    let result = unsafe { runner.memory.mstore(address.as_usize(), data) };
+    let result = runner.memory.mstore(address, data);
 
     if result.is_err() {
         return Err(result.unwrap_err());
@@ -74,6 +81,8 @@ pub fn mstore(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// Returns an `ExecutionError` if there is an error pushing the result onto the stack.
 pub fn msize(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(2)?;
+
     let mut bytes_msize = [0u8; 32];
     U256::from(runner.memory.msize() as u64).to_big_endian(&mut bytes_msize);
 
@@ -108,6 +117,33 @@ mod tests {
         assert_eq!(result, pad_left(&[0xff, 0x00]));
     }
 
+    #[test]
+    fn test_mload_offset_overflow() {
+        let mut runner = Runner::_default(3);
+        // PUSH32 0x0100..00 MLOAD
+        let interpret_result: Result<(), ExecutionError> = runner.interpret(
+            _hex_string_to_bytes(
+                "7f010000000000000000000000000000000000000000000000000000000000000051",
+            ),
+            Some(2),
+            true,
+        );
+
+        assert_eq!(interpret_result.unwrap_err(), ExecutionError::OutOfGas);
+        assert_eq!(runner.memory.msize(), 0);
+    }
+
+    #[test]
+    fn test_mload_beyond_memory_limit() {
+        let mut runner = Runner::_default(3);
+        // PUSH4 0xffffffff MLOAD, a 4 GiB memory can't be paid for
+        let interpret_result: Result<(), ExecutionError> =
+            runner.interpret(_hex_string_to_bytes("63ffffffff51"), Some(2), true);
+
+        assert_eq!(interpret_result.unwrap_err(), ExecutionError::OutOfGas);
+        assert_eq!(runner.memory.msize(), 0);
+    }
+
     #[test]
     fn test_mstore() {
         let mut runner = Runner::_default(3);
@@ -120,7 +156,7 @@ mod tests {
         );
         assert!(interpret_result.is_ok());
 
-        let memory_content = unsafe { runner.memory.read(0x00, 0x20) };
+        let memory_content = runner.memory.read(0x00, 0x20);
         assert!(memory_content.is_ok());
 
         assert_eq!(memory_content.unwrap(), [0xff; 32]);
@@ -144,4 +180,12 @@ mod tests {
         assert_eq!(result2, pad_left(&[0x20]));
         assert_eq!(result3, pad_left(&[0x00]));
     }
+
+    #[test]
+    fn test_msize_cost() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result = runner.interpret(_hex_string_to_bytes("59"), None, true);
+        assert!(interpret_result.is_ok());
+        assert_eq!(runner.gas_used(), 2);
+    }
 }
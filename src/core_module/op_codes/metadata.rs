@@ -0,0 +1,360 @@
+use crate::core_module::env::Hardfork;
+
+/// Returns the mnemonic of an opcode.
+///
+/// # Arguments
+///
+/// * `opcode` - The opcode to name.
+///
+/// # Returns
+///
+/// Returns `None` for unassigned opcodes. The designated invalid opcode 0xFE is named `INVALID`.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::op_codes::metadata::op_name;
+///
+/// assert_eq!(op_name(0x01), Some("ADD"));
+/// assert_eq!(op_name(0x0c), None);
+/// ```
+pub fn op_name(opcode: u8) -> Option<&'static str> {
+    let name = match opcode {
+        /* ---------------------------- Arithmetic OpCodes --------------------------- */
+        0x00 => "STOP",
+        0x01 => "ADD",
+        0x02 => "MUL",
+        0x03 => "SUB",
+        0x04 => "DIV",
+        0x05 => "SDIV",
+        0x06 => "MOD",
+        0x07 => "SMOD",
+        0x08 => "ADDMOD",
+        0x09 => "MULMOD",
+        0x0a => "EXP",
+        0x0b => "SIGNEXTEND",
+
+        /* ------------------------ Comparison & bitwise OpCodes ---------------------- */
+        0x10 => "LT",
+        0x11 => "GT",
+        0x12 => "SLT",
+        0x13 => "SGT",
+        0x14 => "EQ",
+        0x15 => "ISZERO",
+        0x16 => "AND",
+        0x17 => "OR",
+        0x18 => "XOR",
+        0x19 => "NOT",
+        0x1a => "BYTE",
+        0x1b => "SHL",
+        0x1c => "SHR",
+        0x1d => "SAR",
+        0x20 => "SHA3",
+
+        /* --------------------------- Environment OpCodes --------------------------- */
+        0x30 => "ADDRESS",
+        0x31 => "BALANCE",
+        0x32 => "ORIGIN",
+        0x33 => "CALLER",
+        0x34 => "CALLVALUE",
+        0x35 => "CALLDATALOAD",
+        0x36 => "CALLDATASIZE",
+        0x37 => "CALLDATACOPY",
+        0x38 => "CODESIZE",
+        0x39 => "CODECOPY",
+        0x3a => "GASPRICE",
+        0x3b => "EXTCODESIZE",
+        0x3c => "EXTCODECOPY",
+        0x3d => "RETURNDATASIZE",
+        0x3e => "RETURNDATACOPY",
+        0x3f => "EXTCODEHASH",
+        0x40 => "BLOCKHASH",
+        0x41 => "COINBASE",
+        0x42 => "TIMESTAMP",
+        0x43 => "NUMBER",
+        0x44 => "PREVRANDAO",
+        0x45 => "GASLIMIT",
+        0x46 => "CHAINID",
+        0x47 => "SELFBALANCE",
+        0x48 => "BASEFEE",
+        0x49 => "BLOBHASH",
+        0x4a => "BLOBBASEFEE",
+
+        /* ------------------------ Memory, storage & flow OpCodes -------------------- */
+        0x50 => "POP",
+        0x51 => "MLOAD",
+        0x52 => "MSTORE",
+        0x53 => "MSTORE8",
+        0x54 => "SLOAD",
+        0x55 => "SSTORE",
+        0x56 => "JUMP",
+        0x57 => "JUMPI",
+        0x58 => "PC",
+        0x59 => "MSIZE",
+        0x5a => "GAS",
+        0x5b => "JUMPDEST",
+        0x5c => "TLOAD",
+        0x5d => "TSTORE",
+        0x5e => "MCOPY",
+
+        /* ------------------------------ Stack OpCodes ------------------------------ */
+        0x5f => "PUSH0",
+        0x60 => "PUSH1",
+        0x61 => "PUSH2",
+        0x62 => "PUSH3",
+        0x63 => "PUSH4",
+        0x64 => "PUSH5",
+        0x65 => "PUSH6",
+        0x66 => "PUSH7",
+        0x67 => "PUSH8",
+        0x68 => "PUSH9",
+        0x69 => "PUSH10",
+        0x6a => "PUSH11",
+        0x6b => "PUSH12",
+        0x6c => "PUSH13",
+        0x6d => "PUSH14",
+        0x6e => "PUSH15",
+        0x6f => "PUSH16",
+        0x70 => "PUSH17",
+        0x71 => "PUSH18",
+        0x72 => "PUSH19",
+        0x73 => "PUSH20",
+        0x74 => "PUSH21",
+        0x75 => "PUSH22",
+        0x76 => "PUSH23",
+        0x77 => "PUSH24",
+        0x78 => "PUSH25",
+        0x79 => "PUSH26",
+        0x7a => "PUSH27",
+        0x7b => "PUSH28",
+        0x7c => "PUSH29",
+        0x7d => "PUSH30",
+        0x7e => "PUSH31",
+        0x7f => "PUSH32",
+        0x80 => "DUP1",
+        0x81 => "DUP2",
+        0x82 => "DUP3",
+        0x83 => "DUP4",
+        0x84 => "DUP5",
+        0x85 => "DUP6",
+        0x86 => "DUP7",
+        0x87 => "DUP8",
+        0x88 => "DUP9",
+        0x89 => "DUP10",
+        0x8a => "DUP11",
+        0x8b => "DUP12",
+        0x8c => "DUP13",
+        0x8d => "DUP14",
+        0x8e => "DUP15",
+        0x8f => "DUP16",
+        0x90 => "SWAP1",
+        0x91 => "SWAP2",
+        0x92 => "SWAP3",
+        0x93 => "SWAP4",
+        0x94 => "SWAP5",
+        0x95 => "SWAP6",
+        0x96 => "SWAP7",
+        0x97 => "SWAP8",
+        0x98 => "SWAP9",
+        0x99 => "SWAP10",
+        0x9a => "SWAP11",
+        0x9b => "SWAP12",
+        0x9c => "SWAP13",
+        0x9d => "SWAP14",
+        0x9e => "SWAP15",
+        0x9f => "SWAP16",
+
+        /* ------------------------------- Log OpCodes ------------------------------- */
+        0xa0 => "LOG0",
+        0xa1 => "LOG1",
+        0xa2 => "LOG2",
+        0xa3 => "LOG3",
+        0xa4 => "LOG4",
+
+        /* ------------------------------ System OpCodes ----------------------------- */
+        0xf0 => "CREATE",
+        0xf1 => "CALL",
+        0xf2 => "CALLCODE",
+        0xf3 => "RETURN",
+        0xf4 => "DELEGATECALL",
+        0xf5 => "CREATE2",
+        0xfa => "STATICCALL",
+        0xfd => "REVERT",
+        0xfe => "INVALID",
+        0xff => "SELFDESTRUCT",
+
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+/// Returns the mnemonic of an opcode under the rules of a hardfork. Opcode 0x44 is named
+/// `DIFFICULTY` before Paris and `PREVRANDAO` since (EIP-4399).
+///
+/// # Arguments
+///
+/// * `opcode` - The opcode to name.
+/// * `hardfork` - The hardfork whose name to use.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::env::Hardfork;
+/// use evm_rs_emulator::op_codes::metadata::op_name_at;
+///
+/// assert_eq!(op_name_at(0x44, Hardfork::London), Some("DIFFICULTY"));
+/// assert_eq!(op_name_at(0x44, Hardfork::Paris), Some("PREVRANDAO"));
+/// ```
+pub fn op_name_at(opcode: u8, hardfork: Hardfork) -> Option<&'static str> {
+    match opcode {
+        0x44 if !hardfork.is_enabled(Hardfork::Paris) => Some("DIFFICULTY"),
+        _ => op_name(opcode),
+    }
+}
+
+/// Returns the number of stack items an opcode pops (`stack_in`) and pushes (`stack_out`).
+///
+/// # Arguments
+///
+/// * `opcode` - The opcode to look up.
+///
+/// # Returns
+///
+/// Returns `None` for unassigned opcodes.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::op_codes::metadata::stack_io;
+///
+/// assert_eq!(stack_io(0x01), Some((2, 1)));
+/// assert_eq!(stack_io(0x8f), Some((16, 17)));
+/// assert_eq!(stack_io(0x0c), None);
+/// ```
+pub fn stack_io(opcode: u8) -> Option<(usize, usize)> {
+    let io = match opcode {
+        /* ---------------------------- Arithmetic OpCodes --------------------------- */
+        0x00 => (0, 0),
+        0x01..=0x07 | 0x0a | 0x0b => (2, 1),
+        0x08 | 0x09 => (3, 1),
+
+        /* ------------------------ Comparison & bitwise OpCodes ---------------------- */
+        0x10..=0x14 | 0x16..=0x18 | 0x1a..=0x1d | 0x20 => (2, 1),
+        0x15 | 0x19 => (1, 1),
+
+        /* --------------------------- Environment OpCodes --------------------------- */
+        0x30 | 0x32..=0x34 | 0x36 | 0x38 | 0x3a | 0x3d => (0, 1),
+        0x31 | 0x35 | 0x3b | 0x3f => (1, 1),
+        0x37 | 0x39 | 0x3e => (3, 0),
+        0x3c => (4, 0),
+        0x40 | 0x49 => (1, 1),
+        0x41..=0x48 | 0x4a => (0, 1),
+
+        /* ------------------------ Memory, storage & flow OpCodes -------------------- */
+        0x50 | 0x56 => (1, 0),
+        0x51 | 0x54 | 0x5c => (1, 1),
+        0x52 | 0x53 | 0x55 | 0x57 | 0x5d => (2, 0),
+        0x58..=0x5a => (0, 1),
+        0x5b => (0, 0),
+        0x5e => (3, 0),
+
+        /* ------------------------------ Stack OpCodes ------------------------------ */
+        0x5f..=0x7f => (0, 1),
+        0x80..=0x8f => {
+            let n = (opcode - 0x7f) as usize;
+            (n, n + 1)
+        }
+        0x90..=0x9f => {
+            let n = (opcode - 0x8e) as usize;
+            (n, n)
+        }
+
+        /* ------------------------------- Log OpCodes ------------------------------- */
+        0xa0..=0xa4 => ((opcode - 0xa0) as usize + 2, 0),
+
+        /* ------------------------------ System OpCodes ----------------------------- */
+        0xf0 => (3, 1),
+        0xf1 | 0xf2 => (7, 1),
+        0xf3 | 0xfd => (2, 0),
+        0xf4 | 0xfa => (6, 1),
+        0xf5 => (4, 1),
+        0xfe => (0, 0),
+        0xff => (1, 0),
+
+        _ => return None,
+    };
+
+    Some(io)
+}
+
+/// Returns the opcode of a mnemonic. The lookup is case-insensitive and accepts the
+/// `KECCAK256` and `DIFFICULTY` aliases.
+///
+/// # Arguments
+///
+/// * `name` - The mnemonic to look up.
+///
+/// # Returns
+///
+/// Returns `None` if the mnemonic is unknown.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::op_codes::metadata::op_code;
+///
+/// assert_eq!(op_code("PUSH32"), Some(0x7f));
+/// assert_eq!(op_code("push1"), Some(0x60));
+/// ```
+pub fn op_code(name: &str) -> Option<u8> {
+    let name = name.to_ascii_uppercase();
+
+    match name.as_str() {
+        "KECCAK256" => return Some(0x20),
+        "DIFFICULTY" => return Some(0x44),
+        _ => {}
+    }
+
+    (0..=u8::MAX).find(|&opcode| op_name(opcode) == Some(name.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        assert_eq!(op_code("ADD"), Some(0x01));
+        assert_eq!(op_name(op_code("ADD").unwrap()), Some("ADD"));
+        assert_eq!(op_code("PUSH32"), Some(0x7f));
+        assert_eq!(op_code("DUP16"), Some(0x8f));
+        assert_eq!(op_code("SWAP1"), Some(0x90));
+        assert_eq!(op_code("INVALID"), Some(0xfe));
+        assert_eq!(op_code("sha3"), Some(0x20));
+        assert_eq!(op_code("KECCAK256"), Some(0x20));
+        assert_eq!(op_code("NOPE"), None);
+
+        // Every named opcode maps back to itself
+        for opcode in 0..=u8::MAX {
+            if let Some(name) = op_name(opcode) {
+                assert_eq!(op_code(name), Some(opcode));
+            }
+        }
+    }
+
+    #[test]
+    fn test_stack_io() {
+        assert_eq!(stack_io(0x08), Some((3, 1)));
+        assert_eq!(stack_io(0x80), Some((1, 2)));
+        assert_eq!(stack_io(0x90), Some((2, 2)));
+        assert_eq!(stack_io(0x9f), Some((17, 17)));
+        assert_eq!(stack_io(0xa4), Some((6, 0)));
+        assert_eq!(stack_io(0xf1), Some((7, 1)));
+
+        // Every named opcode has a stack signature
+        for opcode in 0..=u8::MAX {
+            assert_eq!(op_name(opcode).is_some(), stack_io(opcode).is_some());
+        }
+    }
+}
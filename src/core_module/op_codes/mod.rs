@@ -5,6 +5,7 @@ pub mod environment;
 pub mod flow;
 pub mod log;
 pub mod memory;
+pub mod metadata;
 pub mod stack;
 pub mod storage;
 pub mod system;
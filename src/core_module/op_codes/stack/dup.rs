@@ -17,6 +17,8 @@ use colored::*;
 ///
 /// * Their is not enough items on the stack
 pub fn dup1(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(1);
 
     if result.is_err() {
@@ -49,6 +51,8 @@ pub fn dup1(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup2(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(2);
 
     if result.is_err() {
@@ -81,6 +85,8 @@ pub fn dup2(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup3(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(3);
 
     if result.is_err() {
@@ -113,6 +119,8 @@ pub fn dup3(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup4(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(4);
 
     if result.is_err() {
@@ -145,6 +153,8 @@ pub fn dup4(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup5(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(5);
 
     if result.is_err() {
@@ -177,6 +187,8 @@ pub fn dup5(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup6(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(6);
 
     if result.is_err() {
@@ -209,6 +221,8 @@ pub fn dup6(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup7(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(7);
 
     if result.is_err() {
@@ -241,6 +255,8 @@ pub fn dup7(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup8(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(8);
 
     if result.is_err() {
@@ -273,6 +289,8 @@ pub fn dup8(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup9(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(9);
 
     if result.is_err() {
@@ -305,6 +323,8 @@ pub fn dup9(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup10(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(10);
 
     if result.is_err() {
@@ -337,6 +357,8 @@ pub fn dup10(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup11(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(11);
 
     if result.is_err() {
@@ -369,6 +391,8 @@ pub fn dup11(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup12(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(12);
 
     if result.is_err() {
@@ -401,6 +425,8 @@ pub fn dup12(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup13(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(13);
 
     if result.is_err() {
@@ -433,6 +459,8 @@ pub fn dup13(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup14(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(14);
 
     if result.is_err() {
@@ -465,6 +493,8 @@ pub fn dup14(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup15(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(15);
 
     if result.is_err() {
@@ -497,6 +527,8 @@ pub fn dup15(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn dup16(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.dup(16);
 
     if result.is_err() {
@@ -15,6 +15,8 @@ use colored::*;
 ///
 /// Returns an `ExecutionError` if the stack is empty.
 pub fn pop(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(2)?;
+
     let result = runner.stack.pop();
 
     if result.is_err() {
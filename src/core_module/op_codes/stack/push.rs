@@ -16,6 +16,9 @@ use colored::*;
 ///
 /// Returns an `ExecutionError` if the data length is out of bounds.
 pub fn push(runner: &mut Runner, data_len: usize) -> Result<(), ExecutionError> {
+    // PUSH0 is cheaper than the other PUSH opcodes
+    runner.decrement_gas(if data_len == 0 { 2 } else { 3 })?;
+
     // Check if the data length is out of bounds
     if runner.pc + 1 + data_len > runner.bytecode.len() {
         return Err(ExecutionError::OutOfBoundsByteCode);
@@ -16,6 +16,8 @@ use colored::*;
 ///
 /// * Their is not enough items on the stack
 pub fn swap1(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(1);
 
     if result.is_err() {
@@ -67,6 +69,8 @@ pub fn swap1(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap2(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(2);
 
     if result.is_err() {
@@ -118,6 +122,8 @@ pub fn swap2(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap3(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(3);
 
     if result.is_err() {
@@ -169,6 +175,8 @@ pub fn swap3(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap4(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(4);
 
     if result.is_err() {
@@ -220,6 +228,8 @@ pub fn swap4(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap5(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(5);
 
     if result.is_err() {
@@ -271,6 +281,8 @@ pub fn swap5(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap6(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(6);
 
     if result.is_err() {
@@ -322,6 +334,8 @@ pub fn swap6(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap7(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(7);
 
     if result.is_err() {
@@ -373,6 +387,8 @@ pub fn swap7(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap8(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(8);
 
     if result.is_err() {
@@ -424,6 +440,8 @@ pub fn swap8(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap9(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(9);
 
     if result.is_err() {
@@ -475,6 +493,8 @@ pub fn swap9(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap10(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(10);
 
     if result.is_err() {
@@ -526,6 +546,8 @@ pub fn swap10(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap11(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(11);
 
     if result.is_err() {
@@ -577,6 +599,8 @@ pub fn swap11(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap12(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(12);
 
     if result.is_err() {
@@ -628,6 +652,8 @@ pub fn swap12(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap13(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(13);
 
     if result.is_err() {
@@ -679,6 +705,8 @@ pub fn swap13(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap14(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(14);
 
     if result.is_err() {
@@ -730,6 +758,8 @@ pub fn swap14(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap15(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(15);
 
     if result.is_err() {
@@ -781,6 +811,8 @@ pub fn swap15(runner: &mut Runner) -> Result<(), ExecutionError> {
 ///
 /// * Their is not enough items on the stack
 pub fn swap16(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(3)?;
+
     let result = runner.stack.swap(16);
 
     if result.is_err() {
@@ -1,3 +1,4 @@
+use crate::core_module::env::Hardfork;
 use crate::core_module::runner::Runner;
 use crate::core_module::utils;
 use crate::core_module::utils::errors::ExecutionError;
@@ -16,6 +17,11 @@ use colored::*;
 /// Returns an `ExecutionError` if the stack is empty or if there is an error loading the value from storage.
 pub fn sload(runner: &mut Runner) -> Result<(), ExecutionError> {
     let address = runner.stack.pop()?;
+
+    // Charge the cold or warm slot cost of the hardfork
+    let cold = runner.access_storage_key(runner.address, address);
+    runner.decrement_gas(runner.gas_schedule().sload_cost(cold))?;
+
     let word = runner.state.sload(runner.address, address)?;
 
     let result = runner.stack.push(word);
@@ -33,7 +39,14 @@ pub fn sload(runner: &mut Runner) -> Result<(), ExecutionError> {
     runner.increment_pc(1)
 }
 
-/// Store 32 bytes in memory
+/// Store 32 bytes in storage
+///
+/// The slot is warmed (EIP-2929): storing to a cold slot costs the cold SLOAD cost of the
+/// hardfork, and a later SLOAD of the slot pays the warm price. The cost depending on the
+/// original and new values of the slot is not charged.
+///
+/// Clearing a slot adds 15000 gas (4800 since London, EIP-3529) to the refund counter of the
+/// transaction.
 ///
 /// # Arguments
 ///
@@ -50,12 +63,39 @@ pub fn sstore(runner: &mut Runner) -> Result<(), ExecutionError> {
     let address = runner.stack.pop()?;
     let word = runner.stack.pop()?;
 
+    // Charge the cold slot surcharge, the slot is warm for the rest of the transaction
+    let cold = runner.access_storage_key(runner.address, address);
+    if cold {
+        runner.decrement_gas(runner.gas_schedule().cold_sload)?;
+    }
+
+    // The value replaced by the store, fetched from the fork for a forked account
+    runner.state.copy_base_account(runner.address);
+    let previous = if runner.state.forked_accounts.contains(&runner.address) {
+        runner.state.sload(runner.address, address)?
+    } else {
+        runner
+            .state
+            .accounts
+            .get(&runner.address)
+            .and_then(|account| account.storage.get(&address).copied())
+            .unwrap_or([0u8; 32])
+    };
     let result = runner.state.sstore(runner.address, address, word);
 
     if result.is_err() {
         return Err(result.unwrap_err());
     }
 
+    // Refund the clearing of a slot
+    if word == [0u8; 32] && previous != [0u8; 32] {
+        runner.gas_refund += if runner.hardfork.is_enabled(Hardfork::London) {
+            4800
+        } else {
+            15000
+        };
+    }
+
     if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
         let hex: String = utils::debug::to_hex_string(word);
         runner.print_debug(&format!("{:<14} ⛔️ [ {} ]", "SSTORE".bright_blue(), hex));
@@ -65,10 +105,67 @@ pub fn sstore(runner: &mut Runner) -> Result<(), ExecutionError> {
     runner.increment_pc(1)
 }
 
+/// Loads a word from the transient storage of the current account and pushes it onto the stack.
+///
+/// # Arguments
+///
+/// * `runner` - A mutable reference to the `Runner` struct.
+///
+/// # Errors
+///
+/// Returns an `ExecutionError` if the stack is empty or if there is an error pushing the value onto the stack.
+pub fn tload(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(100)?;
+
+    let slot = runner.stack.pop()?;
+    let word = runner.state.tload(runner.address, slot);
+
+    runner.stack.push(word)?;
+
+    if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
+        let hex: String = utils::debug::to_hex_string(word);
+        runner.print_debug(&format!("{:<14} 👉 [ {} ]", "TLOAD".bright_blue(), hex));
+    }
+
+    // Increment PC
+    runner.increment_pc(1)
+}
+
+/// Store a word in the transient storage of the current account
+///
+/// # Arguments
+///
+/// * `runner` - A mutable reference to the `Runner` struct.
+///
+/// # Errors
+///
+/// Returns an `ExecutionError` if:
+///
+/// * The stack is empty
+/// * The static mode is enabled (static call)
+/// * There is an error incrementing the program counter
+pub fn tstore(runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.decrement_gas(100)?;
+
+    let slot = runner.stack.pop()?;
+    let word = runner.stack.pop()?;
+
+    runner.state.tstore(runner.address, slot, word)?;
+
+    if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
+        let hex: String = utils::debug::to_hex_string(word);
+        runner.print_debug(&format!("{:<14} ⛔️ [ {} ]", "TSTORE".bright_blue(), hex));
+    }
+
+    // Increment PC
+    runner.increment_pc(1)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::core_module::runner::Runner;
     use crate::core_module::utils::bytes::{_hex_string_to_bytes, pad_left};
+    use crate::core_module::utils::environment::init_account;
     use crate::core_module::utils::errors::ExecutionError;
 
     #[test]
@@ -87,6 +184,27 @@ mod tests {
         assert_eq!(result, pad_left(&[0x2e]));
     }
 
+    #[test]
+    fn test_sload_gas_by_hardfork() {
+        use crate::core_module::env::Hardfork;
+
+        // PUSH1 0x00 SLOAD PUSH1 0x00 SLOAD
+        let sload_twice = |hardfork: Hardfork| {
+            let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+            runner.hardfork = hardfork;
+            let result = runner.interpret(_hex_string_to_bytes("6000546000545050"), None, true);
+            assert!(result.is_ok());
+
+            // Minus the PUSH1 and POP costs
+            runner.gas_used() - 2 * 3 - 2 * 2
+        };
+
+        assert_eq!(sload_twice(Hardfork::Petersburg), 200 + 200);
+        assert_eq!(sload_twice(Hardfork::Istanbul), 800 + 800);
+        // Cold then warm
+        assert_eq!(sload_twice(Hardfork::Berlin), 2100 + 100);
+    }
+
     #[test]
     fn test_sstore() {
         let mut runner = Runner::_default(3);
@@ -100,4 +218,147 @@ mod tests {
             .unwrap();
         assert_eq!(result, pad_left(&[0x2e]));
     }
+
+    #[test]
+    fn test_sstore_warms_slot() {
+        // PUSH1 0x2e PUSH1 0x00 SSTORE PUSH1 0x00 SLOAD
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result =
+            runner.interpret(_hex_string_to_bytes("602e600055600054"), None, true);
+        assert!(interpret_result.is_ok());
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x2e]));
+
+        // Minus the PUSH1 costs, the SSTORE paid the cold access and the SLOAD the warm price
+        assert_eq!(runner.gas_used() - 3 * 3, 2100 + 100);
+    }
+
+    #[test]
+    fn test_sload_overridden_storage() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // Overrides bypass the static mode
+        runner.state.static_mode = true;
+        runner
+            .state
+            .override_storage(runner.address, pad_left(&[0x01]), pad_left(&[0x2a]));
+        runner.state.static_mode = false;
+
+        // PUSH1 0x01 SLOAD
+        let interpret_result: Result<(), ExecutionError> =
+            runner.interpret(_hex_string_to_bytes("600154"), Some(2), true);
+        assert!(interpret_result.is_ok());
+
+        let result: [u8; 32] = runner.stack.pop().unwrap();
+        assert_eq!(result, pad_left(&[0x2a]));
+
+        // Unknown accounts are initialized
+        runner
+            .state
+            .override_storage([0xbb; 20], pad_left(&[0x01]), pad_left(&[0x2b]));
+        assert_eq!(
+            runner.state.accounts[&[0xbb; 20]].storage[&pad_left(&[0x01])],
+            pad_left(&[0x2b])
+        );
+    }
+
+    #[test]
+    fn test_tload_tstore() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result: Result<(), ExecutionError> = runner.interpret(
+            _hex_string_to_bytes("602e60005d60005c60015c"),
+            Some(2),
+            true,
+        );
+        assert!(interpret_result.is_ok());
+
+        let result: [u8; 32] = runner.stack.pop().unwrap();
+        assert_eq!(result, pad_left(&[0x00]));
+        let result: [u8; 32] = runner.stack.pop().unwrap();
+        assert_eq!(result, pad_left(&[0x2e]));
+
+        // Transient storage doesn't touch the persistent storage
+        let result = runner
+            .state
+            .sload(runner.address, pad_left(&[0x00]))
+            .unwrap();
+        assert_eq!(result, pad_left(&[0x00]));
+    }
+
+    #[test]
+    fn test_tstore_in_staticcall() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // Callee: PUSH1 0x2e PUSH1 0x00 TSTORE
+        let callee = [0xbb; 20];
+        init_account(callee, &mut runner).unwrap();
+        runner
+            .state
+            .put_code_at(callee, _hex_string_to_bytes("602e60005d"))
+            .unwrap();
+
+        // STATICCALL the callee
+        let interpret_result: Result<(), ExecutionError> = runner.interpret(
+            _hex_string_to_bytes(
+                "600060006000600073bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb61fffffa",
+            ),
+            Some(2),
+            true,
+        );
+        assert!(interpret_result.is_ok());
+
+        // The call failed
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, pad_left(&[0x00]));
+
+        // The transient storage of the callee is untouched
+        assert_eq!(runner.state.tload(callee, pad_left(&[0x00])), [0u8; 32]);
+        assert!(!runner.state.static_mode);
+    }
+
+    #[test]
+    fn test_tstore_reverted_with_call() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // Reverting callee: TSTORE(0, 0x2e) TSTORE(0, 0x2f) REVERT(0, 0)
+        let reverting = [0xbb; 20];
+        init_account(reverting, &mut runner).unwrap();
+        let code = crate::core_module::asm::assemble(
+            "PUSH1 0x2e\nPUSH0\nTSTORE\nPUSH1 0x2f\nPUSH0\nTSTORE\nPUSH0\nPUSH0\nREVERT",
+        )
+        .unwrap();
+        runner.state.put_code_at(reverting, code).unwrap();
+        runner
+            .state
+            .tstore(reverting, pad_left(&[0x00]), pad_left(&[0x01]))
+            .unwrap();
+
+        // Succeeding callee: TSTORE(0, 0x2e) STOP
+        let succeeding = [0xcc; 20];
+        init_account(succeeding, &mut runner).unwrap();
+        let code = crate::core_module::asm::assemble("PUSH1 0x2e\nPUSH0\nTSTORE\nSTOP").unwrap();
+        runner.state.put_code_at(succeeding, code).unwrap();
+
+        // CALL both callees
+        let call = |address: [u8; 20]| {
+            format!(
+                "PUSH0\nPUSH0\nPUSH0\nPUSH0\nPUSH0\nPUSH20 0x{}\nGAS\nCALL\n",
+                hex::encode(address)
+            )
+        };
+        let bytecode =
+            crate::core_module::asm::assemble(&(call(reverting) + &call(succeeding))).unwrap();
+        runner.interpret(bytecode, None, true).unwrap();
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x01]));
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x00]));
+
+        // The writes of the reverted call are undone, the others are kept
+        assert_eq!(
+            runner.state.tload(reverting, pad_left(&[0x00])),
+            pad_left(&[0x01])
+        );
+        assert_eq!(
+            runner.state.tload(succeeding, pad_left(&[0x00])),
+            pad_left(&[0x2e])
+        );
+    }
 }
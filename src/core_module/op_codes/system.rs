@@ -1,15 +1,19 @@
+use crate::core_module::cheatcodes::{self, CHEATCODE_ADDRESS};
+use crate::core_module::env::Hardfork;
+use crate::core_module::frame::CallKind;
 use crate::core_module::runner::Runner;
 use crate::core_module::utils;
-use crate::core_module::utils::bytes;
-use crate::core_module::utils::bytes::{bytes32_to_address, pad_left};
+use crate::core_module::utils::address::{compute_create2_address, compute_create_address};
+use crate::core_module::utils::bytes::{bytes32_to_address, pad_left, u256_to_usize};
 use crate::core_module::utils::environment::{
     delete_account, get_balance, get_nonce, init_account,
 };
 use crate::core_module::utils::errors::ExecutionError;
 
 // Primitive types
-use ethers::types::U256;
-use ethers::utils::keccak256;
+#[cfg(feature = "alloy")]
+use crate::core_module::utils::primitives::U256Compat;
+use crate::core_module::utils::primitives::{keccak256, U256};
 
 // Colored output
 use colored::*;
@@ -78,55 +82,25 @@ pub fn create(runner: &mut Runner) -> Result<(), ExecutionError> {
     let offset = U256::from_big_endian(&runner.stack.pop()?);
     let size = U256::from_big_endian(&runner.stack.pop()?);
 
+    let (offset, size) = runner.memory_range(offset, size)?;
+
     // Load the init code from memory
-    let init_code = unsafe { runner.memory.read(offset.as_usize(), size.as_usize())? };
+    let init_code = runner.memory.read(offset, size)?;
 
     // Compute the contract address
-    let mut input = vec![0xd6, 0x94];
-    input.extend_from_slice(&runner.caller);
-    input.extend_from_slice(&bytes::strip_zero_padding(&get_nonce(
-        runner.address,
-        runner,
-    )?));
-
-    let hash = keccak256(input);
-    let contract_address: [u8; 20] = hash[12..].try_into().unwrap();
+    let nonce = U256::from_big_endian(&get_nonce(runner.address, runner)?).as_u64();
+    let contract_address = compute_create_address(runner.address, nonce);
 
     // Create the contract with init code as code
     init_account(contract_address, runner)?;
     runner.state.put_code_at(contract_address, init_code)?;
 
     // Call the contract to run its constructor
-    let temp_debug_level = runner.debug_level;
-    runner.debug_level = Some(0);
-    let call_result = runner.call(contract_address, value, Vec::new(), runner.gas, false);
-    runner.debug_level = temp_debug_level;
-
-    // Check if the call failed
-    if call_result.is_err() {
-        runner.stack.push(pad_left(&[0x00]))?;
-    } else {
-        runner.stack.push(pad_left(&contract_address))?;
-    }
-
-    // Get the return data to store the real contract code
-    let returndata = runner.returndata.heap.clone();
-    runner
-        .state
-        .put_code_at(contract_address, returndata.clone())?;
-
-    // Transfer the value
-    runner
-        .state
-        .transfer(runner.caller, contract_address, value)?;
-
-    if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
-        let hex: String = utils::debug::to_hex_string(pad_left(&contract_address));
-        runner.print_debug(&format!("{:<14} 👉 [ {} ]", "CREATE".bright_blue(), hex));
-    }
-
-    // Increment PC
-    runner.increment_pc(1)
+    let kind = CallKind::Create {
+        address: contract_address,
+        value,
+    };
+    enter_callee(runner, contract_address, value, Vec::new(), kind, None)
 }
 
 /// Executes the CREATE2 opcode, which creates a new contract with a given salt value and init code.
@@ -182,53 +156,25 @@ pub fn create2(runner: &mut Runner) -> Result<(), ExecutionError> {
     let size = U256::from_big_endian(&runner.stack.pop()?);
     let salt = runner.stack.pop()?;
 
+    let (offset, size) = runner.memory_range(offset, size)?;
+
     // Load the init code from memory
-    let init_code = unsafe { runner.memory.read(offset.as_usize(), size.as_usize())? };
+    let init_code = runner.memory.read(offset, size)?;
 
     // Compute the contract address
     let init_code_hash = keccak256(init_code.clone());
-
-    let mut input = vec![0xff];
-    input.extend_from_slice(&runner.caller);
-    input.extend_from_slice(&salt);
-    input.extend_from_slice(&init_code_hash);
-
-    let hash = keccak256(input);
-    let contract_address: [u8; 20] = hash[12..].try_into().unwrap();
+    let contract_address = compute_create2_address(runner.address, salt, init_code_hash);
 
     // Create the contract with init code as code
     init_account(contract_address, runner)?;
     runner.state.put_code_at(contract_address, init_code)?;
 
     // Call the contract to run its constructor
-    let temp_debug_level = runner.debug_level;
-    runner.debug_level = Some(2);
-    let call_result = runner.call(contract_address, value, Vec::new(), runner.gas, false);
-    runner.debug_level = temp_debug_level;
-
-    // Check if the call failed
-    if call_result.is_err() {
-        runner.stack.push(pad_left(&[0x00]))?;
-    } else {
-        runner.stack.push(pad_left(&contract_address))?;
-    }
-
-    // Get the return data to store the real contract code
-    let returndata = runner.returndata.heap.clone();
-    runner.state.put_code_at(contract_address, returndata)?;
-
-    // Transfer the value
-    runner
-        .state
-        .transfer(runner.caller, contract_address, value)?;
-
-    if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
-        let hex: String = utils::debug::to_hex_string(pad_left(&contract_address));
-        runner.print_debug(&format!("{:<14} 👉 [ {} ]", "CREATE2".bright_blue(), hex));
-    }
-
-    // Increment PC
-    runner.increment_pc(1)
+    let kind = CallKind::Create2 {
+        address: contract_address,
+        value,
+    };
+    enter_callee(runner, contract_address, value, Vec::new(), kind, None)
 }
 
 /// Executes a CALL or STATICCALL operation.
@@ -253,7 +199,7 @@ pub fn call(runner: &mut Runner, bypass_static: bool) -> Result<(), ExecutionErr
     }
 
     // Get the values on the stack
-    let gas = runner.stack.pop()?;
+    let gas = U256::from_big_endian(&runner.stack.pop()?);
     let to = runner.stack.pop()?;
 
     let value = if bypass_static {
@@ -266,12 +212,20 @@ pub fn call(runner: &mut Runner, bypass_static: bool) -> Result<(), ExecutionErr
     let returndata_offset = U256::from_big_endian(&runner.stack.pop()?);
     let returndata_size = U256::from_big_endian(&runner.stack.pop()?);
 
-    // Load the input data from memory
-    let calldata = unsafe {
-        runner
-            .memory
-            .read(calldata_offset.as_usize(), calldata_size.as_usize())?
-    };
+    // Check the return data size before running the call
+    let output_size = runner.checked_copy_size(returndata_size)?;
+
+    // Load the input data from memory, then charge the expansion of the output range
+    let (input_offset, input_size) = runner.memory_range(calldata_offset, calldata_size)?;
+    let calldata = runner.memory.read(input_offset, input_size)?;
+    let (output_offset, output_size) =
+        runner.memory_range(returndata_offset, U256::from(output_size))?;
+    let (returndata_offset, returndata_size) = (U256::from(output_offset), U256::from(output_size));
+
+    // Charge the call cost before forwarding gas to the callee
+    let cost = call_cost(runner, bytes32_to_address(&to), value);
+    runner.decrement_gas(cost)?;
+    let gas = gas.min(U256::from(u64::MAX)).as_u64();
 
     if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
         let address_hex: String = utils::debug::to_hex_address(bytes32_to_address(&to));
@@ -290,70 +244,287 @@ pub fn call(runner: &mut Runner, bypass_static: bool) -> Result<(), ExecutionErr
     }
 
     // Call the contract
-    let call_result = runner.call(
+    let kind = if bypass_static {
+        CallKind::StaticCall {
+            returndata_offset,
+            returndata_size,
+        }
+    } else {
+        CallKind::Call {
+            returndata_offset,
+            returndata_size,
+        }
+    };
+    enter_callee(
+        runner,
         bytes32_to_address(&to),
         value,
         calldata,
-        U256::from_big_endian(&gas).as_u64(),
-        false,
-    );
+        kind,
+        Some(gas),
+    )
+}
+
+/* -------------------------------------------------------------------------- */
+/*                               Call completion                              */
+/* -------------------------------------------------------------------------- */
+
+/// Enters the callee of a call opcode. The interpreter loop runs the callee, then completes
+/// the call opcode with `complete_call` once it returns.
+/// If the callee can't be entered, the call opcode is completed right away as a failed call.
+/// Calls to `CHEATCODE_ADDRESS` are run by `cheatcodes::apply` and completed right away.
+fn enter_callee(
+    runner: &mut Runner,
+    to: [u8; 20],
+    value: [u8; 32],
+    calldata: Vec<u8>,
+    kind: CallKind,
+    gas: Option<u64>,
+) -> Result<(), ExecutionError> {
+    // Before EIP-150, a message call can't request more gas than the caller has left
+    if let Some(requested) = gas {
+        if !runner.hardfork.is_enabled(Hardfork::TangerineWhistle) && requested > runner.gas {
+            return Err(ExecutionError::OutOfGas);
+        }
+    }
+
+    // Calls to the cheatcode address don't run any code
+    if to == CHEATCODE_ADDRESS {
+        let result = cheatcodes::apply(runner, &calldata);
+        return complete_call(runner, kind, result);
+    }
+
+    match runner.enter_frame(to, value, calldata, kind.clone(), gas) {
+        Ok(()) => Ok(()),
+        Err(error) => complete_call(runner, kind, Err(error)),
+    }
+}
 
+/// Completes a call opcode once its callee returned and the caller frame is restored.
+///
+/// # Arguments
+///
+/// * `runner` - A mutable reference to the `Runner` struct.
+/// * `kind` - The kind of the completed call.
+/// * `call_result` - The result of the callee execution.
+///
+/// # Errors
+///
+/// Returns an `ExecutionError` if the call opcode fails to push its result, write the return
+/// data or store the created contract. An external call (`Runner::call`) returns the callee
+/// result as is.
+pub fn complete_call(
+    runner: &mut Runner,
+    kind: CallKind,
+    call_result: Result<(), ExecutionError>,
+) -> Result<(), ExecutionError> {
+    match kind {
+        CallKind::External { .. } => call_result,
+        CallKind::Call {
+            returndata_offset,
+            returndata_size,
+        } => complete_message_call(
+            runner,
+            "CALL",
+            call_result,
+            returndata_offset,
+            returndata_size,
+        ),
+        CallKind::StaticCall {
+            returndata_offset,
+            returndata_size,
+        } => complete_message_call(
+            runner,
+            "STATICCALL",
+            call_result,
+            returndata_offset,
+            returndata_size,
+        ),
+        CallKind::DelegateCall {
+            returndata_offset,
+            returndata_size,
+        } => complete_message_call(
+            runner,
+            "DELEGATECALL",
+            call_result,
+            returndata_offset,
+            returndata_size,
+        ),
+        CallKind::Create { address, .. } => complete_create(runner, "CREATE", call_result, address),
+        CallKind::Create2 { address, .. } => {
+            complete_create(runner, "CREATE2", call_result, address)
+        }
+    }
+}
+
+/// Completes a CALL, STATICCALL or DELEGATECALL: pushes the success flag and writes the
+/// return data to memory.
+fn complete_message_call(
+    runner: &mut Runner,
+    name: &str,
+    call_result: Result<(), ExecutionError>,
+    returndata_offset: U256,
+    returndata_size: U256,
+) -> Result<(), ExecutionError> {
     if call_result.is_err() {
         runner.stack.push(pad_left(&[0x00]))?;
     } else {
         runner.stack.push(pad_left(&[0x01]))?;
     }
 
-    let return_data = runner.returndata.heap.clone();
-
     if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
         let caller_hex: String = utils::debug::to_hex_address(runner.address);
-        let returndata_hex: String = utils::debug::vec_to_hex_string(return_data.clone());
+        let returndata_hex: String =
+            utils::debug::vec_to_hex_string(runner.returndata.heap.clone());
         runner.print_debug(&format!(
             "\n{} {} {}\n  {}: {}\n",
             if call_result.is_err() {
-                if bypass_static {
-                    "STATICCALL FAILED".red()
-                } else {
-                    "CALL FAILED".red()
-                }
+                format!("{} FAILED", name).red()
             } else {
-                if bypass_static {
-                    "STATICCALL SUCCEEDED".green()
-                } else {
-                    "CALL SUCCEEDED".green()
-                }
+                format!("{} SUCCEEDED", name).green()
             },
             if call_result.is_err() { "❌" } else { "✅" },
-            if call_result.is_err() {
-                call_result.unwrap_err().to_string().red()
-            } else {
-                format!("Back to {}", caller_hex.magenta()).white()
+            match &call_result {
+                Err(error) => error.to_string().red(),
+                Ok(()) => format!("Back to {}", caller_hex.magenta()).white(),
             },
             "Returndata".bright_blue(),
             returndata_hex
         ));
     }
 
+    // Only copy `returndata_size` bytes, RETURNDATASIZE and RETURNDATACOPY still see the
+    // full return data
     let mut return_data: Vec<u8> = runner.returndata.heap.clone();
 
+    // The output range was checked and charged before the call
+    let (returndata_offset, returndata_size) = (
+        u256_to_usize(returndata_offset)?,
+        u256_to_usize(returndata_size)?,
+    );
+
     // Complete return data with zeros if returndata is smaller than returndata_size
-    if return_data.len() < returndata_size.as_usize() {
-        return_data.extend(vec![0; returndata_size.as_usize() - return_data.len()]);
-    }
-    return_data = return_data[0..returndata_size.as_usize()].to_vec();
+    return_data.resize(returndata_size, 0);
 
     // Write the return data to memory
-    unsafe {
-        runner
-            .memory
-            .write(returndata_offset.as_usize(), return_data)?
+    runner.memory.write(returndata_offset, return_data)?;
+
+    // Increment PC
+    runner.increment_pc(1)
+}
+
+/// Completes a CREATE or CREATE2: stores the runtime code and pushes the contract address if
+/// the constructor succeeded, or deletes the contract and pushes 0 if it failed. The value was
+/// moved to the contract before its constructor ran, and is given back if it failed.
+fn complete_create(
+    runner: &mut Runner,
+    name: &str,
+    call_result: Result<(), ExecutionError>,
+    contract_address: [u8; 20],
+) -> Result<(), ExecutionError> {
+    // Reject a runtime code starting with 0xEF (EIP-3541)
+    let call_result = match call_result {
+        Ok(()) if !runner.is_deployable_code(&runner.returndata.heap) => {
+            runner.returndata.heap = Vec::new();
+            if let Some(call) = runner.last_call_mut() {
+                call.success = false;
+            }
+            Err(ExecutionError::InvalidCodePrefix)
+        }
+        result => result,
     };
 
+    // Check if the call failed
+    if call_result.is_err() {
+        // Keep the revert data in the return data but don't create the contract
+        delete_account(contract_address, runner)?;
+        runner.stack.push(pad_left(&[0x00]))?;
+    } else {
+        // Get the return data to store the real contract code
+        let returndata = runner.returndata.heap.clone();
+        runner.state.put_code_at(contract_address, returndata)?;
+
+        // A successful creation leaves an empty return data
+        runner.returndata.heap = Vec::new();
+
+        runner.stack.push(pad_left(&contract_address))?;
+    }
+
+    if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
+        let hex: String = utils::debug::to_hex_string(pad_left(&contract_address));
+        runner.print_debug(&format!("{:<14} 👉 [ {} ]", name.bright_blue(), hex));
+    }
+
     // Increment PC
     runner.increment_pc(1)
 }
 
+/// Computes the gas charged by the call opcodes before forwarding gas to the callee, and
+/// marks the callee as accessed.
+///
+/// The cost is made of:
+/// * The access cost from the gas schedule (2600 for a cold address and 100 for a warm one
+///   since Berlin, 700 from Tangerine Whistle to Istanbul)
+/// * 9000 if some value is sent
+/// * 25000 if the call creates the account, see `pays_new_account`
+///
+/// # Arguments
+///
+/// * `runner` - A mutable reference to the `Runner` struct.
+/// * `to` - The called address.
+/// * `value` - The value sent with the call.
+fn call_cost(runner: &mut Runner, to: [u8; 20], value: [u8; 32]) -> u64 {
+    let schedule = runner.gas_schedule();
+    let cold = runner.access_address(to);
+    let mut cost = schedule.account_access_cost(schedule.call, cold);
+
+    if value != [0u8; 32] {
+        cost += 9000;
+    }
+    if pays_new_account(runner, to, value) {
+        cost += 25000;
+    }
+
+    cost
+}
+
+/// Returns `true` if sending `value` to the account pays the new account cost. Since Spurious
+/// Dragon, the account must be empty and receive some value (EIP-161). Before, the account
+/// must not exist, whatever the value.
+///
+/// # Arguments
+///
+/// * `runner` - A mutable reference to the `Runner` struct.
+/// * `address` - The address of the account.
+/// * `value` - The value sent to the account.
+fn pays_new_account(runner: &mut Runner, address: [u8; 20], value: [u8; 32]) -> bool {
+    if runner.hardfork.is_enabled(Hardfork::SpuriousDragon) {
+        value != [0u8; 32] && is_empty_account(runner, address)
+    } else {
+        !runner.state.accounts.contains_key(&address)
+    }
+}
+
+/// Returns `true` if the account doesn't exist or has no nonce, balance and code (EIP-161).
+///
+/// # Arguments
+///
+/// * `runner` - A mutable reference to the `Runner` struct.
+/// * `address` - The address of the account.
+fn is_empty_account(runner: &mut Runner, address: [u8; 20]) -> bool {
+    match runner.state.accounts.get(&address) {
+        Some(account) => {
+            account.nonce == 0
+                && account.balance == [0u8; 32]
+                && runner
+                    .state
+                    .get_code_at(address)
+                    .map_or(true, |code| code.is_empty())
+        }
+        None => true,
+    }
+}
+
 /// Executes a message call with the same value, code, and storage as the calling environment.
 /// This opcode is similar to CALL, but the code at the target address is executed in the context of the current contract,
 /// rather than in the context of a new contract. The target address, value, and input are still taken from the stack.
@@ -381,19 +552,27 @@ pub fn callcode(_: &mut Runner) -> Result<(), ExecutionError> {
 /// * The `static_mode` flag is set to true and `bypass_static` is set to false.
 pub fn delegatecall(runner: &mut Runner) -> Result<(), ExecutionError> {
     // Get the values on the stack
-    let gas = runner.stack.pop()?;
+    let gas = U256::from_big_endian(&runner.stack.pop()?);
     let to = runner.stack.pop()?;
     let calldata_offset = U256::from_big_endian(&runner.stack.pop()?);
     let calldata_size = U256::from_big_endian(&runner.stack.pop()?);
     let returndata_offset = U256::from_big_endian(&runner.stack.pop()?);
     let returndata_size = U256::from_big_endian(&runner.stack.pop()?);
 
-    // Load the input data from memory
-    let calldata = unsafe {
-        runner
-            .memory
-            .read(calldata_offset.as_usize(), calldata_size.as_usize())?
-    };
+    // Check the return data size before running the call
+    let output_size = runner.checked_copy_size(returndata_size)?;
+
+    // Load the input data from memory, then charge the expansion of the output range
+    let (input_offset, input_size) = runner.memory_range(calldata_offset, calldata_size)?;
+    let calldata = runner.memory.read(input_offset, input_size)?;
+    let (output_offset, output_size) =
+        runner.memory_range(returndata_offset, U256::from(output_size))?;
+    let (returndata_offset, returndata_size) = (U256::from(output_offset), U256::from(output_size));
+
+    // Charge the call cost before forwarding gas to the callee
+    let cost = call_cost(runner, bytes32_to_address(&to), [0u8; 32]);
+    runner.decrement_gas(cost)?;
+    let gas = gas.min(U256::from(u64::MAX)).as_u64();
 
     if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
         let address_hex: String = utils::debug::to_hex_address(bytes32_to_address(&to));
@@ -408,57 +587,18 @@ pub fn delegatecall(runner: &mut Runner) -> Result<(), ExecutionError> {
     }
 
     // Call the contract
-    let call_result = runner.call(
+    let kind = CallKind::DelegateCall {
+        returndata_offset,
+        returndata_size,
+    };
+    enter_callee(
+        runner,
         bytes32_to_address(&to),
         [0u8; 32],
         calldata,
-        U256::from_big_endian(&gas).as_u64(),
-        true,
-    );
-
-    if call_result.is_err() {
-        runner.stack.push(pad_left(&[0x00]))?;
-    } else {
-        runner.stack.push(pad_left(&[0x01]))?;
-    }
-
-    let return_data = runner.returndata.heap.clone();
-
-    if runner.debug_level.is_some() && runner.debug_level.unwrap() >= 1 {
-        let address_hex: String = utils::debug::to_hex_address(runner.address);
-        let returndata_hex: String = utils::debug::vec_to_hex_string(return_data.clone());
-        runner.print_debug(&format!(
-            "\n{} {} {}\n  {}: {}\n",
-            if call_result.is_err() {
-                "DELEGATECALL FAILED".red()
-            } else {
-                "DELEGATECALL SUCCEEDED".green()
-            },
-            if call_result.is_err() { "❌" } else { "✅" },
-            address_hex,
-            "Returndata".bright_blue(),
-            returndata_hex
-        ));
-    }
-
-    let mut return_data: Vec<u8> = runner.returndata.heap.clone();
-
-    // Complete return data with zeros if returndata is smaller than returndata_size
-    if return_data.len() < returndata_size.as_usize() {
-        return_data.extend(vec![0; returndata_size.as_usize() - return_data.len()]);
-    }
-
-    return_data = return_data[0..returndata_size.as_usize()].to_vec();
-
-    // Write the return data to memory
-    unsafe {
-        runner
-            .memory
-            .write(returndata_offset.as_usize(), return_data)?
-    };
-
-    // Increment PC
-    runner.increment_pc(1)
+        kind,
+        Some(gas),
+    )
 }
 
 /// Executes a static call operation, which is similar to a regular call operation, but does not allow
@@ -478,16 +618,15 @@ pub fn delegatecall(runner: &mut Runner) -> Result<(), ExecutionError> {
 /// * The `static_mode` flag is set to true and `bypass_static` is set to false.
 /// * The EVM state changes.
 pub fn staticcall(runner: &mut Runner) -> Result<(), ExecutionError> {
-    runner.state.static_mode = true;
-    let result = call(runner, true);
-    runner.state.static_mode = false;
-
-    result
+    call(runner, true)
 }
 
 /// Executes the SELFDESTRUCT opcode, which transfers the balance of the current contract to the
 /// specified address and deletes the current contract's account.
 ///
+/// It costs 5000 gas, plus 2600 if the beneficiary is cold (since Berlin) and 25000 if some
+/// balance is sent to an empty beneficiary.
+///
 /// # Arguments
 ///
 /// * `runner` - A mutable reference to the `Runner` struct.
@@ -503,15 +642,31 @@ pub fn staticcall(runner: &mut Runner) -> Result<(), ExecutionError> {
 pub fn selfdestruct(runner: &mut Runner) -> Result<(), ExecutionError> {
     // Get the values on the stack
     let address = runner.stack.pop()?;
+    let beneficiary = bytes32_to_address(&address);
 
     let contract_balance = get_balance(runner.address, runner)?;
 
+    // Charge the base, cold access and new account costs
+    let schedule = runner.gas_schedule();
+    let mut cost = schedule.selfdestruct;
+    if runner.access_address(beneficiary) {
+        cost += schedule.cold_account_access;
+    }
+    // SELFDESTRUCT pays for creating its beneficiary since Tangerine Whistle (EIP-150)
+    if runner.hardfork.is_enabled(Hardfork::TangerineWhistle)
+        && pays_new_account(runner, beneficiary, contract_balance)
+    {
+        cost += 25000;
+    }
+    runner.decrement_gas(cost)?;
+
+    // Create the beneficiary if needed
+    init_account(beneficiary, runner)?;
+
     // Transfer the balance
-    runner.state.transfer(
-        runner.address,
-        bytes32_to_address(&address),
-        contract_balance,
-    )?;
+    runner
+        .state
+        .transfer(runner.address, beneficiary, contract_balance)?;
 
     // Delete the account
     delete_account(runner.address, runner)?;
@@ -541,8 +696,10 @@ pub fn return_(runner: &mut Runner) -> Result<(), ExecutionError> {
     let offset = U256::from_big_endian(&runner.stack.pop()?);
     let size = U256::from_big_endian(&runner.stack.pop()?);
 
+    let (offset, size) = runner.memory_range(offset, size)?;
+
     // Load the return data from memory
-    let returndata = unsafe { runner.memory.read(offset.as_usize(), size.as_usize())? };
+    let returndata = runner.memory.read(offset, size)?;
 
     // Set the return data
     runner.returndata.heap = returndata;
@@ -559,8 +716,10 @@ pub fn return_(runner: &mut Runner) -> Result<(), ExecutionError> {
 
 #[cfg(test)]
 mod tests {
-    use crate::core_module::runner::Runner;
-    use crate::core_module::utils::bytes::{_hex_string_to_bytes, bytes32_to_address, pad_left};
+    use super::*;
+    use crate::core_module::env::Hardfork;
+    use crate::core_module::runner::{Runner, MAX_CALL_DEPTH};
+    use crate::core_module::utils::bytes::_hex_string_to_bytes;
     use crate::core_module::utils::environment::get_balance;
     use crate::core_module::utils::errors::ExecutionError;
 
@@ -578,6 +737,13 @@ mod tests {
     #[test]
     fn test_create() {
         let mut runner = Runner::_default(3);
+        // The creating contract pays the value of the contract
+        runner
+            .state
+            .accounts
+            .get_mut(&runner.address)
+            .unwrap()
+            .balance = pad_left(&[0xff]);
         let interpret_result: Result<(), ExecutionError> = runner.interpret(
             _hex_string_to_bytes("6c63ffffffff6000526004601cf3600052600d601360fff0"),
             Some(2),
@@ -589,8 +755,8 @@ mod tests {
         assert_eq!(
             result,
             pad_left(&[
-                0x9b, 0xbf, 0xed, 0x68, 0x89, 0x32, 0x2e, 0x01, 0x6e, 0x0a, 0x02, 0xee, 0x45, 0x9d,
-                0x30, 0x6f, 0xc1, 0x95, 0x45, 0xd8
+                0xf4, 0x94, 0xa7, 0x83, 0x32, 0x6a, 0x5f, 0xcc, 0xb5, 0x7a, 0x27, 0x8b, 0xd1, 0x49,
+                0xcf, 0x8b, 0xcd, 0xf8, 0x2f, 0x3c
             ])
         );
 
@@ -600,11 +766,226 @@ mod tests {
 
         let balance = get_balance(bytes32_to_address(&result), &mut runner).unwrap();
         assert_eq!(balance, pad_left(&[0xff]));
+        let address = runner.address;
+        assert_eq!(get_balance(address, &mut runner).unwrap(), [0u8; 32]);
+
+        // A successful creation leaves an empty return data
+        assert!(runner.returndata.heap.is_empty());
+    }
+
+    #[test]
+    fn test_create_endowment_before_constructor() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner
+            .state
+            .accounts
+            .get_mut(&runner.address)
+            .unwrap()
+            .balance = pad_left(&[0x07]);
+
+        // CREATE with 7 wei a contract whose constructor stores its SELFBALANCE at slot 0
+        let bytecode = _hex_string_to_bytes("63475f55005f526004601c6007f0");
+        runner.interpret(bytecode, None, true).unwrap();
+        let contract_address = bytes32_to_address(&runner.stack.pop().unwrap());
+
+        // The constructor saw the value, taken from the creating contract
+        assert_eq!(
+            runner.state.sload(contract_address, [0u8; 32]).unwrap(),
+            pad_left(&[0x07])
+        );
+        let address = runner.address;
+        assert_eq!(get_balance(address, &mut runner).unwrap(), [0u8; 32]);
+        assert_eq!(
+            get_balance(contract_address, &mut runner).unwrap(),
+            pad_left(&[0x07])
+        );
+    }
+
+    #[test]
+    fn test_create_after_set_nonce() {
+        // CREATE a contract whose constructor is a STOP
+        let bytecode = _hex_string_to_bytes("60015f5ff0");
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let nonce = runner.state.nonce(runner.address);
+        runner.interpret(bytecode.clone(), None, true).unwrap();
+        let default_address = bytes32_to_address(&runner.stack.pop().unwrap());
+        assert_eq!(
+            default_address,
+            compute_create_address(runner.address, nonce)
+        );
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.state.set_nonce(runner.address, 42);
+        runner.interpret(bytecode, None, true).unwrap();
+        let address = bytes32_to_address(&runner.stack.pop().unwrap());
+
+        assert_ne!(address, default_address);
+        assert_eq!(address, compute_create_address(runner.address, 42));
+        assert_eq!(runner.state.nonce(runner.address), 43);
+    }
+
+    #[test]
+    fn test_create_reverting_constructor() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let accounts_len = runner.state.accounts.len();
+
+        // The constructor reverts with 0xaa, then RETURNDATASIZE
+        let interpret_result: Result<(), ExecutionError> = runner.interpret(
+            _hex_string_to_bytes("6960aa6000526001601ffd600052600a60166000f03d"),
+            Some(2),
+            true,
+        );
+        assert!(interpret_result.is_ok());
+
+        // RETURNDATASIZE sees the revert data
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, pad_left(&[0x01]));
+        assert_eq!(runner.returndata.heap, vec![0xaa]);
+
+        // CREATE failed
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, pad_left(&[0x00]));
+
+        // No contract was stored
+        assert_eq!(runner.state.accounts.len(), accounts_len);
+    }
+
+    #[test]
+    fn test_create_ef_runtime_code() {
+        // The constructor returns 0xEF as runtime code
+        let bytecode = _hex_string_to_bytes("6960ef6000526001601ff3600052600a60166000f0");
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let accounts_len = runner.state.accounts.len();
+        let interpret_result = runner.interpret(bytecode.clone(), Some(2), true);
+        assert!(interpret_result.is_ok());
+
+        // CREATE failed and no contract was stored
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, pad_left(&[0x00]));
+        assert_eq!(runner.state.accounts.len(), accounts_len);
+        assert!(runner.returndata.heap.is_empty());
+        assert!(!runner.call_tree[0].success);
+        assert_eq!(runner.call_tree[0].deployed_code(), None);
+
+        // Before London, the code is deployed
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.hardfork = Hardfork::Berlin;
+        let interpret_result = runner.interpret(bytecode, Some(2), true);
+        assert!(interpret_result.is_ok());
+
+        let result = runner.stack.pop().unwrap();
+        assert_ne!(result, pad_left(&[0x00]));
+        let stored_code = runner.state.get_code_at(bytes32_to_address(&result));
+        assert_eq!(stored_code.unwrap(), &vec![0xef]);
+    }
+
+    #[test]
+    fn test_create_call_tree() {
+        use crate::core_module::frame::CallType;
+
+        // The init code returns 0xffffffff as runtime code
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let interpret_result = runner.interpret(
+            _hex_string_to_bytes("6c63ffffffff6000526004601cf3600052600d60136000f0"),
+            None,
+            true,
+        );
+        assert!(interpret_result.is_ok());
+
+        let contract = bytes32_to_address(&runner.stack.pop().unwrap());
+        assert_eq!(runner.call_tree.len(), 1);
+        let create = &runner.call_tree[0];
+        assert_eq!(create.call_type, CallType::Create);
+        assert_eq!(create.address, contract);
+        assert_eq!(
+            create.input,
+            _hex_string_to_bytes("63ffffffff6000526004601cf3")
+        );
+        assert!(create.success);
+        assert_eq!(create.deployed_code().map(|code| code.len()), Some(4));
+    }
+
+    #[test]
+    fn test_nested_create() {
+        use crate::core_module::asm::assemble;
+
+        // The child returns a STOP as runtime code
+        let child_init = assemble("PUSH1 0x01\nPUSH0\nRETURN").unwrap();
+        // The parent constructor CREATEs the child and stores its address in slot 0
+        let parent_init = assemble(&format!(
+            "PUSH4 0x{}\nPUSH0\nMSTORE\nPUSH1 0x04\nPUSH1 0x1c\nPUSH0\nCREATE\nPUSH0\nSSTORE\nPUSH1 0x01\nPUSH0\nRETURN",
+            hex::encode(&child_init)
+        ))
+        .unwrap();
+        let bytecode = assemble(&format!(
+            "PUSH19 0x{}\nPUSH0\nMSTORE\nPUSH1 0x13\nPUSH1 0x0d\nPUSH0\nCREATE",
+            hex::encode(&parent_init)
+        ))
+        .unwrap();
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let nonce = runner.state.nonce(runner.address);
+        let interpret_result = runner.interpret(bytecode, None, true);
+        assert!(interpret_result.is_ok());
+
+        // The parent is created with the creator's nonce, and the child with the parent's
+        // nonce, which starts at 1 (EIP-161)
+        let parent = bytes32_to_address(&runner.stack.pop().unwrap());
+        let child = compute_create_address(parent, 1);
+        assert_eq!(parent, compute_create_address(runner.address, nonce));
+        assert_eq!(
+            bytes32_to_address(&runner.state.sload(parent, [0u8; 32]).unwrap()),
+            child
+        );
+
+        // Each creation bumped the nonce of its creator
+        assert_eq!(runner.state.nonce(runner.address), nonce + 1);
+        assert_eq!(runner.state.nonce(parent), 2);
+        assert_eq!(runner.state.nonce(child), 1);
+
+        // Both contracts are deployed, the child's CREATE is nested in the parent's
+        assert_eq!(runner.state.get_code_at(parent).unwrap(), &vec![0x00]);
+        assert_eq!(runner.state.get_code_at(child).unwrap(), &vec![0x00]);
+        assert_eq!(runner.call_tree.len(), 1);
+        assert_eq!(runner.call_tree[0].address, parent);
+        assert_eq!(runner.call_tree[0].children.len(), 1);
+        assert_eq!(runner.call_tree[0].children[0].address, child);
+    }
+
+    #[test]
+    fn test_nested_create_depth_limit() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        // Without the 1/64 rule, the gas doesn't run out before the depth limit
+        runner.hardfork = Hardfork::Homestead;
+
+        // Each constructor CREATEs a copy of itself, until the depth limit
+        let result = runner.interpret(_hex_string_to_bytes("385f5f39385f5ff000"), None, true);
+        assert!(result.is_ok());
+
+        // The constructors of depth 1 to 1024 ran, the CREATE from depth 1024 failed
+        let mut depth = 0;
+        let mut node = &runner.call_tree[0];
+        while let Some(child) = node.children.first() {
+            node = child;
+            depth += 1;
+        }
+        assert_eq!(depth, MAX_CALL_DEPTH - 1);
+        assert!(runner.frames.is_empty());
+        assert_eq!(runner.call_depth, 0);
     }
 
     #[test]
     fn test_create2() {
         let mut runner = Runner::_default(3);
+        // The creating contract pays the value of the contract
+        runner
+            .state
+            .accounts
+            .get_mut(&runner.address)
+            .unwrap()
+            .balance = pad_left(&[0xff]);
         let interpret_result: Result<(), ExecutionError> = runner.interpret(
             _hex_string_to_bytes("6c63ffffffff6000526004601cf360005263aaa4aaaf600d601360aff5"),
             Some(2),
@@ -616,8 +997,8 @@ mod tests {
         assert_eq!(
             result,
             pad_left(&[
-                0x5b, 0xad, 0x4e, 0xb0, 0xa4, 0xc4, 0xcf, 0xb7, 0x7d, 0x6c, 0x3f, 0x9d, 0x56, 0xa8,
-                0x49, 0x03, 0x2f, 0x22, 0x47, 0xd2
+                0xc7, 0xe7, 0x63, 0x33, 0x41, 0x5f, 0x52, 0x9d, 0x91, 0x45, 0xbf, 0x30, 0xa3, 0x11,
+                0xa1, 0x8d, 0x6a, 0xb3, 0xed, 0xf3
             ])
         );
 
@@ -627,6 +1008,225 @@ mod tests {
 
         let balance = get_balance(bytes32_to_address(&result), &mut runner).unwrap();
         assert_eq!(balance, pad_left(&[0xaf]));
+        let address = runner.address;
+        assert_eq!(
+            get_balance(address, &mut runner).unwrap(),
+            pad_left(&[0x50])
+        );
+    }
+
+    #[test]
+    fn test_create_forwards_all_but_one_64th() {
+        use crate::core_module::asm::assemble;
+        use crate::core_module::utils::bytes::bytes32_to_address;
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // The constructor stores the gas it sees: GAS PUSH0 SSTORE STOP
+        let source = "PUSH4 0x5a5f5500\nPUSH0\nMSTORE\nPUSH1 0x04\nPUSH1 0x1c\nPUSH0\nCREATE\nGAS";
+        let result = runner.interpret(assemble(source).unwrap(), None, true);
+        assert!(result.is_ok());
+
+        let gas_after = U256::from_big_endian(&runner.stack.pop().unwrap()).as_u64();
+        let contract = bytes32_to_address(&runner.stack.pop().unwrap());
+        let slot = runner.state.sload(contract, [0u8; 32]).unwrap();
+        let constructor_gas = U256::from_big_endian(&slot).as_u64();
+
+        // The constructor doesn't get the full remaining gas
+        let cap = 30_000_000 - 30_000_000 / 64;
+        assert!(constructor_gas < cap);
+        assert!(constructor_gas > cap - 100);
+
+        // The withheld gas is given back to the caller, minus the constructor cold SSTORE
+        assert!(gas_after > constructor_gas + 30_000_000 / 64 - 2100 - 100);
+    }
+
+    #[test]
+    fn test_call_stipend() {
+        let callee = [0xb2; 20];
+        let forwarded_gas = |value: u8, stipend: bool| {
+            let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+            runner.call_stipend = stipend;
+            runner
+                .state
+                .accounts
+                .get_mut(&runner.address)
+                .unwrap()
+                .balance = pad_left(&[0x01]);
+            init_account(callee, &mut runner).unwrap();
+            runner.state.put_code_at(callee, vec![0x00]).unwrap();
+
+            // CALL the callee with 10000 gas and the given value
+            let mut bytecode = _hex_string_to_bytes("5f5f5f5f60");
+            bytecode.push(value);
+            bytecode.push(0x73);
+            bytecode.extend(callee);
+            bytecode.extend(_hex_string_to_bytes("612710f1"));
+            runner.interpret(bytecode, None, true).unwrap();
+
+            runner.last_call_gas_forwarded()
+        };
+
+        assert_eq!(forwarded_gas(1, true), Some(10000 + 2300));
+        assert_eq!(forwarded_gas(1, false), Some(10000));
+        assert_eq!(forwarded_gas(0, true), Some(10000));
+    }
+
+    #[test]
+    fn test_call_stipend_to_eoa() {
+        let callee = [0xb2; 20];
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner
+            .state
+            .accounts
+            .get_mut(&runner.address)
+            .unwrap()
+            .balance = pad_left(&[0x01]);
+        runner.state.set_nonce(callee, 1);
+
+        // CALL the callee without code with 10000 gas and 1 wei
+        let mut bytecode = _hex_string_to_bytes("5f5f5f5f600173");
+        bytecode.extend(callee);
+        bytecode.extend(_hex_string_to_bytes("612710f1"));
+        let result = runner.interpret(bytecode, Some(2), true);
+        assert!(result.is_ok());
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x01]));
+
+        // The callee got the stipend and gave it back unused
+        assert_eq!(runner.last_call_gas_forwarded(), Some(10000 + 2300));
+        assert_eq!(runner.gas_used(), 17 + 2600 + 9000 - 2300);
+        assert_eq!(get_balance(callee, &mut runner).unwrap(), pad_left(&[0x01]));
+    }
+
+    #[test]
+    fn test_call_returns_unused_gas() {
+        use crate::core_module::asm::assemble;
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let callee = [0xb2; 20];
+
+        // The callee returns the gas it sees
+        init_account(callee, &mut runner).unwrap();
+        let callee_code = assemble("GAS\nPUSH0\nMSTORE\nPUSH1 0x20\nPUSH0\nRETURN").unwrap();
+        runner.state.put_code_at(callee, callee_code).unwrap();
+
+        // The caller forwards 10000 gas to the callee, then reads its own gas
+        let source = format!(
+            "GAS\nPUSH1 0x20\nPUSH0\nPUSH0\nPUSH0\nPUSH0\nPUSH20 0x{}\nPUSH2 0x2710\nCALL\nGAS",
+            hex::encode(callee)
+        );
+        let result = runner.interpret(assemble(&source).unwrap(), None, true);
+        assert!(result.is_ok());
+
+        let gas_after = U256::from_big_endian(&runner.stack.pop().unwrap()).as_u64();
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x01]));
+        let gas_before = U256::from_big_endian(&runner.stack.pop().unwrap()).as_u64();
+        let callee_gas = U256::from_big_endian(&runner.memory.heap[..32]).as_u64();
+
+        // The callee only gets the requested gas
+        assert!(callee_gas < 10000);
+        assert!(callee_gas > 10000 - 10);
+
+        // The callee leftover is given back: the caller only paid the cold call and the callee run
+        let spent = gas_before - gas_after;
+        assert!(spent >= 2600);
+        assert!(spent < 2600 + 100);
+    }
+
+    #[test]
+    fn test_call_exceptional_halt_consumes_gas() {
+        let callee = [0xb2; 20];
+        let call_gas_used = |code: &str| {
+            let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+            init_account(callee, &mut runner).unwrap();
+            runner
+                .state
+                .put_code_at(callee, _hex_string_to_bytes(code))
+                .unwrap();
+
+            // CALL the callee with 10000 gas
+            let mut bytecode = _hex_string_to_bytes("5f5f5f5f5f73");
+            bytecode.extend(callee);
+            bytecode.extend(_hex_string_to_bytes("612710f1"));
+            runner.interpret(bytecode, None, true).unwrap();
+
+            (runner.call_tree[0].gas_used, runner.gas_used())
+        };
+
+        // STOP and REVERT leave the unused gas to the caller, INVALID consumes all of it
+        let (stop, stop_total) = call_gas_used("00");
+        let (revert, revert_total) = call_gas_used("5f5ffd");
+        let (invalid, invalid_total) = call_gas_used("fe");
+        assert_eq!(stop, 0);
+        assert_eq!(revert, 4);
+        assert_eq!(invalid, 10000);
+        assert_eq!(revert_total, stop_total + 4);
+        assert_eq!(invalid_total, stop_total + 10000);
+    }
+
+    #[test]
+    fn test_call_refunds() {
+        let callee = [0xb2; 20];
+        let gas_refund = |code: &str| {
+            let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+            init_account(callee, &mut runner).unwrap();
+            runner
+                .state
+                .sstore(callee, pad_left(&[0x01]), pad_left(&[0x01]))
+                .unwrap();
+            runner
+                .state
+                .put_code_at(callee, _hex_string_to_bytes(code))
+                .unwrap();
+
+            // CALL the callee with 10000 gas
+            let mut bytecode = _hex_string_to_bytes("5f5f5f5f5f73");
+            bytecode.extend(callee);
+            bytecode.extend(_hex_string_to_bytes("612710f1"));
+            runner.interpret(bytecode, None, true).unwrap();
+
+            runner.gas_refund
+        };
+
+        // Clearing slot 1 refunds the caller, unless the callee reverts
+        assert_eq!(gas_refund("5f600155"), 4800);
+        assert_eq!(gas_refund("5f6001555f5ffd"), 0);
+    }
+
+    #[test]
+    fn test_call_before_eip150() {
+        let callee = [0xb2; 20];
+        let run = |hardfork: Hardfork, requested: &str| {
+            let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+            runner.hardfork = hardfork;
+            init_account(callee, &mut runner).unwrap();
+            runner.state.put_code_at(callee, vec![0x00]).unwrap();
+
+            // CALL the callee with the requested gas
+            let mut bytecode = _hex_string_to_bytes("5f5f5f5f5f73");
+            bytecode.extend(callee);
+            bytecode.extend(_hex_string_to_bytes(requested));
+            bytecode.push(0xf1);
+            let result = runner.interpret_with_gas_limit(bytecode, 100_000, None);
+
+            (result, runner.last_call_gas_forwarded())
+        };
+
+        // The callee gets the requested gas, not capped to 63/64 of the remaining gas
+        assert_eq!(run(Hardfork::Homestead, "6201863c"), (Ok(()), Some(99900)));
+
+        // Requesting more gas than left halts the caller
+        assert_eq!(
+            run(Hardfork::Homestead, "620186a0"),
+            (Err(ExecutionError::OutOfGas), None)
+        );
+
+        // Tangerine Whistle caps the requested gas to 63/64 of the gas left after the call cost
+        let left = 100_000 - 16 - 700;
+        assert_eq!(
+            run(Hardfork::TangerineWhistle, "620186a0"),
+            (Ok(()), Some(left - left / 64))
+        );
     }
 
     #[test]
@@ -650,6 +1250,78 @@ mod tests {
         assert!(result == pad_left(&[0x00]));
     }
 
+    #[test]
+    fn test_call_zero_size_out_of_range_offsets() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // CALL an empty account with empty input and output ranges at offset 0x80..00
+        let offset = format!("7f80{}", "00".repeat(31));
+        let interpret_result = runner.interpret(
+            _hex_string_to_bytes(&format!("6000{offset}6000{offset}600061dead5af1")),
+            Some(2),
+            true,
+        );
+        assert!(interpret_result.is_ok());
+
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, pad_left(&[0x01]));
+        assert_eq!(runner.memory.msize(), 0);
+    }
+
+    #[test]
+    fn test_return_zero_size_out_of_range_offset() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // PUSH1 0x00 PUSH32 0x80..00 RETURN: the offset of an empty range is ignored
+        let interpret_result = runner.interpret(
+            _hex_string_to_bytes(&format!("60007f80{}f3", "00".repeat(31))),
+            Some(2),
+            true,
+        );
+        assert!(interpret_result.is_ok());
+        assert!(runner.returndata.heap.is_empty());
+        assert_eq!(runner.memory.msize(), 0);
+    }
+
+    #[test]
+    fn test_call_keeps_full_returndata() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // Return the words 0x05 and 0x07:
+        // PUSH1 0x05 PUSH0 MSTORE PUSH1 0x07 PUSH1 0x20 MSTORE PUSH1 0x40 PUSH0 RETURN
+        init_account([0xcc; 20], &mut runner).unwrap();
+        runner
+            .state
+            .put_code_at(
+                [0xcc; 20],
+                _hex_string_to_bytes("60055f52600760205260405ff3"),
+            )
+            .unwrap();
+
+        // CALL 0xcc..cc copying only 32 bytes of return data, then RETURNDATASIZE
+        let interpret_result = runner.interpret(
+            _hex_string_to_bytes(
+                "6020600060006000600073cccccccccccccccccccccccccccccccccccccccc61fffff13d",
+            ),
+            Some(2),
+            true,
+        );
+        assert!(interpret_result.is_ok());
+
+        // RETURNDATASIZE is the full returned length
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, pad_left(&[0x40]));
+        let mut returndata = [0u8; 64];
+        returndata[31] = 0x05;
+        returndata[63] = 0x07;
+        assert_eq!(runner.returndata.heap, returndata.to_vec());
+
+        // Only the first word was written to memory
+        let memory = runner.memory.read(0, 64).unwrap();
+        assert_eq!(memory[..32], pad_left(&[0x05]));
+        assert_eq!(memory[32..], [0u8; 32]);
+    }
+
     #[test]
     fn test_callcode() {
         let mut runner = Runner::_default(3);
@@ -685,6 +1357,246 @@ mod tests {
         assert!(result == pad_left(&[0x00]));
     }
 
+    #[test]
+    fn test_delegatecall_keeps_callvalue() {
+        use crate::core_module::asm::assemble;
+
+        let value = pad_left(&[0x07]);
+        let mut runner = Runner::new([0xaa; 20], None, None, Some(value), None, None);
+        let callee = [0xb2; 20];
+
+        // The callee returns its CALLVALUE
+        init_account(callee, &mut runner).unwrap();
+        let callee_code = assemble("CALLVALUE\nPUSH0\nMSTORE\nPUSH1 0x20\nPUSH0\nRETURN").unwrap();
+        runner.state.put_code_at(callee, callee_code).unwrap();
+
+        let source = format!(
+            "PUSH1 0x20\nPUSH0\nPUSH0\nPUSH0\nPUSH20 0x{}\nGAS\nDELEGATECALL",
+            hex::encode(callee)
+        );
+        let result = runner.interpret(assemble(&source).unwrap(), None, true);
+        assert!(result.is_ok());
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x01]));
+
+        // The callee sees the value of the delegate caller, and no value was transferred
+        assert_eq!(runner.memory.heap[..32], value);
+        assert_eq!(runner.callvalue, value);
+        assert_eq!(get_balance(callee, &mut runner).unwrap(), [0u8; 32]);
+    }
+
+    #[test]
+    fn test_call_new_account_cost() {
+        // CALL 0xcc..cc with 1 wei
+        let bytecode = _hex_string_to_bytes(
+            "6000600060006000600173cccccccccccccccccccccccccccccccccccccccc61fffff1",
+        );
+
+        // The callee doesn't exist
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let _ = runner.interpret(bytecode.clone(), Some(2), true);
+        let fresh_gas_used = runner.gas_used();
+
+        // The callee has some balance
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        init_account([0xcc; 20], &mut runner).unwrap();
+        runner.state.accounts.get_mut(&[0xcc; 20]).unwrap().balance = pad_left(&[0x01]);
+        let _ = runner.interpret(bytecode, Some(2), true);
+        let existing_gas_used = runner.gas_used();
+
+        assert_eq!(fresh_gas_used - existing_gas_used, 25000);
+        assert!(existing_gas_used >= 2600 + 9000);
+    }
+
+    #[test]
+    fn test_call_new_account_cost_before_eip161() {
+        // CALL 0xcc..cc without value
+        let bytecode = _hex_string_to_bytes(
+            "6000600060006000600073cccccccccccccccccccccccccccccccccccccccc61fffff1",
+        );
+        let gas_used = |hardfork: Hardfork, exists: bool| {
+            let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+            runner.hardfork = hardfork;
+            if exists {
+                runner.state.set_nonce([0xcc; 20], 0);
+            }
+            runner.interpret(bytecode.clone(), Some(2), true).unwrap();
+            runner.gas_used()
+        };
+
+        // Before Spurious Dragon, calling an account that doesn't exist creates it
+        assert_eq!(
+            gas_used(Hardfork::TangerineWhistle, false)
+                - gas_used(Hardfork::TangerineWhistle, true),
+            25000
+        );
+        assert_eq!(
+            gas_used(Hardfork::SpuriousDragon, false),
+            gas_used(Hardfork::SpuriousDragon, true)
+        );
+    }
+
+    #[test]
+    fn test_call_empty_account_cost() {
+        // CALL 0xcc..cc with 1 wei
+        let bytecode = _hex_string_to_bytes(
+            "6000600060006000600173cccccccccccccccccccccccccccccccccccccccc61fffff1",
+        );
+        let gas_used = |setup: &dyn Fn(&mut Runner)| {
+            let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+            runner
+                .state
+                .accounts
+                .get_mut(&runner.address)
+                .unwrap()
+                .balance = pad_left(&[0x02]);
+            setup(&mut runner);
+            let result = runner.interpret(bytecode.clone(), Some(2), true);
+            assert!(result.is_ok());
+
+            // The call succeeded and moved the value
+            assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x01]));
+            let address = runner.address;
+            assert_eq!(
+                get_balance(address, &mut runner).unwrap(),
+                pad_left(&[0x01])
+            );
+            assert_eq!(
+                get_balance([0xcc; 20], &mut runner).unwrap(),
+                pad_left(&[0x01])
+            );
+
+            runner.gas_used()
+        };
+
+        // An existing account without nonce, balance and code is empty (EIP-161)
+        let empty = gas_used(&|runner| runner.state.set_nonce([0xcc; 20], 0));
+        // An account with code only is not empty
+        let with_code = gas_used(&|runner| {
+            runner.state.set_nonce([0xcc; 20], 0);
+            runner.state.put_code_at([0xcc; 20], vec![0x00]).unwrap();
+        });
+        // An account with a nonce only is not empty
+        let with_nonce = gas_used(&|runner| runner.state.set_nonce([0xcc; 20], 1));
+
+        assert_eq!(empty - with_nonce, 25000);
+        assert_eq!(with_code, with_nonce);
+    }
+
+    #[test]
+    fn test_call_value_reverted() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner
+            .state
+            .accounts
+            .get_mut(&runner.address)
+            .unwrap()
+            .balance = pad_left(&[0x01]);
+        // PUSH0 PUSH0 REVERT
+        init_account([0xcc; 20], &mut runner).unwrap();
+        runner
+            .state
+            .put_code_at([0xcc; 20], _hex_string_to_bytes("5f5ffd"))
+            .unwrap();
+
+        // CALL 0xcc..cc with 1 wei
+        let result = runner.interpret(
+            _hex_string_to_bytes(
+                "6000600060006000600173cccccccccccccccccccccccccccccccccccccccc61fffff1",
+            ),
+            Some(2),
+            true,
+        );
+        assert!(result.is_ok());
+
+        // The failed call gives the value back to the caller
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x00]));
+        let address = runner.address;
+        assert_eq!(
+            get_balance(address, &mut runner).unwrap(),
+            pad_left(&[0x01])
+        );
+        assert_eq!(get_balance([0xcc; 20], &mut runner).unwrap(), [0u8; 32]);
+    }
+
+    #[test]
+    fn test_call_state_reverted() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner
+            .state
+            .accounts
+            .get_mut(&runner.address)
+            .unwrap()
+            .balance = pad_left(&[0x01]);
+        // SSTORE 0x2a at slot 1, LOG0, CALL 0xdd..dd with 1 wei, then PUSH0 PUSH0 REVERT
+        init_account([0xcc; 20], &mut runner).unwrap();
+        runner
+            .state
+            .sstore([0xcc; 20], pad_left(&[0x01]), pad_left(&[0x07]))
+            .unwrap();
+        runner
+            .state
+            .put_code_at(
+                [0xcc; 20],
+                _hex_string_to_bytes(
+                    "602a6001555f5fa05f5f5f5f600173dddddddddddddddddddddddddddddddddddddddd5af1505f5ffd",
+                ),
+            )
+            .unwrap();
+
+        // CALL 0xcc..cc with 1 wei
+        let result = runner.interpret(
+            _hex_string_to_bytes(
+                "6000600060006000600173cccccccccccccccccccccccccccccccccccccccc5af1",
+            ),
+            Some(2),
+            true,
+        );
+        assert!(result.is_ok());
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x00]));
+
+        // The storage write, the log, the value transfer and the account created by the
+        // callee are undone
+        assert_eq!(
+            runner.state.sload([0xcc; 20], pad_left(&[0x01])).unwrap(),
+            pad_left(&[0x07])
+        );
+        assert!(runner.state.logs.is_empty());
+        let address = runner.address;
+        assert_eq!(
+            get_balance(address, &mut runner).unwrap(),
+            pad_left(&[0x01])
+        );
+        assert_eq!(get_balance([0xcc; 20], &mut runner).unwrap(), [0u8; 32]);
+        assert!(!runner.state.accounts.contains_key(&[0xdd; 20]));
+    }
+
+    #[test]
+    fn test_call_warm_access_cost() {
+        // STATICCALL 0xcc..cc twice
+        let staticcall = "600060006000600073cccccccccccccccccccccccccccccccccccccccc61fffffa";
+
+        let new_runner = || {
+            let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+            init_account([0xcc; 20], &mut runner).unwrap();
+            runner.state.put_code_at([0xcc; 20], vec![0x00]).unwrap();
+            runner
+        };
+
+        let mut runner = new_runner();
+        let _ = runner.interpret(_hex_string_to_bytes(staticcall), Some(2), true);
+        let cold_gas_used = runner.gas_used();
+
+        let mut runner = new_runner();
+        let _ = runner.interpret(
+            _hex_string_to_bytes(&format!("{}{}", staticcall, staticcall)),
+            Some(2),
+            true,
+        );
+        let twice_gas_used = runner.gas_used();
+
+        assert_eq!(twice_gas_used - cold_gas_used, cold_gas_used - 2600 + 100);
+    }
+
     #[test]
     fn test_staticcall() {
         let mut runner = Runner::_default(3);
@@ -706,9 +1618,233 @@ mod tests {
         assert!(result == pad_left(&[0x00]));
     }
 
+    #[test]
+    fn test_selfdestruct_cost() {
+        // PUSH20 0xcc..cc SELFDESTRUCT
+        let bytecode = _hex_string_to_bytes("73ccccccccccccccccccccccccccccccccccccccccff");
+
+        // Cold and empty beneficiary
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner
+            .state
+            .accounts
+            .get_mut(&runner.address)
+            .unwrap()
+            .balance = pad_left(&[0x01]);
+        let result = runner.interpret(bytecode, Some(2), true);
+        assert!(result.is_ok());
+
+        assert_eq!(runner.gas_used(), 3 + 5000 + 2600 + 25000);
+        let balance = get_balance([0xcc; 20], &mut runner).unwrap();
+        assert_eq!(balance, pad_left(&[0x01]));
+
+        // Warm and existing beneficiary (the caller)
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner
+            .state
+            .accounts
+            .get_mut(&runner.address)
+            .unwrap()
+            .balance = pad_left(&[0x01]);
+        let result = runner.interpret(
+            _hex_string_to_bytes("73aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaff"),
+            Some(2),
+            true,
+        );
+        assert!(result.is_ok());
+
+        assert_eq!(runner.gas_used(), 3 + 5000);
+    }
+
+    #[test]
+    fn test_deep_recursion() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        // Without the 1/64 rule, the gas doesn't run out before the depth limit
+        runner.hardfork = Hardfork::Homestead;
+
+        // Increment slot 0, then CALL itself with all the gas but 100, until the depth limit
+        let result = runner.interpret(
+            _hex_string_to_bytes("5f546001015f555f5f5f5f5f3060645a03f100"),
+            None,
+            true,
+        );
+        assert!(result.is_ok());
+
+        // Depth 0 to 1024 ran, the call from depth 1024 failed
+        let slot = runner.state.sload(runner.address, [0u8; 32]).unwrap();
+        assert_eq!(slot, pad_left(&[0x04, 0x01]));
+        assert!(runner.frames.is_empty());
+        assert_eq!(runner.call_depth, 0);
+        assert_eq!(runner.stack.stack, vec![pad_left(&[0x01])]);
+    }
+
+    #[test]
+    fn test_call_tree() {
+        use crate::core_module::asm::assemble;
+        use crate::core_module::frame::CallType;
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let (a, b) = ([0xa1; 20], [0xb2; 20]);
+
+        // B returns 0x2a
+        init_account(b, &mut runner).unwrap();
+        let b_code = assemble("PUSH1 0x2a\nPUSH0\nMSTORE\nPUSH1 0x20\nPUSH0\nRETURN").unwrap();
+        runner.state.put_code_at(b, b_code).unwrap();
+
+        // A DELEGATECALLs then STATICCALLs B
+        init_account(a, &mut runner).unwrap();
+        let call_b = |opcode: &str| {
+            format!(
+                "PUSH0\nPUSH0\nPUSH0\nPUSH0\nPUSH20 0x{}\nGAS\n{}\n",
+                hex::encode(b),
+                opcode
+            )
+        };
+        let a_code = assemble(&(call_b("DELEGATECALL") + &call_b("STATICCALL") + "STOP")).unwrap();
+        runner.state.put_code_at(a, a_code).unwrap();
+
+        // CALL A with some calldata
+        let source = format!(
+            "PUSH1 0x01\nPUSH0\nMSTORE\nPUSH0\nPUSH0\nPUSH1 0x01\nPUSH1 0x1f\nPUSH0\nPUSH20 0x{}\nGAS\nCALL",
+            hex::encode(a)
+        );
+        let result = runner.interpret(assemble(&source).unwrap(), None, true);
+        assert!(result.is_ok());
+
+        assert_eq!(runner.call_tree.len(), 1);
+        let root = &runner.call_tree[0];
+        assert_eq!(root.call_type, CallType::Call);
+        assert_eq!(root.address, a);
+        assert_eq!(root.input, vec![0x01]);
+        assert!(root.success);
+        assert!(root.gas_used > 0);
+
+        let types: Vec<CallType> = root.children.iter().map(|call| call.call_type).collect();
+        assert_eq!(types, vec![CallType::DelegateCall, CallType::StaticCall]);
+        for child in &root.children {
+            assert_eq!(child.address, b);
+            assert_eq!(child.output, pad_left(&[0x2a]).to_vec());
+            assert!(child.success);
+            assert!(child.children.is_empty());
+            assert!(child.gas_used < root.gas_used);
+        }
+    }
+
+    #[test]
+    fn test_reentrant_calls() {
+        use crate::core_module::asm::assemble;
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let (a, b) = (runner.address, [0xb2; 20]);
+
+        // B calls back into its caller the first time it is called
+        init_account(b, &mut runner).unwrap();
+        let b_code = assemble(
+            "PUSH0\nSLOAD\nPUSH1 0x12\nJUMPI\nPUSH1 0x01\nPUSH0\nSSTORE\n\
+             PUSH0\nPUSH0\nPUSH0\nPUSH0\nPUSH0\nCALLER\nGAS\nCALL\nPOP\nJUMPDEST\nSTOP",
+        )
+        .unwrap();
+        runner.state.put_code_at(b, b_code).unwrap();
+
+        // A calls B
+        let a_code = assemble(&format!(
+            "PUSH0\nPUSH0\nPUSH0\nPUSH0\nPUSH0\nPUSH20 0x{}\nGAS\nCALL\nSTOP",
+            hex::encode(b)
+        ))
+        .unwrap();
+        let result = runner.run(a_code, None);
+
+        assert!(result.outcome.is_success());
+        assert!(result.is_reentrant());
+        assert_eq!(result.reentrant_calls, vec![(a, b)]);
+
+        // The nested call A -> B -> A -> B was made
+        let root = &runner.call_tree[0];
+        assert_eq!(root.children[0].children[0].address, b);
+    }
+
+    #[test]
+    fn test_nested_staticcall_keeps_static_mode() {
+        use crate::core_module::asm::assemble;
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let (b, c) = ([0xb2; 20], [0xc3; 20]);
+        let staticcall = |to: [u8; 20]| {
+            format!(
+                "PUSH0\nPUSH0\nPUSH0\nPUSH0\nPUSH20 0x{}\nGAS\nSTATICCALL\n",
+                hex::encode(to)
+            )
+        };
+
+        // C does nothing
+        init_account(c, &mut runner).unwrap();
+        runner.state.put_code_at(c, vec![0x00]).unwrap();
+
+        // B STATICCALLs C, then writes to its storage
+        init_account(b, &mut runner).unwrap();
+        let b_code = assemble(&(staticcall(c) + "POP\nPUSH1 0x01\nPUSH0\nSSTORE\nSTOP")).unwrap();
+        runner.state.put_code_at(b, b_code).unwrap();
+
+        // STATICCALL B
+        let result = runner.interpret(assemble(&staticcall(b)).unwrap(), None, true);
+        assert!(result.is_ok());
+
+        // The inner call returned to a static context, so the SSTORE failed
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x00]));
+        let call_b = &runner.call_tree[0];
+        assert!(!call_b.success);
+        assert!(call_b.children[0].success);
+        assert!(runner.state.accounts[&b].storage.is_empty());
+
+        // The static mode ended with the outer call
+        assert!(!runner.state.static_mode);
+    }
+
+    #[test]
+    fn test_account_hooks() {
+        use std::sync::{Arc, Mutex};
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        let created = Arc::new(Mutex::new(Vec::new()));
+        let destroyed = Arc::new(Mutex::new(Vec::new()));
+        let created_log = created.clone();
+        let destroyed_log = destroyed.clone();
+        runner.state.on_account_created = Some(Arc::new(move |address| {
+            created_log.lock().unwrap().push(address)
+        }));
+        runner.state.on_account_destroyed = Some(Arc::new(move |address| {
+            destroyed_log.lock().unwrap().push(address)
+        }));
+
+        // CREATE a contract with CALLER SELFDESTRUCT as code, then CALL it
+        let interpret_result = runner.interpret(
+            _hex_string_to_bytes(
+                "6a6133ff6000526002601ef3600052600b60156000f0600060006000600060008561fffff1",
+            ),
+            Some(2),
+            true,
+        );
+        assert!(interpret_result.is_ok());
+
+        let result = runner.stack.pop().unwrap();
+        assert_eq!(result, pad_left(&[0x01]));
+        let contract_address = bytes32_to_address(&runner.stack.pop().unwrap());
+
+        assert_eq!(*created.lock().unwrap(), vec![contract_address]);
+        assert_eq!(*destroyed.lock().unwrap(), vec![contract_address]);
+    }
+
     #[test]
     fn test_selfdestruct() {
         let mut runner = Runner::_default(3);
+        // The creating contract pays the value of the contract
+        runner
+            .state
+            .accounts
+            .get_mut(&runner.address)
+            .unwrap()
+            .balance = pad_left(&[0xff]);
 
         // Create a contract that has ff as code
         let interpret_result: Result<(), ExecutionError> = runner.interpret(
@@ -722,8 +1858,8 @@ mod tests {
         assert_eq!(
             address,
             pad_left(&[
-                0x9b, 0xbf, 0xed, 0x68, 0x89, 0x32, 0x2e, 0x01, 0x6e, 0x0a, 0x02, 0xee, 0x45, 0x9d,
-                0x30, 0x6f, 0xc1, 0x95, 0x45, 0xd8
+                0xf4, 0x94, 0xa7, 0x83, 0x32, 0x6a, 0x5f, 0xcc, 0xb5, 0x7a, 0x27, 0x8b, 0xd1, 0x49,
+                0xcf, 0x8b, 0xcd, 0xf8, 0x2f, 0x3c
             ])
         );
 
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+
+use super::utils::errors::ExecutionError;
+
+/* -------------------------------------------------------------------------- */
+/*                            StateProvider trait                             */
+/* -------------------------------------------------------------------------- */
+
+/// A source of chain state used by `EvmState` for the accounts it doesn't know yet (forking).
+pub trait StateProvider {
+    /// Returns the code of an account (empty for accounts without code).
+    fn get_code(&self, address: [u8; 20]) -> Result<Vec<u8>, ExecutionError>;
+
+    /// Returns the balance of an account.
+    fn get_balance(&self, address: [u8; 20]) -> Result<[u8; 32], ExecutionError>;
+
+    /// Returns the nonce of an account.
+    fn get_nonce(&self, address: [u8; 20]) -> Result<u64, ExecutionError>;
+
+    /// Returns the value of a storage slot of an account.
+    fn get_storage_at(&self, address: [u8; 20], slot: [u8; 32])
+        -> Result<[u8; 32], ExecutionError>;
+
+    /// Returns the hash of a block.
+    fn get_block_hash(&self, number: u64) -> Result<[u8; 32], ExecutionError>;
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            JSON-RPC implementation                         */
+/* -------------------------------------------------------------------------- */
+
+/// Blocks on a provider request and maps its error to an `ExecutionError`.
+fn block_on<T, E: std::fmt::Display>(
+    future: impl std::future::Future<Output = Result<T, E>>,
+) -> Result<T, ExecutionError> {
+    tokio::runtime::Runtime::new()
+        .expect("Could not create a Runtime")
+        .block_on(future)
+        .map_err(|error| ExecutionError::ProviderError(error.to_string()))
+}
+
+/// Fetches the state from a JSON-RPC node with ethers.
+impl StateProvider for Provider<Http> {
+    fn get_code(&self, address: [u8; 20]) -> Result<Vec<u8>, ExecutionError> {
+        let code = block_on(Middleware::get_code(self, Address::from(address), None))?;
+        Ok(code.to_vec())
+    }
+
+    fn get_balance(&self, address: [u8; 20]) -> Result<[u8; 32], ExecutionError> {
+        let balance = block_on(Middleware::get_balance(self, Address::from(address), None))?;
+
+        let mut bytes = [0u8; 32];
+        balance.to_big_endian(&mut bytes);
+        Ok(bytes)
+    }
+
+    fn get_nonce(&self, address: [u8; 20]) -> Result<u64, ExecutionError> {
+        let nonce = block_on(self.get_transaction_count(Address::from(address), None))?;
+        Ok(nonce.as_u64())
+    }
+
+    fn get_storage_at(
+        &self,
+        address: [u8; 20],
+        slot: [u8; 32],
+    ) -> Result<[u8; 32], ExecutionError> {
+        let value = block_on(Middleware::get_storage_at(
+            self,
+            Address::from(address),
+            H256::from(&slot),
+            None,
+        ))?;
+        Ok(value.to_fixed_bytes())
+    }
+
+    fn get_block_hash(&self, number: u64) -> Result<[u8; 32], ExecutionError> {
+        let block = block_on(self.get_block(number))?;
+
+        block
+            .and_then(|block| block.hash)
+            .map(|hash| hash.to_fixed_bytes())
+            .ok_or_else(|| ExecutionError::ProviderError(format!("Block {} not found", number)))
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                            MockProvider struct                             */
+/* -------------------------------------------------------------------------- */
+
+/// An in-memory state provider, to test the fork fallbacks without a live node.
+/// Like a node, it returns empty values for unknown accounts, slots and blocks.
+#[derive(Debug, Clone, Default)]
+pub struct MockProvider {
+    /// The code of each account.
+    pub codes: HashMap<[u8; 20], Vec<u8>>,
+    /// The balance of each account.
+    pub balances: HashMap<[u8; 20], [u8; 32]>,
+    /// The nonce of each account.
+    pub nonces: HashMap<[u8; 20], u64>,
+    /// The storage slots of each account.
+    pub storage: HashMap<([u8; 20], [u8; 32]), [u8; 32]>,
+    /// The hash of each block.
+    pub block_hashes: HashMap<u64, [u8; 32]>,
+}
+
+/// Reads the state from the mock maps.
+impl StateProvider for MockProvider {
+    fn get_code(&self, address: [u8; 20]) -> Result<Vec<u8>, ExecutionError> {
+        Ok(self.codes.get(&address).cloned().unwrap_or_default())
+    }
+
+    fn get_balance(&self, address: [u8; 20]) -> Result<[u8; 32], ExecutionError> {
+        Ok(self.balances.get(&address).copied().unwrap_or([0u8; 32]))
+    }
+
+    fn get_nonce(&self, address: [u8; 20]) -> Result<u64, ExecutionError> {
+        Ok(self.nonces.get(&address).copied().unwrap_or(0))
+    }
+
+    fn get_storage_at(
+        &self,
+        address: [u8; 20],
+        slot: [u8; 32],
+    ) -> Result<[u8; 32], ExecutionError> {
+        Ok(self
+            .storage
+            .get(&(address, slot))
+            .copied()
+            .unwrap_or([0u8; 32]))
+    }
+
+    fn get_block_hash(&self, number: u64) -> Result<[u8; 32], ExecutionError> {
+        Ok(self.block_hashes.get(&number).copied().unwrap_or([0u8; 32]))
+    }
+}
@@ -0,0 +1,303 @@
+use ethers::abi::{decode, ParamType, Token};
+
+use super::state::Log;
+use super::utils::errors::ExecutionError;
+
+/* -------------------------------------------------------------------------- */
+/*                           ExecutionOutcome enum                            */
+/* -------------------------------------------------------------------------- */
+
+/// Classifies how an execution ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionOutcome {
+    /// The execution completed without error (STOP, RETURN or end of the bytecode).
+    Success,
+    /// The execution was intentionally reverted by the contract, with the revert data.
+    Revert(Vec<u8>),
+    /// The execution was halted by an interpreter error (invalid opcode, stack underflow...).
+    Halt(ExecutionError),
+}
+
+/// Implementation of the ExecutionOutcome enum.
+impl ExecutionOutcome {
+    /// Returns `true` if the execution completed successfully.
+    pub fn is_success(&self) -> bool {
+        matches!(self, ExecutionOutcome::Success)
+    }
+
+    /// Returns `true` if the execution was reverted by the contract.
+    pub fn is_revert(&self) -> bool {
+        matches!(self, ExecutionOutcome::Revert(_))
+    }
+
+    /// Returns `true` if the execution was halted by an interpreter error.
+    pub fn is_halt(&self) -> bool {
+        matches!(self, ExecutionOutcome::Halt(_))
+    }
+
+    /// Returns the reason of a revert, if the revert data is an `Error(string)`.
+    pub fn revert_reason(&self) -> Option<String> {
+        match self {
+            ExecutionOutcome::Revert(data) => decode_revert_reason(data),
+            _ => None,
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                              Revert reasons                                */
+/* -------------------------------------------------------------------------- */
+
+/// The selector of `Error(string)`, used by `revert("reason")` and `require(cond, "reason")`.
+pub const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Decodes the reason of a revert data encoded as `Error(string)`.
+///
+/// # Arguments
+///
+/// * `data` - The revert data.
+///
+/// # Returns
+///
+/// Returns the reason, or `None` if the data is not a valid `Error(string)`.
+///
+/// # Examples
+///
+/// ```
+/// use ethers::abi::{encode, Token};
+/// use evm_rs_emulator::decode_revert_reason;
+///
+/// let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+/// data.extend(encode(&[Token::String("nope".to_string())]));
+/// assert_eq!(decode_revert_reason(&data), Some("nope".to_string()));
+/// assert_eq!(decode_revert_reason(&[0xde, 0xad]), None);
+/// ```
+pub fn decode_revert_reason(data: &[u8]) -> Option<String> {
+    if data.len() < 4 || data[..4] != ERROR_STRING_SELECTOR {
+        return None;
+    }
+
+    match decode(&[ParamType::String], &data[4..]).ok()?.pop()? {
+        Token::String(reason) => Some(reason),
+        _ => None,
+    }
+}
+
+/// Maps the result of an interpretation to an outcome.
+/// `Revert` and `RevertWithoutData` errors become a `Revert`, every other error becomes a `Halt`.
+impl From<Result<(), ExecutionError>> for ExecutionOutcome {
+    fn from(result: Result<(), ExecutionError>) -> Self {
+        match result {
+            Ok(()) => ExecutionOutcome::Success,
+            Err(ExecutionError::Revert(data)) => ExecutionOutcome::Revert(data),
+            Err(ExecutionError::RevertWithoutData) => ExecutionOutcome::Revert(Vec::new()),
+            Err(error) => ExecutionOutcome::Halt(error),
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                           ExecutionResult struct                           */
+/* -------------------------------------------------------------------------- */
+
+/// Represents the result of a complete execution.
+#[derive(Debug)]
+pub struct ExecutionResult {
+    /// How the execution ended.
+    pub outcome: ExecutionOutcome,
+    /// The return data (or revert data) left by the execution.
+    pub return_data: Vec<u8>,
+    /// The logs emitted by the execution.
+    pub logs: Vec<Log>,
+    /// The amount of gas consumed by the execution.
+    pub gas_used: u64,
+    /// The stack left by the execution, bottom first.
+    pub stack: Vec<[u8; 32]>,
+    /// The `(caller, callee)` pairs called again while a call between them was still active,
+    /// a heuristic hint of reentrancy.
+    pub reentrant_calls: Vec<([u8; 20], [u8; 20])>,
+    /// The unknown accounts read without a provider, whose balance or code defaulted to empty.
+    pub missing_accounts: Vec<[u8; 20]>,
+    /// The unset storage slots read without a provider, which defaulted to zero.
+    pub missing_slots: Vec<([u8; 20], [u8; 32])>,
+}
+
+/// Implementation of the ExecutionResult struct.
+impl ExecutionResult {
+    /// Returns `true` if a reentrant call was made during the execution.
+    pub fn is_reentrant(&self) -> bool {
+        !self.reentrant_calls.is_empty()
+    }
+
+    /// Returns `true` if the execution read some state missing locally without a provider to
+    /// fetch it, a hint that a fork or a state override was needed.
+    pub fn has_missing_reads(&self) -> bool {
+        !self.missing_accounts.is_empty() || !self.missing_slots.is_empty()
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                               RunDiff struct                               */
+/* -------------------------------------------------------------------------- */
+
+/// The differences between two executions, e.g. before and after a gas optimization.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunDiff {
+    /// The gas used by the second execution minus the gas used by the first one.
+    pub gas_delta: i64,
+    /// Whether both executions ended the same way.
+    pub outcome_matches: bool,
+    /// Whether both executions left the same return data.
+    pub return_data_matches: bool,
+    /// The number of logs of the second execution minus the number of logs of the first one.
+    pub log_count_delta: i64,
+}
+
+/// Implementation of the RunDiff struct.
+impl RunDiff {
+    /// Returns `true` if both executions behaved the same, whatever the gas they used.
+    pub fn is_equivalent(&self) -> bool {
+        self.outcome_matches && self.return_data_matches && self.log_count_delta == 0
+    }
+}
+
+/// Compares two executions.
+///
+/// # Arguments
+///
+/// * `a` - The first execution, e.g. of the original code.
+/// * `b` - The second execution, e.g. of the optimized code.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::{diff_runs, Runner};
+///
+/// // PUSH1 0x01 PUSH1 0x02
+/// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+/// let a = runner.run(vec![0x60, 0x01, 0x60, 0x02], None);
+/// // PUSH1 0x01
+/// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+/// let b = runner.run(vec![0x60, 0x01], None);
+///
+/// let diff = diff_runs(&a, &b);
+/// assert_eq!(diff.gas_delta, -3);
+/// assert!(diff.is_equivalent());
+/// ```
+pub fn diff_runs(a: &ExecutionResult, b: &ExecutionResult) -> RunDiff {
+    RunDiff {
+        gas_delta: b.gas_used as i64 - a.gas_used as i64,
+        outcome_matches: a.outcome == b.outcome,
+        return_data_matches: a.return_data == b.return_data,
+        log_count_delta: b.logs.len() as i64 - a.logs.len() as i64,
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                           SimulationResult struct                          */
+/* -------------------------------------------------------------------------- */
+
+/// A storage slot value changed by a simulated call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageChange {
+    pub address: [u8; 20],
+    pub slot: [u8; 32],
+    /// The value before the call (zero if unset).
+    pub before: [u8; 32],
+    /// The value the call would have written.
+    pub after: [u8; 32],
+}
+
+/// An account balance changed by a simulated call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalanceChange {
+    pub address: [u8; 20],
+    /// The balance before the call (zero for a new account).
+    pub before: [u8; 32],
+    /// The balance after the call (zero for a destroyed account).
+    pub after: [u8; 32],
+}
+
+/// Represents the result of a call simulated by `Runner::simulate_call`, whose state changes
+/// were rolled back.
+#[derive(Debug)]
+pub struct SimulationResult {
+    /// How the call ended.
+    pub outcome: ExecutionOutcome,
+    /// The return data (or revert data) of the call.
+    pub return_data: Vec<u8>,
+    /// The logs the call would have emitted.
+    pub logs: Vec<Log>,
+    /// The amount of gas consumed by the call.
+    pub gas_used: u64,
+    /// The storage slots the call would have changed, sorted by address and slot.
+    pub storage_changes: Vec<StorageChange>,
+    /// The balances the call would have changed, sorted by address.
+    pub balance_changes: Vec<BalanceChange>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_module::runner::Runner;
+    use crate::core_module::utils::bytes::_hex_string_to_bytes;
+
+    #[test]
+    fn test_run_success() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let result = runner.run(_hex_string_to_bytes("600160020100"), None);
+
+        assert_eq!(result.outcome, ExecutionOutcome::Success);
+        assert!(result.outcome.is_success());
+        assert!(result.return_data.is_empty());
+    }
+
+    #[test]
+    fn test_run_revert_with_data() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let result = runner.run(_hex_string_to_bytes("60aa6000526001601ffd"), None);
+
+        assert_eq!(result.outcome, ExecutionOutcome::Revert(vec![0xaa]));
+        assert!(result.outcome.is_revert());
+        assert_eq!(result.return_data, vec![0xaa]);
+        // Not an Error(string)
+        assert_eq!(result.outcome.revert_reason(), None);
+    }
+
+    #[test]
+    fn test_run_invalid_opcode_halt() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let result = runner.run(_hex_string_to_bytes("6001fe"), None);
+
+        assert_eq!(
+            result.outcome,
+            ExecutionOutcome::Halt(ExecutionError::InvalidOpcode(0xfe))
+        );
+        assert!(result.outcome.is_halt());
+    }
+
+    #[test]
+    fn test_diff_runs() {
+        // PUSH1 0x01 PUSH1 0x02 ADD PUSH0 MSTORE PUSH1 0x20 PUSH0 RETURN
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let a = runner.run(_hex_string_to_bytes("60016002015f5260205ff3"), None);
+        // PUSH1 0x03 PUSH0 MSTORE PUSH0 PUSH0 LOG0 PUSH1 0x20 PUSH0 RETURN
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let b = runner.run(_hex_string_to_bytes("60035f525f5fa060205ff3"), None);
+
+        let diff = diff_runs(&a, &b);
+        assert!(diff.outcome_matches);
+        assert!(diff.return_data_matches);
+        assert_eq!(diff.log_count_delta, 1);
+        assert_ne!(diff.gas_delta, 0);
+        assert!(!diff.is_equivalent());
+
+        // The same output for less gas
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let c = runner.run(_hex_string_to_bytes("60035f5260205ff3"), None);
+
+        let diff = diff_runs(&a, &c);
+        assert!(diff.is_equivalent());
+        assert!(diff.gas_delta < 0);
+    }
+}
@@ -1,27 +1,83 @@
-use crate::core_module::utils::bytes::pad_left;
+use crate::core_module::utils::bytes::{pad_left, u256_to_usize};
 
-use super::memory::Memory;
+use super::disassembler::{disassemble, scan_instructions};
+use super::env::{BlockEnv, EvmConfig, GasSchedule, Hardfork, TxEnv};
+use super::frame::{CallKind, CallNode, CallType, Frame};
+use super::journal::{StepDiff, StepJournal};
+use super::memory::{memory_expansion_cost, Memory, MAX_MEMORY_SIZE};
 use super::op_codes;
+use super::result::{
+    BalanceChange, ExecutionOutcome, ExecutionResult, SimulationResult, StorageChange,
+};
 use super::stack::Stack;
-use super::state::EvmState;
+use super::state::{AccountState, EvmState};
+use super::trace::TraceStep;
 use super::utils;
-use super::utils::environment::{increment_nonce, init_account};
+use super::utils::address::{compute_create_address, is_precompile};
+use super::utils::environment::{
+    delete_account, get_balance, get_nonce, increment_nonce, init_account,
+};
 use super::utils::errors::ExecutionError;
+#[cfg(feature = "alloy")]
+use super::utils::primitives::U256Compat;
+use super::utils::primitives::U256;
 
-use ethers::types::U256;
+use ethers::abi::{Abi, AbiParser, Token};
+use ethers::types::transaction::eip2930::{AccessList, AccessListItem};
+use ethers::types::{H160, H256};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 // Colored output
 use colored::*;
 
+/// The maximum call depth of the EVM.
+pub const MAX_CALL_DEPTH: usize = 1024;
+
+/// The default maximum size of a single copy to memory (32 MiB).
+pub const DEFAULT_MAX_COPY_SIZE: usize = 32 * 1024 * 1024;
+
+/// The free gas given to the callee of a CALL sending value.
+pub const CALL_STIPEND: u64 = 2300;
+
 pub struct Runner {
     // Execution
     pub pc: usize,
     pub bytecode: Vec<u8>,
+    pub jumpdests: Vec<bool>,
     pub debug_level: Option<u8>,
     pub call_depth: u32,
+    pub frames: Vec<Frame>,
+    pub call_tree: Vec<CallNode>,
+    pub reentrant_calls: Vec<([u8; 20], [u8; 20])>,
+    pub max_stack_depth: usize,
+    pub max_memory_size: usize,
+    pub journal: Option<StepJournal>,
+    pub trace_steps: Option<Vec<TraceStep>>,
+    pub trace_failures_only: bool,
+    pub max_copy_size: usize,
+    pub last_outcome: Option<ExecutionOutcome>,
+    pub scan_mode: bool,
+    pub scanned_pcs: Vec<usize>,
+    pub print_errors: bool,
+    pub lenient_mode: bool,
+    pub check_stack_io: bool,
+    pub max_steps: Option<u64>,
+    pub steps: u64,
 
     // Environment
+    pub hardfork: Hardfork,
+    pub block: BlockEnv,
+    pub tx: TxEnv,
+    pub accessed_addresses: HashSet<[u8; 20]>,
+    pub accessed_storage_keys: HashSet<([u8; 20], [u8; 32])>,
     pub gas: u64,
+    pub gas_limit: u64,
+    pub gas_refund: u64,
+    pub memory_gas: u64,
+    pub execution_gas: u64,
+    pub call_stipend: bool,
+    pub last_forwarded_gas: Option<u64>,
     pub origin: [u8; 20],
     pub caller: [u8; 20],
     pub callvalue: [u8; 32],
@@ -29,6 +85,7 @@ pub struct Runner {
 
     // Data
     pub state: EvmState,
+    pub snapshots: Vec<EvmState>,
     pub memory: Memory,
     pub calldata: Memory,
     pub returndata: Memory,
@@ -63,12 +120,33 @@ impl Runner {
             // Set the program counter to 0
             pc: 0,
             gas: 30_000_000,
+            gas_limit: 30_000_000,
+            // No storage cleared yet
+            gas_refund: 0,
+            // Split the gas spent between memory expansion and opcode costs
+            memory_gas: 0,
+            execution_gas: 0,
+            // Give the stipend to the calls sending value
+            call_stipend: true,
+            // No call made yet
+            last_forwarded_gas: None,
+            // Follow the latest hardfork rules
+            hardfork: Hardfork::default(),
+            // Use a mainnet block environment
+            block: BlockEnv::default(),
+            // Pay the base fee without any tip
+            tx: TxEnv::default(),
+            // Create an empty access set
+            accessed_addresses: HashSet::new(),
+            accessed_storage_keys: HashSet::new(),
             // Create a new storage
             state: if state.is_some() {
                 state.unwrap()
             } else {
                 EvmState::new(None)
             },
+            // No state snapshot taken yet
+            snapshots: Vec::new(),
             // Create an empty memory
             memory: Memory::new(None),
             // Create an empty memory for the call data
@@ -99,10 +177,41 @@ impl Runner {
             },
             // Create a new empty bytecode
             bytecode: Vec::new(),
+            // Create an empty jumpdest analysis
+            jumpdests: Vec::new(),
             // Set debug mode to false
             debug_level: None,
             // Set the call depth to 0
             call_depth: 0,
+            // No caller frame
+            frames: Vec::new(),
+            // No call made yet
+            call_tree: Vec::new(),
+            reentrant_calls: Vec::new(),
+            // No stack or memory growth yet
+            max_stack_depth: 0,
+            max_memory_size: 0,
+            // Don't record step diffs
+            journal: None,
+            // Don't record the execution trace
+            trace_steps: None,
+            trace_failures_only: false,
+            // Cap the copies to memory
+            max_copy_size: DEFAULT_MAX_COPY_SIZE,
+            // Nothing was run yet
+            last_outcome: None,
+            // Execute the bytecode instead of scanning it
+            scan_mode: false,
+            scanned_pcs: Vec::new(),
+            // Print the execution errors
+            print_errors: true,
+            // Abort on unknown opcodes
+            lenient_mode: false,
+            // Let each opcode check its own stack
+            check_stack_io: false,
+            // Only bound the execution by its gas
+            max_steps: None,
+            steps: 0,
         };
 
         // Initialize accounts in the EVM state
@@ -110,9 +219,14 @@ impl Runner {
         let _ = init_account(instance.caller, &mut instance);
         let _ = init_account(instance.origin, &mut instance);
 
+        // The transaction sender and recipient are warm from the start (EIP-2929)
+        instance.reset_accessed();
+
         // Set caller balance to 1000
         let mut result_bytes = [0u8; 32];
-        U256::from("3635C9ADC5DEA00000").to_big_endian(&mut result_bytes);
+        U256::from_str_radix("3635C9ADC5DEA00000", 16)
+            .unwrap()
+            .to_big_endian(&mut result_bytes);
         instance
             .state
             .accounts
@@ -124,6 +238,95 @@ impl Runner {
         instance
     }
 
+    /// Creates a new instance of the EVM runner whose environment is read from a config.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller` - The address of the account that initiated the call.
+    /// * `config` - The block, transaction, hardfork, gas limit and fork settings of the run.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of the EVM runner.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::env::{EvmConfig, Hardfork};
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let config = EvmConfig { hardfork: Hardfork::London, ..Default::default() };
+    /// let runner = Runner::with_config([0xaa; 20], config);
+    /// assert_eq!(runner.hardfork, Hardfork::London);
+    /// ```
+    pub fn with_config(caller: [u8; 20], config: EvmConfig) -> Self {
+        let mut runner = Self::new(
+            caller,
+            None,
+            None,
+            None,
+            None,
+            Some(EvmState::new(config.fork_url)),
+        );
+        runner.block = config.block;
+        runner.tx = config.tx;
+        runner.hardfork = config.hardfork;
+        runner.gas_limit = config.gas_limit;
+        runner.gas = config.gas_limit;
+
+        runner
+    }
+
+    /// Replaces the stack of the runner, to set up an opcode executed in isolation.
+    ///
+    /// # Arguments
+    ///
+    /// * `stack` - The stack items, the last one being the top of the stack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::bytes::pad_left;
+    /// use evm_rs_emulator::op_codes::arithmetic::unsigned::add;
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None)
+    ///     .with_stack(vec![pad_left(&[0x01]), pad_left(&[0x02])]);
+    /// add(&mut runner).unwrap();
+    /// assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x03]));
+    /// ```
+    pub fn with_stack(mut self, stack: Vec<[u8; 32]>) -> Self {
+        self.stack = Stack {
+            max_depth: stack.len(),
+            stack,
+        };
+        self
+    }
+
+    /// Replaces the memory of the runner, to set up an opcode executed in isolation.
+    ///
+    /// # Arguments
+    ///
+    /// * `memory` - The memory content.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::bytes::pad_left;
+    /// use evm_rs_emulator::op_codes::memory::mload;
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None)
+    ///     .with_memory(vec![0xff; 32])
+    ///     .with_stack(vec![pad_left(&[0x00])]);
+    /// mload(&mut runner).unwrap();
+    /// assert_eq!(runner.stack.pop().unwrap(), [0xff; 32]);
+    /// ```
+    pub fn with_memory(mut self, memory: Vec<u8>) -> Self {
+        self.memory = Memory::new(Some(memory));
+        self
+    }
+
     /// Creates a new `Runner` instance with default values and sets the debug level to the given value.
     ///
     /// # Arguments
@@ -175,13 +378,332 @@ impl Runner {
         self.pc = value;
     }
 
+    /// Sets the program counter to the specified value after checking that it points to a
+    /// valid JUMPDEST of the current bytecode.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to set the program counter to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError` if:
+    /// * The value is out of the bytecode bounds
+    /// * The value doesn't point to a JUMPDEST (bytes inside PUSH data are not JUMPDESTs)
+    pub fn try_set_pc(&mut self, value: usize) -> Result<(), ExecutionError> {
+        if value >= self.bytecode.len() {
+            return Err(ExecutionError::OutOfBoundsByteCode);
+        }
+
+        // The jumpdest analysis may be missing if the bytecode was replaced directly
+        if !self.jumpdests.get(value).copied().unwrap_or(false) {
+            return Err(ExecutionError::InvalidJumpDestination);
+        }
+
+        self.pc = value;
+        Ok(())
+    }
+
     /// Returns the current value of the program counter.
     pub fn get_pc(&mut self) -> usize {
         self.pc
     }
 
+    /// Returns the calldata of the current call.
+    pub fn calldata_bytes(&self) -> &[u8] {
+        &self.calldata.heap
+    }
+
+    /// Returns the return data of the last call (or of the current execution once it returned).
+    pub fn returndata_bytes(&self) -> &[u8] {
+        &self.returndata.heap
+    }
+
+    /// Consumes gas from the remaining gas of the runner.
+    ///
+    /// The gas is only deducted if the whole amount is available, so an opcode charging its
+    /// cost before doing anything else leaves no partial state behind when it runs out of gas.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - The amount of gas to consume.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError::OutOfGas` if the remaining gas is lower than `amount`.
+    pub fn decrement_gas(&mut self, amount: u64) -> Result<(), ExecutionError> {
+        if amount > self.gas {
+            return Err(ExecutionError::OutOfGas);
+        }
+
+        self.gas -= amount;
+        self.execution_gas += amount;
+        Ok(())
+    }
+
+    /// Charges the expansion of the memory to cover `size` bytes at `offset`, before the
+    /// memory is accessed. The charge is added to `memory_gas`.
+    /// An access of zero bytes doesn't expand the memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset of the memory access.
+    /// * `size` - The size of the memory access.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError::OutOfGas` if the remaining gas can't pay for the expansion,
+    /// or if the access ends beyond `MAX_MEMORY_SIZE`.
+    pub fn charge_memory_expansion(
+        &mut self,
+        offset: usize,
+        size: usize,
+    ) -> Result<(), ExecutionError> {
+        if size == 0 {
+            return Ok(());
+        }
+
+        let end = offset
+            .checked_add(size)
+            .filter(|end| *end <= MAX_MEMORY_SIZE)
+            .ok_or(ExecutionError::OutOfGas)?;
+        let cost = memory_expansion_cost(self.memory.msize(), end);
+        if cost > self.gas {
+            return Err(ExecutionError::OutOfGas);
+        }
+
+        self.gas -= cost;
+        self.memory_gas += cost;
+        Ok(())
+    }
+
+    /// Converts a memory range read from the stack and charges its expansion.
+    /// A range of zero bytes doesn't access the memory: its offset is ignored, whatever its
+    /// value, and `(0, 0)` is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset of the memory access.
+    /// * `size` - The size of the memory access.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError::OutOfGas` if the range doesn't fit in memory or if the
+    /// remaining gas can't pay for the expansion.
+    ///
+    /// # Returns
+    ///
+    /// Returns the offset and the size of the range.
+    pub fn memory_range(
+        &mut self,
+        offset: U256,
+        size: U256,
+    ) -> Result<(usize, usize), ExecutionError> {
+        if size.is_zero() {
+            return Ok((0, 0));
+        }
+
+        let (offset, size) = (u256_to_usize(offset)?, u256_to_usize(size)?);
+        self.charge_memory_expansion(offset, size)?;
+        Ok((offset, size))
+    }
+
+    /// Returns the gas given to the callee of the last call, including the stipend of a CALL
+    /// sending value, or `None` if no call was made.
+    pub fn last_call_gas_forwarded(&self) -> Option<u64> {
+        self.last_forwarded_gas
+    }
+
+    /// Returns the amount of gas consumed by the current or last transaction.
+    /// A remaining gas above the gas limit counts as no gas used.
+    pub fn gas_used(&self) -> u64 {
+        self.gas_limit.saturating_sub(self.gas)
+    }
+
+    /// Returns the fee of the given amount of gas at the effective gas price (EIP-1559).
+    ///
+    /// # Arguments
+    ///
+    /// * `gas` - The amount of gas paid for.
+    pub fn gas_fee(&self, gas: u64) -> U256 {
+        U256::from(gas) * U256::from(self.tx.effective_gas_price(self.block.basefee))
+    }
+
+    /// Checks that the origin can pay for the whole gas limit before the transaction runs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError::InsufficientBalance` if the origin balance is lower than
+    /// the fee of `gas_limit`.
+    fn check_gas_fee_balance(&mut self) -> Result<(), ExecutionError> {
+        let max_fee = self.gas_fee(self.gas_limit);
+        if max_fee.is_zero() {
+            return Ok(());
+        }
+
+        let balance = U256::from_big_endian(&get_balance(self.origin, self)?);
+        if balance < max_fee {
+            return Err(ExecutionError::InsufficientBalance);
+        }
+
+        Ok(())
+    }
+
+    /// Charges the origin for the gas used at the effective gas price and credits the
+    /// coinbase with the priority fee (EIP-1559). The base fee part of the price is burnt.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError::InsufficientBalance` if the origin can't pay the fee, or
+    /// an `ExecutionError` if the coinbase account can't be created.
+    fn pay_gas_fee(&mut self) -> Result<(), ExecutionError> {
+        let gas_used = self.gas_used();
+        let fee = self.gas_fee(gas_used);
+        if fee.is_zero() {
+            return Ok(());
+        }
+
+        // Charge the origin
+        let origin = self.origin;
+        let balance = U256::from_big_endian(&get_balance(origin, self)?);
+        if balance < fee {
+            return Err(ExecutionError::InsufficientBalance);
+        }
+        self.state.copy_base_account(origin);
+        let account = self
+            .state
+            .accounts
+            .get_mut(&origin)
+            .ok_or(ExecutionError::AccountNotFound)?;
+        (balance - fee).to_big_endian(&mut account.balance);
+
+        // Only the priority fee goes to the coinbase
+        let priority_fee =
+            U256::from(gas_used) * U256::from(self.tx.priority_fee_per_gas(self.block.basefee));
+        if priority_fee.is_zero() {
+            return Ok(());
+        }
+
+        let coinbase = self.block.coinbase;
+        init_account(coinbase, self)?;
+
+        let account = self
+            .state
+            .accounts
+            .get_mut(&coinbase)
+            .ok_or(ExecutionError::AccountNotFound)?;
+        let balance = U256::from_big_endian(&account.balance).saturating_add(priority_fee);
+        balance.to_big_endian(&mut account.balance);
+
+        Ok(())
+    }
+
+    /// Checks the size of a copy to memory read from the stack against `max_copy_size`.
+    /// This guards against huge allocations when no gas limit stops the copy first.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The size of the copy.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError::CopySizeTooLarge` if the size exceeds `max_copy_size`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the size of the copy.
+    pub fn checked_copy_size(&self, size: U256) -> Result<usize, ExecutionError> {
+        if size > U256::from(self.max_copy_size) {
+            return Err(ExecutionError::CopySizeTooLarge);
+        }
+
+        Ok(size.as_usize())
+    }
+
+    /// Marks an address as accessed (EIP-2929).
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The accessed address.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the address was cold, i.e. accessed for the first time.
+    pub fn access_address(&mut self, address: [u8; 20]) -> bool {
+        self.accessed_addresses.insert(address)
+    }
+
+    /// Marks a storage slot as accessed (EIP-2929).
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The account of the slot.
+    /// * `slot` - The accessed slot.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the slot was cold, i.e. accessed for the first time.
+    pub fn access_storage_key(&mut self, address: [u8; 20], slot: [u8; 32]) -> bool {
+        self.accessed_storage_keys.insert((address, slot))
+    }
+
+    /// Forgets the addresses and storage slots accessed by a previous transaction, then warms
+    /// the origin, the caller, the runner address and the precompiles (EIP-2929).
+    pub fn reset_accessed(&mut self) {
+        self.accessed_addresses.clear();
+        self.accessed_storage_keys.clear();
+
+        self.access_address(self.origin);
+        self.access_address(self.caller);
+        self.access_address(self.address);
+        for index in 0x01..=0x0a {
+            let mut precompile = [0u8; 20];
+            precompile[19] = index;
+            self.access_address(precompile);
+        }
+    }
+
+    /// Builds an EIP-2930 access list from the addresses and storage slots accessed so far,
+    /// like `eth_createAccessList`. The origin, the caller, the runner address and the
+    /// precompiles are left out unless some of their slots were accessed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the access list, sorted by address and slot.
+    pub fn generated_access_list(&self) -> AccessList {
+        let mut list: BTreeMap<[u8; 20], BTreeSet<[u8; 32]>> = BTreeMap::new();
+
+        let excluded = [self.origin, self.caller, self.address];
+        for address in &self.accessed_addresses {
+            if !excluded.contains(address) && !is_precompile(*address) {
+                list.entry(*address).or_default();
+            }
+        }
+        for (address, slot) in &self.accessed_storage_keys {
+            list.entry(*address).or_default().insert(*slot);
+        }
+
+        AccessList(
+            list.into_iter()
+                .map(|(address, slots)| AccessListItem {
+                    address: H160::from(address),
+                    storage_keys: slots.into_iter().map(H256::from).collect(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the gas schedule of the runner hardfork.
+    pub fn gas_schedule(&self) -> GasSchedule {
+        GasSchedule::for_hardfork(self.hardfork)
+    }
+
     /// Interprets the given bytecode and executes it on the EVM.
     ///
+    /// An initial interpretation at depth 0 starts a new transaction: the remaining gas is
+    /// reset to `gas_limit` and the gas counters of the previous transaction are cleared.
+    /// The origin pays for the gas used at the end of the transaction (see `gas_fee`).
+    ///
     /// # Arguments
     ///
     /// * `bytecode` - A vector of bytes representing the bytecode to be executed.
@@ -190,7 +712,8 @@ impl Runner {
     ///
     /// # Errors
     ///
-    /// Returns an `ExecutionError` if an error occurs during execution.
+    /// Returns an `ExecutionError` if an error occurs during execution, or an
+    /// `ExecutionError::InsufficientBalance` if the origin can't pay for the gas limit.
     ///
     /// # Examples
     ///
@@ -209,9 +732,34 @@ impl Runner {
         initial_interpretation: bool,
     ) -> Result<(), ExecutionError> {
         // Set the bytecode
-        self.bytecode = bytecode;
+        self.load_bytecode(bytecode);
 
         if initial_interpretation {
+            // Forget the outcome of the previous transaction
+            if self.call_depth == 0 {
+                self.last_outcome = None;
+                self.scanned_pcs.clear();
+                self.call_tree.clear();
+                self.reentrant_calls.clear();
+                self.state.missing_accounts.clear();
+                self.state.missing_slots.clear();
+                self.gas = self.gas_limit;
+                self.memory_gas = 0;
+                self.execution_gas = 0;
+                self.gas_refund = 0;
+                self.reset_accessed();
+                self.steps = 0;
+                if let Some(trace_steps) = self.trace_steps.as_mut() {
+                    trace_steps.clear();
+                }
+
+                // The origin must be able to pay for the whole gas limit
+                if let Err(error) = self.check_gas_fee_balance() {
+                    self.print_execution_error(&error);
+                    return Err(error);
+                }
+            }
+
             // Set the runner address code
             let put_code_result = self.state.put_code_at(self.address, self.bytecode.clone());
             if put_code_result.is_err() {
@@ -238,14 +786,22 @@ impl Runner {
         // Check if the bytecode is empty
         if self.bytecode.is_empty() {
             // Return an error
-            println!("{}: {}", "ERROR: ".red(), ExecutionError::EmptyByteCode);
+            if self.print_errors {
+                println!("{}: {}", "ERROR: ".red(), ExecutionError::EmptyByteCode);
+            }
             return Err(ExecutionError::EmptyByteCode);
         }
 
         // Interpret the bytecode
         while self.pc < self.bytecode.len() {
+            // Visit the instruction without executing it
+            if self.scan_mode {
+                self.scan_step();
+                continue;
+            }
+
             // Interpret an opcode
-            let result = self.interpret_op_code(self.bytecode[self.pc]);
+            let result = self.step();
 
             // Check if the interpretation was successful
             if result.is_err() {
@@ -255,6 +811,23 @@ impl Runner {
             }
         }
 
+        // Record the high-water marks of this frame
+        self.max_stack_depth = self.max_stack_depth.max(self.stack.max_depth);
+        self.max_memory_size = self.max_memory_size.max(self.memory.max_size);
+
+        // Charge the gas fee at the end of the transaction
+        if initial_interpretation && self.call_depth == 0 {
+            if let Err(fee_error) = self.pay_gas_fee() {
+                error = error.or(Some(fee_error));
+            }
+
+            // Remember how the transaction ended for `export_run`
+            self.last_outcome = Some(match &error {
+                Some(error) => Err(error.clone()).into(),
+                None => ExecutionOutcome::Success,
+            });
+        }
+
         /* -------------------------------------------------------------------------- */
         /*                             Print debug footer                             */
         /* -------------------------------------------------------------------------- */
@@ -276,66 +849,603 @@ impl Runner {
         /*                            Print execution error                           */
         /* -------------------------------------------------------------------------- */
 
-        if error.is_some() {
-            println!(
-                "{} {}\n  {}: 0x{:X}\n  {}: 0x{:X}\n  {}",
-                "ERROR:".red(),
-                "Runtime error".red(),
-                "PC".yellow(),
-                self.pc,
-                "OpCode".yellow(),
-                self.bytecode[self.pc],
-                error.as_ref().unwrap().to_string().red()
-            );
+        if let Some(error) = error {
+            self.print_execution_error(&error);
 
-            return Err(error.unwrap());
+            return Err(error);
         }
 
         Ok(())
     }
 
-    /// Interpret a single opcode.
+    /// Runs the given bytecode and classifies how the execution ended.
+    ///
+    /// Unlike `interpret`, a contract-level REVERT is not mixed up with interpreter errors:
+    /// the returned `ExecutionResult` tells whether the execution succeeded, reverted with
+    /// some data or was halted by an error.
     ///
     /// # Arguments
     ///
-    /// * `opcode` - A single opcode to interpret.
+    /// * `bytecode` - A vector of bytes representing the bytecode to be executed.
+    /// * `debug` - An optional u8 value representing the debug level.
     ///
-    /// # Errors
+    /// # Examples
     ///
-    /// Returns an `ExecutionError` if the opcode is invalid or if an error occurs during execution.
+    /// ```
+    /// use evm_rs_emulator::{ExecutionOutcome, Runner};
     ///
-    /// # OpCodes
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    /// // PUSH1 0x00 PUSH1 0x00 REVERT
+    /// let result = runner.run(vec![0x60, 0x00, 0x60, 0x00, 0xfd], None);
+    /// assert_eq!(result.outcome, ExecutionOutcome::Revert(vec![]));
+    /// ```
+    pub fn run(&mut self, bytecode: Vec<u8>, debug: Option<u8>) -> ExecutionResult {
+        let logs_len = self.state.logs.len();
+        let result = self.interpret(bytecode, debug, true);
+
+        ExecutionResult {
+            outcome: result.into(),
+            return_data: self.returndata.heap.clone(),
+            logs: self.state.logs[logs_len..].to_vec(),
+            gas_used: self.gas_used(),
+            stack: self.stack.as_slice().to_vec(),
+            reentrant_calls: self.reentrant_calls.clone(),
+            missing_accounts: self.state.missing_accounts.clone(),
+            missing_slots: self.state.missing_slots.clone(),
+        }
+    }
+
+    /// Dumps the last execution as a JSON object, to share a reproduction of a run.
     ///
-    /// The function matches the given opcode with the corresponding function from the `op_codes` module.
-    /// The OpCodes are divided into the following categories:
+    /// The object has three keys:
+    /// * `input` - The bytecode, caller, origin, address, value, calldata, gas limit, hardfork,
+    ///   block and transaction environments.
+    /// * `result` - The outcome (`success`, `revert` or `halt`, with the halt error), the return
+    ///   data, the gas used and the final stack (bottom first). The outcome is `null` if nothing
+    ///   was run yet.
+    /// * `state` - The accounts, sorted by address, with their storage and code, and the logs.
     ///
-    /// * Execution OpCodes
-    /// * Math operations OpCodes
-    /// * Push OpCodes
-    /// * Dup OpCodes
-    /// * Swap OpCodes
-    /// * Memory OpCodes
-    /// * Storage OpCodes
+    /// Byte values are written as `0x` prefixed hex strings.
     ///
-    /// For more information on each OpCode, please refer to the `op_codes` module.
-    pub fn interpret_op_code(&mut self, opcode: u8) -> Result<(), ExecutionError> {
-        match opcode {
-            /* ---------------------------- Execution OpCodes --------------------------- */
-            0x00 => op_codes::flow::stop(self),
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    /// runner.run(vec![0x60, 0x01, 0x60, 0x02, 0x01], None);
+    ///
+    /// let run = runner.export_run();
+    /// assert_eq!(run["input"]["bytecode"], "0x6001600201");
+    /// assert_eq!(run["result"]["outcome"], "success");
+    /// ```
+    pub fn export_run(&self) -> serde_json::Value {
+        use serde_json::{json, Map, Value};
+
+        let to_hex = |bytes: &[u8]| format!("0x{}", hex::encode(bytes));
+
+        /* ---------------------------------- Input --------------------------------- */
+
+        let input = json!({
+            "bytecode": to_hex(&self.bytecode),
+            "caller": to_hex(&self.caller),
+            "origin": to_hex(&self.origin),
+            "address": to_hex(&self.address),
+            "callvalue": to_hex(&self.callvalue),
+            "calldata": to_hex(&self.calldata.heap),
+            "gas_limit": self.gas_limit,
+            "hardfork": format!("{:?}", self.hardfork),
+            "block": {
+                "chain_id": self.block.chain_id,
+                "basefee": self.block.basefee,
+                "coinbase": to_hex(&self.block.coinbase),
+            },
+            "tx": {
+                "max_fee_per_gas": self.tx.max_fee_per_gas,
+                "max_priority_fee_per_gas": self.tx.max_priority_fee_per_gas,
+            },
+        });
 
-            /* ------------------------- Math operations OpCodes ------------------------ */
-            0x01 => op_codes::arithmetic::unsigned::add(self),
-            0x02 => op_codes::arithmetic::unsigned::mul(self),
-            0x03 => op_codes::arithmetic::unsigned::sub(self),
-            0x04 => op_codes::arithmetic::unsigned::div(self),
-            0x06 => op_codes::arithmetic::unsigned::modulo(self),
-            0x08 => op_codes::arithmetic::unsigned::addmod(self),
-            0x09 => op_codes::arithmetic::unsigned::mulmod(self),
-            0x0a => op_codes::arithmetic::unsigned::exp(self),
-            0x05 => op_codes::arithmetic::signed::sdiv(self),
-            0x07 => op_codes::arithmetic::signed::smodulo(self),
+        /* --------------------------------- Result --------------------------------- */
 
-            /* ------------------------------ Push OpCodes ------------------------------ */
+        let (outcome, error) = match &self.last_outcome {
+            Some(ExecutionOutcome::Success) => (json!("success"), Value::Null),
+            Some(ExecutionOutcome::Revert(_)) => (json!("revert"), Value::Null),
+            Some(ExecutionOutcome::Halt(error)) => (json!("halt"), json!(error.to_string())),
+            None => (Value::Null, Value::Null),
+        };
+
+        let result = json!({
+            "outcome": outcome,
+            "error": error,
+            "return_data": to_hex(&self.returndata.heap),
+            "gas_used": self.gas_used(),
+            "stack": self.stack.as_slice().iter().map(|word| to_hex(word)).collect::<Vec<_>>(),
+        });
+
+        /* ---------------------------------- State --------------------------------- */
+
+        let mut accounts = Map::new();
+        for (address, account) in &self.state.accounts {
+            let storage: Map<String, Value> = account
+                .storage
+                .iter()
+                .map(|(slot, value)| (to_hex(slot), json!(to_hex(value))))
+                .collect();
+            let code = self
+                .state
+                .codes
+                .get(&account.code_hash)
+                .map(|code| to_hex(code))
+                .unwrap_or_else(|| to_hex(&[]));
+
+            accounts.insert(
+                to_hex(address),
+                json!({
+                    "nonce": account.nonce,
+                    "balance": to_hex(&account.balance),
+                    "code": code,
+                    "storage": storage,
+                }),
+            );
+        }
+
+        let logs: Vec<Value> = self
+            .state
+            .logs
+            .iter()
+            .map(|log| {
+                json!({
+                    "address": to_hex(&log.address),
+                    "topics": log.topics.iter().map(|topic| to_hex(topic)).collect::<Vec<_>>(),
+                    "data": to_hex(&log.data),
+                })
+            })
+            .collect();
+
+        json!({
+            "input": input,
+            "result": result,
+            "state": {
+                "accounts": accounts,
+                "logs": logs,
+            },
+        })
+    }
+
+    /// Sets the bytecode to execute and analyzes its jump destinations, without running it.
+    /// At the top level, the step journal is cleared since its diffs belong to the previous
+    /// bytecode.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytecode` - A vector of bytes representing the bytecode to be executed.
+    pub fn load_bytecode(&mut self, bytecode: Vec<u8>) {
+        self.bytecode = bytecode;
+        self.analyze_jumpdests();
+
+        if self.call_depth == 0 {
+            if let Some(journal) = self.journal.as_mut() {
+                journal.clear();
+            }
+        }
+    }
+
+    /// Enables the step journal, which records a reversible diff of every top-level step so
+    /// it can be undone with `step_back`. This is memory-intensive, so only the last
+    /// `capacity` steps are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum number of steps that can be undone.
+    pub fn enable_step_journal(&mut self, capacity: usize) {
+        self.journal = Some(StepJournal::new(capacity));
+    }
+
+    /// Enables the in-memory execution trace. Every executed opcode, including the ones of
+    /// the callees, is recorded until the next top-level `interpret`.
+    pub fn enable_trace(&mut self) {
+        self.trace_steps = Some(Vec::new());
+        self.trace_failures_only = false;
+    }
+
+    /// Enables the in-memory execution trace of the failure path only. The steps of a callee
+    /// that returns successfully are dropped once it returns. The steps of the top-level frame
+    /// and of the failed callees are kept, even under a successful callee.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    /// runner.enable_failure_trace();
+    /// // PUSH1 0x01 PUSH1 0x02 ADD
+    /// runner.interpret(vec![0x60, 0x01, 0x60, 0x02, 0x01], None, true).unwrap();
+    ///
+    /// assert_eq!(runner.trace().len(), 3);
+    /// ```
+    pub fn enable_failure_trace(&mut self) {
+        self.trace_steps = Some(Vec::new());
+        self.trace_failures_only = true;
+    }
+
+    /// Returns the execution trace recorded since the last top-level `interpret`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the executed opcodes in order, or an empty slice if the trace is disabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    /// runner.enable_trace();
+    /// // PUSH1 0x01 PUSH1 0x02 ADD
+    /// runner.interpret(vec![0x60, 0x01, 0x60, 0x02, 0x01], None, true).unwrap();
+    ///
+    /// let trace = runner.trace();
+    /// assert_eq!(trace.len(), 3);
+    /// assert_eq!(trace[2].opcode, 0x01);
+    /// assert_eq!(trace[2].stack_top.unwrap()[31], 0x03);
+    /// ```
+    pub fn trace(&self) -> &[TraceStep] {
+        self.trace_steps.as_deref().unwrap_or(&[])
+    }
+
+    /// Executes the opcode at the current program counter.
+    ///
+    /// A call opcode is stepped over: the callee runs until it returns to the current frame.
+    /// If the step journal is enabled, the changes made by a top-level step are recorded (even
+    /// if the opcode fails) so they can be undone with `step_back`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError` if the program counter is out of the bytecode bounds or if
+    /// the opcode fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    /// // PUSH1 0x01 PUSH1 0x02
+    /// runner.load_bytecode(vec![0x60, 0x01, 0x60, 0x02]);
+    /// runner.step().unwrap();
+    /// assert_eq!(runner.pc, 2);
+    /// assert_eq!(runner.stack.stack.len(), 1);
+    /// ```
+    pub fn step(&mut self) -> Result<(), ExecutionError> {
+        let opcode = *self
+            .bytecode
+            .get(self.pc)
+            .ok_or(ExecutionError::OutOfBoundsByteCode)?;
+
+        let diff = if self.journal.is_some() && self.call_depth == 0 {
+            Some(StepDiff::before(self, opcode))
+        } else {
+            None
+        };
+
+        let depth = self.frames.len();
+        let mut result = self.interpret_op_code(opcode);
+
+        // Run the callee until it returns to this frame
+        if self.frames.len() > depth {
+            let callee_result = self.execute(depth + 1);
+            result = self.return_to_caller(callee_result);
+        }
+
+        if let (Some(journal), Some(diff)) = (self.journal.as_mut(), diff) {
+            journal.push(diff);
+        }
+
+        result
+    }
+
+    /// Steps the execution until the predicate holds, e.g. once a log is emitted or a slot
+    /// changed. The predicate is checked after each step of the current frame (see `step`).
+    ///
+    /// # Arguments
+    ///
+    /// * `predicate` - The condition to stop at, checked on the runner after each step.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError` if a step fails.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the predicate stopped the execution, or `false` if the end of the
+    /// bytecode was reached first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    /// // PUSH1 0x01 PUSH1 0x02 PUSH1 0x03
+    /// runner.load_bytecode(vec![0x60, 0x01, 0x60, 0x02, 0x60, 0x03]);
+    ///
+    /// assert!(runner.run_until(|runner| runner.stack.stack.len() == 2).unwrap());
+    /// assert_eq!(runner.pc, 4);
+    /// assert!(!runner.run_until(|_| false).unwrap());
+    /// ```
+    pub fn run_until(
+        &mut self,
+        predicate: impl Fn(&Runner) -> bool,
+    ) -> Result<bool, ExecutionError> {
+        while self.pc < self.bytecode.len() {
+            self.step()?;
+
+            if predicate(self) {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Records the instruction at the current program counter and moves to the next one,
+    /// without executing it. Used by the scan mode, which visits every instruction of the
+    /// bytecode, including the ones after a STOP, RETURN or invalid opcode.
+    fn scan_step(&mut self) {
+        self.scanned_pcs.push(self.pc);
+
+        // Skip the PUSH1 to PUSH32 data
+        let opcode = self.bytecode[self.pc];
+        let data_len = if (0x60..=0x7f).contains(&opcode) {
+            (opcode - 0x5f) as usize
+        } else {
+            0
+        };
+        self.pc = (self.pc + 1 + data_len).min(self.bytecode.len());
+    }
+
+    /// Undoes the last recorded step, restoring the program counter, gas, stack, memory,
+    /// return data and state to their values before it.
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if the step journal is disabled or has no step left to undo.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    /// runner.enable_step_journal(16);
+    /// // PUSH1 0x01
+    /// runner.load_bytecode(vec![0x60, 0x01]);
+    /// runner.step().unwrap();
+    ///
+    /// assert!(runner.step_back());
+    /// assert_eq!(runner.pc, 0);
+    /// assert!(runner.stack.stack.is_empty());
+    /// assert!(!runner.step_back());
+    /// ```
+    pub fn step_back(&mut self) -> bool {
+        let diff = match self.journal.as_mut().and_then(|journal| journal.pop()) {
+            Some(diff) => diff,
+            None => return false,
+        };
+
+        diff.undo(self);
+        true
+    }
+
+    /// Returns the opcode at the program counter, or `None` at the end of the bytecode.
+    pub fn current_op(&self) -> Option<u8> {
+        self.bytecode.get(self.pc).copied()
+    }
+
+    /// Disassembles the instructions around the program counter, e.g. to display the context
+    /// of a step in a debugger.
+    ///
+    /// # Arguments
+    ///
+    /// * `window` - The number of instructions to show before and after the current one.
+    ///
+    /// # Returns
+    ///
+    /// Returns the offset and text (mnemonic and PUSH data) of each instruction, in bytecode
+    /// order. The instructions before the end of the bytecode are returned if it was reached.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    /// // PUSH1 0x01 PUSH1 0x02 ADD STOP
+    /// runner.load_bytecode(vec![0x60, 0x01, 0x60, 0x02, 0x01, 0x00]);
+    /// runner.step().unwrap();
+    ///
+    /// let context = runner.disassemble_around(1);
+    /// assert_eq!(context, vec![
+    ///     (0, "PUSH1 0x01".to_string()),
+    ///     (2, "PUSH1 0x02".to_string()),
+    ///     (4, "ADD".to_string()),
+    /// ]);
+    /// ```
+    pub fn disassemble_around(&self, window: usize) -> Vec<(usize, String)> {
+        let instructions = disassemble(&self.bytecode);
+        let current = instructions
+            .iter()
+            .position(|instruction| instruction.pc >= self.pc)
+            .unwrap_or(instructions.len());
+
+        let start = current.saturating_sub(window);
+        let end = (current + window + 1).min(instructions.len());
+
+        instructions[start..end]
+            .iter()
+            .map(|instruction| {
+                let text = if instruction.push_data.is_empty() {
+                    instruction.name().to_string()
+                } else {
+                    format!(
+                        "{} 0x{}",
+                        instruction.name(),
+                        hex::encode(&instruction.push_data)
+                    )
+                };
+                (instruction.pc, text)
+            })
+            .collect()
+    }
+
+    /// Interprets the given bytecode with a strict gas limit.
+    ///
+    /// The execution halts with an `ExecutionError::OutOfGas` as soon as an opcode can't pay
+    /// for its cost. In that case the program counter is left on the failing instruction and
+    /// the failing opcode has no effect on the stack, memory or state.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytecode` - A vector of bytes representing the bytecode to be executed.
+    /// * `gas_limit` - The maximum amount of gas the execution can consume.
+    /// * `debug` - An optional u8 value representing the debug level.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError` if an error occurs during execution.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::errors::ExecutionError;
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    /// // PUSH1 0x01 PUSH1 0x02 costs 6 gas
+    /// let result = runner.interpret_with_gas_limit(vec![0x60, 0x01, 0x60, 0x02], 5, None);
+    /// assert_eq!(result.unwrap_err(), ExecutionError::OutOfGas);
+    /// assert_eq!(runner.pc, 2);
+    /// ```
+    pub fn interpret_with_gas_limit(
+        &mut self,
+        bytecode: Vec<u8>,
+        gas_limit: u64,
+        debug: Option<u8>,
+    ) -> Result<(), ExecutionError> {
+        self.gas_limit = gas_limit;
+
+        self.interpret(bytecode, debug, true)
+    }
+
+    /// Interprets the given bytecode with new calldata, to run the same code with many inputs
+    /// on one runner.
+    ///
+    /// The fields of the previous invocation are reset before running: the program counter,
+    /// stack, memory, return data, transient storage, accessed addresses and slots and
+    /// remaining gas. The persistent state (accounts, storage, logs) is kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytecode` - A vector of bytes representing the bytecode to be executed.
+    /// * `calldata` - The input data of this invocation.
+    /// * `debug` - An optional u8 value representing the debug level.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError` if an error occurs during execution.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    /// // CALLDATASIZE
+    /// let bytecode = vec![0x36];
+    /// runner.interpret_with_calldata(bytecode.clone(), vec![0x01], None).unwrap();
+    /// runner.interpret_with_calldata(bytecode, vec![0x01, 0x02], None).unwrap();
+    /// assert_eq!(runner.stack.stack.len(), 1);
+    /// assert_eq!(runner.stack.stack[0][31], 2);
+    /// ```
+    pub fn interpret_with_calldata(
+        &mut self,
+        bytecode: Vec<u8>,
+        calldata: Vec<u8>,
+        debug: Option<u8>,
+    ) -> Result<(), ExecutionError> {
+        self.calldata = Memory::new(Some(calldata));
+        self.pc = 0;
+        self.stack = Stack::new();
+        self.memory = Memory::new(None);
+        self.returndata = Memory::new(None);
+        self.state.transient_storage.clear();
+        self.state.transient_journal.clear();
+
+        self.interpret(bytecode, debug, true)
+    }
+
+    /// Interpret a single opcode.
+    ///
+    /// # Arguments
+    ///
+    /// * `opcode` - A single opcode to interpret.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError` if the opcode is invalid or if an error occurs during execution.
+    ///
+    /// # OpCodes
+    ///
+    /// The function matches the given opcode with the corresponding function from the `op_codes` module.
+    /// The OpCodes are divided into the following categories:
+    ///
+    /// * Execution OpCodes
+    /// * Math operations OpCodes
+    /// * Push OpCodes
+    /// * Dup OpCodes
+    /// * Swap OpCodes
+    /// * Memory OpCodes
+    /// * Storage OpCodes
+    ///
+    /// For more information on each OpCode, please refer to the `op_codes` module.
+    pub fn interpret_op_code(&mut self, opcode: u8) -> Result<(), ExecutionError> {
+        // Abort a run executing too many opcodes, whatever its gas
+        if let Some(max_steps) = self.max_steps {
+            if self.steps >= max_steps {
+                return Err(ExecutionError::StepLimitReached);
+            }
+        }
+        self.steps += 1;
+
+        // Fail early if the opcode would underflow or overflow the stack
+        if self.check_stack_io {
+            self.check_stack(opcode)?;
+        }
+
+        let pc = self.pc;
+        let gas = self.gas;
+        let depth = self.call_depth;
+        let frames_len = self.frames.len();
+
+        let result = match opcode {
+            /* ---------------------------- Execution OpCodes --------------------------- */
+            0x00 => op_codes::flow::stop(self),
+
+            /* ------------------------- Math operations OpCodes ------------------------ */
+            0x01 => op_codes::arithmetic::unsigned::add(self),
+            0x02 => op_codes::arithmetic::unsigned::mul(self),
+            0x03 => op_codes::arithmetic::unsigned::sub(self),
+            0x04 => op_codes::arithmetic::unsigned::div(self),
+            0x06 => op_codes::arithmetic::unsigned::modulo(self),
+            0x08 => op_codes::arithmetic::unsigned::addmod(self),
+            0x09 => op_codes::arithmetic::unsigned::mulmod(self),
+            0x0a => op_codes::arithmetic::unsigned::exp(self),
+            0x05 => op_codes::arithmetic::signed::sdiv(self),
+            0x07 => op_codes::arithmetic::signed::smodulo(self),
+
+            /* ------------------------------ Push OpCodes ------------------------------ */
             0x50 => op_codes::stack::pop::pop(self),
 
             0x5f => op_codes::stack::push::push(self, 0),
@@ -416,6 +1526,8 @@ impl Runner {
             /* ----------------------------- Storage OpCodes ---------------------------- */
             0x54 => op_codes::storage::sload(self),
             0x55 => op_codes::storage::sstore(self),
+            0x5c => op_codes::storage::tload(self),
+            0x5d => op_codes::storage::tstore(self),
 
             /* --------------------------- Comparison OpCodes --------------------------- */
             0x10 => op_codes::comparison::lt(self),
@@ -432,6 +1544,7 @@ impl Runner {
             0x19 => op_codes::bitwise::not(self),
             0x1b => op_codes::bitwise::shl(self),
             0x1c => op_codes::bitwise::shr(self),
+            0x1d => op_codes::bitwise::sar(self),
             0x20 => op_codes::bitwise::sha(self),
 
             /* ---------------------------- Environment OpCodes ------------------------- */
@@ -486,13 +1599,69 @@ impl Runner {
             0xfa => op_codes::system::staticcall(self),
             0xff => op_codes::system::selfdestruct(self),
 
+            // The designated INVALID opcode aborts even in lenient mode
+            0xfe => op_codes::system::invalid(self),
+            // Unknown opcodes stop the execution in lenient mode (non-conformant)
+            _ if self.lenient_mode => op_codes::flow::stop(self),
+
             // Default case
             _ => op_codes::system::invalid(self),
+        };
+
+        if self.trace_steps.is_some() {
+            // A call or create leaves the caller stack, its result is pushed on return
+            let stack_top = if self.frames.len() == frames_len {
+                self.stack.as_slice().last().copied()
+            } else {
+                None
+            };
+            let step = TraceStep {
+                pc,
+                opcode,
+                gas,
+                depth,
+                stack_top,
+            };
+            if let Some(trace_steps) = self.trace_steps.as_mut() {
+                trace_steps.push(step);
+            }
+        }
+
+        result
+    }
+
+    /// Checks that the stack has enough items for the inputs of an opcode, and enough room
+    /// for its outputs, using the opcode metadata. Unassigned opcodes are not checked.
+    /// Only run when `check_stack_io` is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `opcode` - The opcode about to run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError::StackTooSmall` if the stack has less than `stack_in` items,
+    /// or an `ExecutionError::StackTooDeep` if it would exceed 1024 items after the opcode.
+    fn check_stack(&self, opcode: u8) -> Result<(), ExecutionError> {
+        let (stack_in, stack_out) = match op_codes::metadata::stack_io(opcode) {
+            Some(io) => io,
+            None => return Ok(()),
+        };
+
+        let len = self.stack.stack.len();
+        if len < stack_in {
+            return Err(ExecutionError::StackTooSmall);
+        }
+        if len - stack_in + stack_out > 1024 {
+            return Err(ExecutionError::StackTooDeep);
         }
+
+        Ok(())
     }
 
     /// Executes a call to a contract.
     /// Set up a new runner environment for the call and interpret the bytecode.
+    /// The caller frame is saved on the `frames` stack and restored once the callee returns.
     ///
     /// # Arguments
     ///
@@ -504,7 +1673,8 @@ impl Runner {
     ///
     /// # Errors
     ///
-    /// Returns an `ExecutionError` if the call fails.
+    /// Returns an `ExecutionError` if the call fails, or `ExecutionError::CallTooDeep` if the
+    /// call depth limit (1024) is reached.
     pub fn call(
         &mut self,
         to: [u8; 20],
@@ -513,83 +1683,666 @@ impl Runner {
         _gas: u64,
         delegate: bool,
     ) -> Result<(), ExecutionError> {
-        let mut error: Option<ExecutionError> = None;
+        self.enter_frame(to, value, calldata, CallKind::External { delegate }, None)?;
 
-        // Store the initial runner state
-        let initial_caller = self.caller.clone();
-        let initial_callvalue = self.callvalue.clone();
-        let initial_address = self.address.clone();
-        let initial_calldata = self.calldata.clone();
-        let initial_returndata = self.returndata.clone();
-        let initial_memory = self.memory.clone();
-        let initial_stack = self.stack.clone();
-        let initial_pc = self.pc.clone();
-        let initial_debug_level = self.debug_level.clone();
-        let initial_bytecode = self.bytecode.clone();
+        let callee_result = self.execute(self.frames.len());
+        let (_, result) = self.exit_frame(callee_result);
 
-        // Update runner state
+        result
+    }
+
+    /// Simulates a call like `eth_call`: the call is executed, then every change it made is
+    /// rolled back. The state, access sets, gas, return data and call tree are left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `to` - The address of the contract to call.
+    /// * `data` - The input data to the contract.
+    /// * `value` - The value to send with the call.
+    ///
+    /// # Returns
+    ///
+    /// Returns the outcome, return data, logs and gas used of the call, with the storage and
+    /// balance changes it would have made.
+    pub fn simulate_call(
+        &mut self,
+        to: [u8; 20],
+        data: Vec<u8>,
+        value: [u8; 32],
+    ) -> SimulationResult {
+        // Snapshot everything the call may change
+        let state = self.state.clone();
+        let accessed_addresses = self.accessed_addresses.clone();
+        let accessed_storage_keys = self.accessed_storage_keys.clone();
+        let gas = self.gas;
+        let (memory_gas, execution_gas) = (self.memory_gas, self.execution_gas);
+        let gas_refund = self.gas_refund;
+        let returndata = self.returndata.heap.clone();
+        let call_tree_len = self.call_tree.len();
+
+        let result = self.call(to, value, data, gas, false);
+
+        let outcome = ExecutionOutcome::from(result);
+        let return_data = self.returndata.heap.clone();
+        let logs = self.state.logs[state.logs.len()..].to_vec();
+        let gas_used = gas.saturating_sub(self.gas);
+
+        // Diff the state changes before rolling them back
+        let mut addresses: Vec<[u8; 20]> = state
+            .accounts
+            .keys()
+            .chain(self.state.accounts.keys())
+            .copied()
+            .collect();
+        addresses.sort();
+        addresses.dedup();
+
+        let mut storage_changes = Vec::new();
+        let mut balance_changes = Vec::new();
+        for address in addresses {
+            let before = state.accounts.get(&address);
+            let after = self.state.accounts.get(&address);
+
+            let balance = |account: Option<&AccountState>| {
+                account.map(|account| account.balance).unwrap_or([0u8; 32])
+            };
+            if balance(before) != balance(after) {
+                balance_changes.push(BalanceChange {
+                    address,
+                    before: balance(before),
+                    after: balance(after),
+                });
+            }
+
+            let slot_value = |account: Option<&AccountState>, slot: [u8; 32]| {
+                account
+                    .and_then(|account| account.storage.get(&slot).copied())
+                    .unwrap_or([0u8; 32])
+            };
+            let mut slots: Vec<[u8; 32]> = before
+                .into_iter()
+                .chain(after)
+                .flat_map(|account| account.storage.keys().copied())
+                .collect();
+            slots.sort();
+            slots.dedup();
+            for slot in slots {
+                if slot_value(before, slot) != slot_value(after, slot) {
+                    storage_changes.push(StorageChange {
+                        address,
+                        slot,
+                        before: slot_value(before, slot),
+                        after: slot_value(after, slot),
+                    });
+                }
+            }
+        }
+
+        // Roll back
+        self.state = state;
+        self.accessed_addresses = accessed_addresses;
+        self.accessed_storage_keys = accessed_storage_keys;
+        self.gas = gas;
+        self.memory_gas = memory_gas;
+        self.execution_gas = execution_gas;
+        self.gas_refund = gas_refund;
+        self.returndata.heap = returndata;
+        self.call_tree.truncate(call_tree_len);
+
+        SimulationResult {
+            outcome,
+            return_data,
+            logs,
+            gas_used,
+            storage_changes,
+            balance_changes,
+        }
+    }
+
+    /// Calls a contract function from its Solidity signature. The selector is computed from
+    /// the signature, the arguments are ABI encoded and the return data is decoded with the
+    /// signature return types.
+    ///
+    /// # Arguments
+    ///
+    /// * `to` - The address of the contract to call.
+    /// * `signature` - The function signature, e.g. `"balanceOf(address)(uint256)"` or
+    ///   `"function balanceOf(address) returns (uint256)"`.
+    /// * `args` - The function arguments.
+    /// * `value` - The value to send with the call.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ExecutionError::AbiError` if the signature can't be parsed or if the arguments
+    /// or the return data don't match it, or the `ExecutionError` of the call if it fails.
+    ///
+    /// # Returns
+    ///
+    /// Returns the decoded return values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ethers::abi::Token;
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    /// // Runtime code returning 42 as a word
+    /// let init_code = hex::decode("67602a5f5260205ff35f5260086018f3").unwrap();
+    /// let address = runner.deploy(init_code).unwrap();
+    ///
+    /// let output = runner.call_fn(address, "get()(uint256)", &[], [0u8; 32]).unwrap();
+    /// assert_eq!(output, vec![Token::Uint(42.into())]);
+    /// ```
+    pub fn call_fn(
+        &mut self,
+        to: [u8; 20],
+        signature: &str,
+        args: &[Token],
+        value: [u8; 32],
+    ) -> Result<Vec<Token>, ExecutionError> {
+        let function = AbiParser::default()
+            .parse_function(signature)
+            .map_err(|error| ExecutionError::AbiError(error.to_string()))?;
+        let calldata = function
+            .encode_input(args)
+            .map_err(|error| ExecutionError::AbiError(error.to_string()))?;
+
+        self.call(to, value, calldata, self.gas, false)?;
+
+        function
+            .decode_output(&self.returndata.heap)
+            .map_err(|error| ExecutionError::AbiError(error.to_string()))
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                                Call frames                                 */
+    /* -------------------------------------------------------------------------- */
+
+    /// Saves the current frame and switches to the callee code. The callee is run by the
+    /// interpreter loop, without recursing on the native stack.
+    ///
+    /// # Arguments
+    ///
+    /// * `to` - The address of the contract to call.
+    /// * `value` - The value to send with the call.
+    /// * `calldata` - The input data to the contract.
+    /// * `kind` - The kind of the call, which tells how the result is handed back.
+    /// * `gas` - The gas requested by a message call, or `None` to forward all the gas.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError` without entering the callee if the call depth limit is
+    /// reached or if the value of a CALL or a CREATE can't be transferred.
+    pub(crate) fn enter_frame(
+        &mut self,
+        to: [u8; 20],
+        value: [u8; 32],
+        calldata: Vec<u8>,
+        kind: CallKind,
+        gas: Option<u64>,
+    ) -> Result<(), ExecutionError> {
+        // Check the call depth before touching the caller frame
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(ExecutionError::CallTooDeep);
+        }
+
+        // Load the callee code before touching the caller frame. A callee without code runs
+        // as an immediate STOP
+        let code = match self.state.get_code_at(to) {
+            Ok(code) => code.to_owned(),
+            Err(ExecutionError::CodeNotFound) => Vec::new(),
+            Err(error) => return Err(error),
+        };
+
+        // Save the accounts before the value transfer, which is undone with the callee changes
+        // if the call fails
+        let state_checkpoint = self.state.checkpoint();
+
+        // A CALL or a CREATE moves its value from the caller to the callee before running it
+        let moves_value = matches!(
+            kind,
+            CallKind::Call { .. } | CallKind::Create { .. } | CallKind::Create2 { .. }
+        );
+        if moves_value && value != [0u8; 32] {
+            if let Entry::Vacant(entry) = self.state.accounts.entry(to) {
+                entry.insert(AccountState {
+                    nonce: 0,
+                    balance: [0u8; 32],
+                    storage: HashMap::new(),
+                    code_hash: [0u8; 32],
+                });
+                if let Some(hook) = &self.state.on_account_created {
+                    hook(to);
+                }
+            }
+            self.state.transfer(self.address, to, value)?;
+        }
+
+        // Save the caller frame, moving its buffers instead of cloning them
+        let delegate = kind.is_delegate();
+        let static_call = kind.is_static();
+        let callee_debug_level = match self.debug_level {
+            Some(level) if level > 1 => Some(level - 1),
+            _ => Some(0),
+        };
+        // Flag a (caller, callee) pair already on the active call stack
+        let reentrant = self
+            .frames
+            .iter()
+            .any(|frame| frame.address == self.address && frame.call.address == to);
+        if reentrant && !self.reentrant_calls.contains(&(self.address, to)) {
+            self.reentrant_calls.push((self.address, to));
+        }
+
+        // Open the call tree node of the call
+        let call_type = kind.call_type();
+        let call = CallNode {
+            call_type,
+            address: to,
+            input: match call_type {
+                CallType::Create | CallType::Create2 => code.clone(),
+                _ => calldata.clone(),
+            },
+            output: Vec::new(),
+            success: false,
+            gas_used: 0,
+            children: Vec::new(),
+        };
+
+        // A callee gets all the remaining gas but one 64th (EIP-150), capped by the gas
+        // requested by a message call
+        let available = if self.hardfork.is_enabled(Hardfork::TangerineWhistle) {
+            self.gas - self.gas / 64
+        } else {
+            self.gas
+        };
+        let gas_reserved = match (&kind, gas) {
+            (CallKind::Create { .. } | CallKind::Create2 { .. }, _) => self.gas - available,
+            (_, Some(requested)) => self.gas - requested.min(available),
+            (_, None) => 0,
+        };
+
+        // A CALL sending value gives the stipend to the callee on top of the forwarded gas.
+        // The caller already paid for it with the value transfer cost
+        let stipend = match kind {
+            CallKind::Call { .. } if self.call_stipend && value != [0u8; 32] => CALL_STIPEND,
+            _ => 0,
+        };
+        self.gas = self.gas - gas_reserved + stipend;
+        self.last_forwarded_gas = Some(self.gas);
+
+        self.frames.push(Frame {
+            kind,
+            call,
+            gas_at_call: self.gas,
+            gas_reserved,
+            refund_checkpoint: self.gas_refund,
+            trace_start: self.trace_steps.as_ref().map_or(0, Vec::len),
+            transient_checkpoint: self.state.transient_checkpoint(),
+            state_checkpoint,
+            caller: self.caller,
+            callvalue: self.callvalue,
+            address: self.address,
+            calldata: std::mem::replace(&mut self.calldata, Memory::new(Some(calldata))),
+            returndata: std::mem::replace(&mut self.returndata, Memory::new(None)),
+            memory: std::mem::replace(&mut self.memory, Memory::new(None)),
+            stack: std::mem::replace(&mut self.stack, Stack::new()),
+            pc: std::mem::replace(&mut self.pc, 0),
+            debug_level: std::mem::replace(&mut self.debug_level, callee_debug_level),
+            static_mode: self.state.static_mode,
+            bytecode: std::mem::take(&mut self.bytecode),
+            jumpdests: std::mem::take(&mut self.jumpdests),
+        });
+
+        // Update runner state
         if !delegate {
-            self.caller = self.address.clone();
+            self.caller = self.address;
             self.callvalue = value;
             self.address = to;
         }
+        if static_call {
+            self.state.static_mode = true;
+        }
         self.call_depth += 1;
-        self.calldata = Memory::new(Some(calldata));
-        self.returndata = Memory::new(None);
-        self.memory = Memory::new(None);
-        self.stack = Stack::new();
-        self.pc = 0;
-        self.debug_level = if self.debug_level.is_some() && self.debug_level.unwrap() > 1 {
-            Some(self.debug_level.unwrap() - 1)
-        } else {
-            Some(0)
-        };
+        self.load_bytecode(code);
 
-        // Interpret the bytecode
-        let code = self.state.get_code_at(to)?.to_owned();
-        let interpret_result = self.interpret(code, self.debug_level, false);
+        Ok(())
+    }
 
-        // Check if the interpretation was successful
-        if interpret_result.is_err() {
-            error = Some(interpret_result.unwrap_err());
+    /// Returns the call tree node of the last call completed by the current frame.
+    pub(crate) fn last_call_mut(&mut self) -> Option<&mut CallNode> {
+        match self.frames.last_mut() {
+            Some(frame) => frame.call.children.last_mut(),
+            None => self.call_tree.last_mut(),
         }
+    }
+
+    /// Restores the caller frame once the callee ended.
+    ///
+    /// # Arguments
+    ///
+    /// * `callee_result` - The result of the callee execution.
+    ///
+    /// # Returns
+    ///
+    /// Returns the kind of the call and its result.
+    fn exit_frame(
+        &mut self,
+        callee_result: Result<(), ExecutionError>,
+    ) -> (CallKind, Result<(), ExecutionError>) {
+        // Record the high-water marks of the callee frame
+        self.max_stack_depth = self.max_stack_depth.max(self.stack.max_depth);
+        self.max_memory_size = self.max_memory_size.max(self.memory.max_size);
 
         // Get the return data
-        let return_data = self.returndata.heap.clone();
+        let return_data = std::mem::take(&mut self.returndata.heap);
+
+        // Restore the caller frame
+        let frame = self
+            .frames
+            .pop()
+            .expect("A callee frame always has a caller frame");
+        self.caller = frame.caller;
+        self.callvalue = frame.callvalue;
+        self.address = frame.address;
+        self.calldata = frame.calldata;
+        self.returndata = frame.returndata;
+        self.memory = frame.memory;
+        self.stack = frame.stack;
+        self.pc = frame.pc;
+        self.debug_level = frame.debug_level;
+        self.state.static_mode = frame.static_mode;
+        self.bytecode = frame.bytecode;
+        self.jumpdests = frame.jumpdests;
+        self.call_depth -= 1;
 
-        // Restore the initial runner state
-        if !delegate {
-            self.caller = initial_caller;
-            self.callvalue = initial_callvalue;
-            self.address = initial_address;
+        // Complete the call tree node and attach it to its parent call
+        let mut call = frame.call;
+        call.output = return_data.clone();
+        call.success = callee_result.is_ok();
+
+        // Only a successful or reverted callee leaves gas, an exceptional halt consumes it all
+        if !matches!(
+            callee_result,
+            Ok(()) | Err(ExecutionError::Revert(_) | ExecutionError::RevertWithoutData)
+        ) {
+            self.gas = 0;
+        }
+        call.gas_used = frame.gas_at_call.saturating_sub(self.gas);
+
+        // Give back the gas withheld from the callee
+        self.gas += frame.gas_reserved;
+
+        // Undo the refunds, transient storage writes, logs and account changes of a failed
+        // callee, including the value transfer. The refunds of a successful callee stay with
+        // the caller
+        if callee_result.is_err() {
+            self.gas_refund = frame.refund_checkpoint;
+            self.state.revert_transient(frame.transient_checkpoint);
+            self.state.revert_to(frame.state_checkpoint);
+        }
+
+        // Drop the steps of a successful callee from a failure trace. The steps of its failed
+        // callees are still in the trace and are kept
+        if self.trace_failures_only && callee_result.is_ok() {
+            if let Some(trace_steps) = self.trace_steps.as_mut() {
+                let callee_depth = self.call_depth + 1;
+                let kept: Vec<TraceStep> = trace_steps
+                    .get(frame.trace_start..)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter(|step| step.depth != callee_depth)
+                    .cloned()
+                    .collect();
+                trace_steps.truncate(frame.trace_start);
+                trace_steps.extend(kept);
+            }
+        }
+        match self.frames.last_mut() {
+            Some(parent) => parent.call.children.push(call),
+            None => self.call_tree.push(call),
         }
-        self.calldata = initial_calldata;
-        self.returndata = initial_returndata;
-        self.memory = initial_memory;
-        self.stack = initial_stack;
-        self.pc = initial_pc;
-        self.debug_level = initial_debug_level;
-        self.bytecode = initial_bytecode;
-        self.call_depth -= 1;
 
         // Write the return data to the initial state
         self.returndata.heap = return_data;
 
         // Increment the nonce of the caller
-        increment_nonce(self.address, self)?;
+        let result = increment_nonce(self.address, self).and(callee_result);
+
+        (frame.kind, result)
+    }
+
+    /// Restores the caller frame and completes the call opcode that entered the callee.
+    ///
+    /// # Arguments
+    ///
+    /// * `callee_result` - The result of the callee execution.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError` if the call opcode fails to complete.
+    fn return_to_caller(
+        &mut self,
+        callee_result: Result<(), ExecutionError>,
+    ) -> Result<(), ExecutionError> {
+        let (kind, result) = self.exit_frame(callee_result);
+        op_codes::system::complete_call(self, kind, result)
+    }
+
+    /// Runs the frame at the given depth until it ends. Calls made by this frame are run in
+    /// the same loop: their caller frames are saved on `frames` and restored once they return.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The depth of the frame to run, i.e. the length of `frames` while it runs.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `ExecutionError` the frame ended with.
+    fn execute(&mut self, depth: usize) -> Result<(), ExecutionError> {
+        let mut ended: Option<Result<(), ExecutionError>> = None;
+
+        loop {
+            let result = match ended.take() {
+                Some(result) => result,
+                None if self.pc < self.bytecode.len() => {
+                    match self.interpret_op_code(self.bytecode[self.pc]) {
+                        Ok(()) => continue,
+                        Err(error) => Err(error),
+                    }
+                }
+                None => Ok(()),
+            };
+
+            // The current frame ended
+            if let Err(error) = &result {
+                self.print_execution_error(error);
+            }
+
+            if self.frames.len() == depth {
+                return result;
+            }
 
-        if error.is_some() {
-            return Err(error.unwrap());
+            // Complete the call opcode of the caller, which may end the caller too
+            ended = self.return_to_caller(result).err().map(Err);
         }
+    }
 
-        // Return Ok
-        Ok(())
+    /// Marks every JUMPDEST of the current bytecode, skipping the PUSH data.
+    fn analyze_jumpdests(&mut self) {
+        self.jumpdests = vec![false; self.bytecode.len()];
+
+        for (pc, opcode, _) in scan_instructions(&self.bytecode) {
+            if opcode == 0x5b {
+                self.jumpdests[pc] = true;
+            }
+        }
+    }
+
+    /// Deploys a contract: runs the given init code at the address a CREATE from the runner
+    /// address would use, then stores the runtime code it returns.
+    ///
+    /// # Arguments
+    ///
+    /// * `init_code` - The contract init code (constructor).
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError` if the constructor fails or if the runtime code can't be
+    /// deployed (see `is_deployable_code`). The contract account is deleted in both cases.
+    ///
+    /// # Returns
+    ///
+    /// Returns the address of the deployed contract.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    /// // Return 4 FF as runtime code
+    /// let init_code = hex::decode("63ffffffff6000526004601cf3").unwrap();
+    /// let address = runner.deploy(init_code).unwrap();
+    /// assert_eq!(runner.state.get_code_at(address).unwrap(), &vec![0xff; 4]);
+    /// ```
+    pub fn deploy(&mut self, init_code: Vec<u8>) -> Result<[u8; 20], ExecutionError> {
+        // Compute the contract address
+        let nonce = U256::from_big_endian(&get_nonce(self.address, self)?).as_u64();
+        let contract_address = compute_create_address(self.address, nonce);
+
+        // Create the contract with init code as code and run its constructor
+        init_account(contract_address, self)?;
+        self.state.put_code_at(contract_address, init_code)?;
+        let call_result = self.call(contract_address, [0u8; 32], Vec::new(), self.gas, false);
+
+        // A successful creation leaves an empty return data
+        let runtime_code = std::mem::take(&mut self.returndata.heap);
+
+        let call_result = match call_result {
+            Ok(()) if !self.is_deployable_code(&runtime_code) => {
+                Err(ExecutionError::InvalidCodePrefix)
+            }
+            result => result,
+        };
+        if let Err(error) = call_result {
+            delete_account(contract_address, self)?;
+            return Err(error);
+        }
+
+        self.state.put_code_at(contract_address, runtime_code)?;
+
+        Ok(contract_address)
+    }
+
+    /// Runs the given init code and returns the runtime bytecode it deploys, without
+    /// committing anything to the state.
+    ///
+    /// The constructor is run at the address a CREATE from the runner address would use,
+    /// then the state, the return data and the remaining gas are rolled back.
+    ///
+    /// # Arguments
+    ///
+    /// * `init_code` - The contract init code (constructor).
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError` if the constructor fails or if the runtime code can't be
+    /// deployed (see `is_deployable_code`).
+    ///
+    /// # Returns
+    ///
+    /// Returns the runtime bytecode returned by the constructor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::Runner;
+    ///
+    /// let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    /// // Return 4 FF as runtime code
+    /// let init_code = hex::decode("63ffffffff6000526004601cf3").unwrap();
+    /// let runtime_code = runner.simulate_deploy(init_code).unwrap();
+    /// assert_eq!(runtime_code, vec![0xff; 4]);
+    /// ```
+    pub fn simulate_deploy(&mut self, init_code: Vec<u8>) -> Result<Vec<u8>, ExecutionError> {
+        // Store the initial runner state
+        let initial_state = self.state.clone();
+        let initial_returndata = self.returndata.clone();
+        let initial_gas = (self.gas, self.memory_gas, self.execution_gas);
+
+        // Compute the contract address
+        let nonce = U256::from_big_endian(&get_nonce(self.address, self)?).as_u64();
+        let contract_address = compute_create_address(self.address, nonce);
+
+        // Create the contract with init code as code and run its constructor
+        let call_result = init_account(contract_address, self)
+            .and_then(|_| self.state.put_code_at(contract_address, init_code))
+            .and_then(|_| self.call(contract_address, [0u8; 32], Vec::new(), self.gas, false));
+
+        // Get the runtime code
+        let runtime_code = self.returndata.heap.clone();
+
+        // Restore the initial runner state
+        self.state = initial_state;
+        self.returndata = initial_returndata;
+        (self.gas, self.memory_gas, self.execution_gas) = initial_gas;
+
+        call_result?;
+
+        if !self.is_deployable_code(&runtime_code) {
+            return Err(ExecutionError::InvalidCodePrefix);
+        }
+
+        Ok(runtime_code)
+    }
+
+    /// Returns `true` if the given runtime code can be deployed under the runner hardfork.
+    /// Since London (EIP-3541), a new contract code can't start with the 0xEF byte.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The runtime code returned by a constructor.
+    pub fn is_deployable_code(&self, code: &[u8]) -> bool {
+        !(self.hardfork.is_enabled(Hardfork::London) && code.first() == Some(&0xef))
     }
 
     /* -------------------------------------------------------------------------- */
     /*                               Debug functions                              */
     /* -------------------------------------------------------------------------- */
 
+    /// Prints an execution error with the program counter and opcode it happened at, unless
+    /// `print_errors` is disabled.
+    fn print_execution_error(&self, error: &ExecutionError) {
+        if !self.print_errors {
+            return;
+        }
+
+        println!(
+            "{} {}\n  {}: 0x{:X}\n  {}: 0x{:X}\n  {}",
+            "ERROR:".red(),
+            "Runtime error".red(),
+            "PC".yellow(),
+            self.pc,
+            "OpCode".yellow(),
+            self.bytecode.get(self.pc).copied().unwrap_or(0),
+            error.to_string().red()
+        );
+    }
+
+    /// Prints the call tree of the execution, labeling the calls with the functions of the
+    /// ABI if one is given.
+    ///
+    /// # Arguments
+    ///
+    /// * `abi` - The ABI of the called contracts, if known.
+    pub fn print_call_tree(&self, abi: Option<&Abi>) {
+        for call in &self.call_tree {
+            print!("{}", call.format_tree(abi));
+        }
+    }
+
     /// Prints a debug message with a tab prefix that indicates the current call depth.
     /// The more the call depth is high, the more the tab prefix will be long.
     pub fn print_debug(&self, s: &str) {
@@ -701,7 +2454,7 @@ impl Runner {
         );
         println!("{}", footer_line.clone().green());
 
-        let mut reversed_stack = self.stack.stack.clone();
+        let mut reversed_stack = self.stack.as_slice().to_vec();
         reversed_stack.reverse();
 
         // Print all the stack 32 bytes elements with a space between each bytes
@@ -761,6 +2514,294 @@ impl Runner {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_memory_expansion_charge() {
+        use crate::core_module::asm::assemble;
+        use crate::core_module::memory::memory_expansion_cost;
+
+        let run = |bytecode: &str| {
+            let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+            let result =
+                runner.interpret_with_gas_limit(assemble(bytecode).unwrap(), 1_000_000, None);
+            (result, runner.gas_used())
+        };
+
+        // Storing a word 16 KiB away pays for the expansion of the memory
+        let (result, near) = run("PUSH1 0x2a\nPUSH2 0x0000\nMSTORE");
+        assert!(result.is_ok());
+        let (result, far) = run("PUSH1 0x2a\nPUSH2 0x4000\nMSTORE");
+        assert!(result.is_ok());
+        assert_eq!(
+            far - near,
+            memory_expansion_cost(0, 0x4020) - memory_expansion_cost(0, 0x20)
+        );
+
+        // An access beyond the maximum memory size runs out of gas
+        let (result, _) = run("PUSH1 0x2a\nPUSH4 0x80000000\nMSTORE");
+        assert_eq!(result.unwrap_err(), ExecutionError::OutOfGas);
+    }
+
+    #[test]
+    fn test_memory_gas() {
+        use crate::core_module::asm::assemble;
+        use crate::core_module::memory::memory_expansion_cost;
+
+        // Store a word 16 KiB away, then hash it
+        let bytecode =
+            assemble("PUSH1 0x2a\nPUSH2 0x4000\nMSTORE\nPUSH1 0x20\nPUSH2 0x4000\nKECCAK256")
+                .unwrap();
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner
+            .interpret_with_gas_limit(bytecode, 1_000_000, None)
+            .unwrap();
+
+        // Only the MSTORE expanded the memory, which dominates the gas used
+        assert_eq!(runner.memory_gas, memory_expansion_cost(0, 0x4020));
+        assert!(runner.memory_gas > 10 * runner.execution_gas);
+        assert_eq!(runner.memory_gas + runner.execution_gas, runner.gas_used());
+
+        // Without memory accesses, all the gas is execution gas
+        runner
+            .interpret_with_gas_limit(vec![0x60, 0x01, 0x60, 0x02, 0x01], 1_000_000, None)
+            .unwrap();
+        assert_eq!(runner.memory_gas, 0);
+        assert_eq!(runner.execution_gas, runner.gas_used());
+    }
+
+    #[test]
+    fn test_interpret_with_gas_limit() {
+        // PUSH1 0x01 PUSH1 0x02 SWAP1 POP
+        let bytecode = vec![0x60, 0x01, 0x60, 0x02, 0x90, 0x50];
+        let run = |gas_limit: u64| {
+            let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+            let result = runner.interpret_with_gas_limit(bytecode.clone(), gas_limit, None);
+            (runner, result)
+        };
+
+        // Bisect the minimum gas needed to complete the program
+        let (mut low, mut high) = (0u64, 1_000u64);
+        while low < high {
+            let mid = (low + high) / 2;
+            if run(mid).1.is_ok() {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        assert_eq!(low, 11);
+
+        let (runner, result) = run(low);
+        assert!(result.is_ok());
+        assert_eq!(runner.gas, 0);
+        assert_eq!(runner.gas_used(), 11);
+
+        // One gas short halts on the POP without executing it
+        let (runner, result) = run(low - 1);
+        assert_eq!(result.unwrap_err(), ExecutionError::OutOfGas);
+        assert_eq!(runner.pc, 5);
+        assert_eq!(runner.gas, 1);
+        assert_eq!(runner.stack.stack.len(), 2);
+        assert_eq!(runner.stack.stack[1], pad_left(&[0x01]));
+    }
+
+    #[test]
+    fn test_gas_used_per_transaction() {
+        // PUSH1 0x01 PUSH1 0x02 SWAP1 POP
+        let bytecode = vec![0x60, 0x01, 0x60, 0x02, 0x90, 0x50];
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // A reused runner doesn't add up the gas of its transactions
+        runner.interpret(bytecode.clone(), None, true).unwrap();
+        assert_eq!(runner.gas_used(), 11);
+        runner.pc = 0;
+        runner.interpret(bytecode, None, true).unwrap();
+        assert_eq!(runner.gas_used(), 11);
+        assert_eq!(runner.execution_gas, 11);
+
+        // A remaining gas above the gas limit doesn't underflow
+        runner.gas = runner.gas_limit + 1;
+        assert_eq!(runner.gas_used(), 0);
+    }
+
+    #[test]
+    fn test_simulate_deploy() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let accounts_len = runner.state.accounts.len();
+        let codes_len = runner.state.codes.len();
+        let nonce = get_nonce(runner.address, &mut runner).unwrap();
+
+        // MSTORE 0xffffffff at 0 then RETURN 4 bytes at 0x1c
+        let init_code = utils::bytes::_hex_string_to_bytes("63ffffffff6000526004601cf3");
+        let runtime_code = runner.simulate_deploy(init_code).unwrap();
+        assert_eq!(runtime_code, vec![0xff, 0xff, 0xff, 0xff]);
+
+        // Nothing was committed to the state
+        assert_eq!(runner.state.accounts.len(), accounts_len);
+        assert_eq!(runner.state.codes.len(), codes_len);
+        assert_eq!(get_nonce(runner.address, &mut runner).unwrap(), nonce);
+        assert!(runner.returndata.heap.is_empty());
+    }
+
+    #[test]
+    fn test_high_water_marks() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        // PUSH1 x3, POP x2, PUSH1, then MSTORE at 0x40
+        let bytecode = utils::bytes::_hex_string_to_bytes("6001600260035050600460405200");
+        let result = runner.interpret(bytecode, None, true);
+
+        assert!(result.is_ok());
+        assert_eq!(runner.stack.stack.len(), 1);
+        assert_eq!(runner.max_stack_depth, 3);
+        assert_eq!(runner.max_memory_size, 96);
+    }
+
+    #[test]
+    fn test_calldata_and_returndata_bytes() {
+        let mut runner = Runner::new(
+            [0xaa; 20],
+            None,
+            None,
+            None,
+            Some(vec![0xde, 0xad, 0xbe, 0xef]),
+            None,
+        );
+        assert_eq!(runner.calldata_bytes(), &[0xde, 0xad, 0xbe, 0xef]);
+        assert!(runner.returndata_bytes().is_empty());
+
+        // MSTORE 0xaa at 0 then RETURN 1 byte at 0x1f
+        let bytecode = utils::bytes::_hex_string_to_bytes("60aa6000526001601ff3");
+        let result = runner.interpret(bytecode, None, true);
+
+        assert!(result.is_ok());
+        assert_eq!(runner.returndata_bytes(), &[0xaa]);
+        assert_eq!(runner.calldata_bytes(), &[0xde, 0xad, 0xbe, 0xef]);
+    }
+
+    #[test]
+    fn test_step_back() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.enable_step_journal(8);
+
+        // PUSH1 0x01 PUSH1 0x02 ADD
+        runner.load_bytecode(utils::bytes::_hex_string_to_bytes("6001600201"));
+        runner.step().unwrap();
+        runner.step().unwrap();
+        let gas = runner.gas;
+        runner.step().unwrap();
+        assert_eq!(runner.stack.stack, vec![pad_left(&[0x03])]);
+
+        // Undo ADD
+        assert!(runner.step_back());
+        assert_eq!(runner.pc, 4);
+        assert_eq!(runner.gas, gas);
+        assert_eq!(
+            runner.stack.stack,
+            vec![pad_left(&[0x01]), pad_left(&[0x02])]
+        );
+
+        // Step forward again
+        runner.step().unwrap();
+        assert_eq!(runner.stack.stack, vec![pad_left(&[0x03])]);
+    }
+
+    #[test]
+    fn test_step_back_memory_and_storage() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.enable_step_journal(8);
+
+        // PUSH1 0xff PUSH1 0x00 MSTORE PUSH1 0x02 PUSH1 0x00 SSTORE
+        runner.load_bytecode(utils::bytes::_hex_string_to_bytes(
+            "60ff60005260026000556000",
+        ));
+        for _ in 0..6 {
+            runner.step().unwrap();
+        }
+        let slot = [0u8; 32];
+        assert_eq!(
+            runner.state.accounts[&runner.address].storage[&slot],
+            pad_left(&[0x02])
+        );
+
+        // Undo SSTORE
+        runner.step_back();
+        assert!(!runner.state.accounts[&runner.address]
+            .storage
+            .contains_key(&slot));
+        assert_eq!(runner.stack.stack.len(), 2);
+
+        // Undo the pushes and MSTORE
+        runner.step_back();
+        runner.step_back();
+        runner.step_back();
+        assert_eq!(runner.pc, 4);
+        assert!(runner.memory.heap.is_empty());
+        assert_eq!(runner.stack.stack.len(), 2);
+        assert_eq!(runner.journal.as_ref().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_step_back_overwritten_memory() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.enable_step_journal(8);
+
+        // PUSH1 0x2a PUSH1 0xff PUSH0 MSTORE PUSH1 0xee PUSH1 0x10 MSTORE
+        runner.load_bytecode(utils::bytes::_hex_string_to_bytes("602a60ff5f5260ee601052"));
+        for _ in 0..7 {
+            runner.step().unwrap();
+        }
+        assert_eq!(runner.memory.heap[31], 0x00);
+        assert_eq!(runner.memory.heap[47], 0xee);
+
+        // The diff holds the MSTORE inputs and the overwritten bytes only
+        let diff = runner.journal.as_mut().unwrap().pop().unwrap();
+        assert_eq!(diff.stack_kept, 1);
+        assert_eq!(diff.stack_removed.len(), 2);
+        assert_eq!(diff.memory_offset, 0x10);
+        assert_eq!(diff.memory_overwritten.len(), 0x10);
+        runner.journal.as_mut().unwrap().push(diff);
+
+        // Undo the second MSTORE
+        assert!(runner.step_back());
+        assert_eq!(runner.memory.heap, pad_left(&[0xff]).to_vec());
+        assert_eq!(
+            runner.stack.stack,
+            vec![pad_left(&[0x2a]), pad_left(&[0xee]), pad_left(&[0x10])]
+        );
+    }
+
+    #[test]
+    fn test_try_set_pc() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        // PUSH1 0x5b JUMPDEST STOP
+        let _ = runner.interpret(vec![0x60, 0x5b, 0x5b, 0x00], None, true);
+
+        assert_eq!(
+            runner.try_set_pc(10).unwrap_err(),
+            ExecutionError::OutOfBoundsByteCode
+        );
+        assert_eq!(
+            runner.try_set_pc(4).unwrap_err(),
+            ExecutionError::OutOfBoundsByteCode
+        );
+
+        // 0x5b inside the PUSH1 data is not a JUMPDEST
+        assert_eq!(
+            runner.try_set_pc(1).unwrap_err(),
+            ExecutionError::InvalidJumpDestination
+        );
+        assert_eq!(runner.pc, 4);
+
+        assert!(runner.try_set_pc(2).is_ok());
+        assert_eq!(runner.get_pc(), 2);
+
+        // Bytecode replaced without a new jumpdest analysis
+        runner.bytecode = vec![0x00; 16];
+        assert_eq!(
+            runner.try_set_pc(10).unwrap_err(),
+            ExecutionError::InvalidJumpDestination
+        );
+    }
+
     #[test]
     fn test_push0() {
         let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
@@ -798,4 +2839,684 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_export_run() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        assert!(runner.export_run()["result"]["outcome"].is_null());
+
+        // SSTORE(1, 0x2a) MSTORE(0, 0xaa) LOG0(0, 32) STOP
+        let bytecode = utils::bytes::_hex_string_to_bytes("602a60015560aa5f5260205fa000");
+        let result = runner.run(bytecode, None);
+        assert!(result.outcome.is_success());
+
+        let run = runner.export_run();
+        for key in ["input", "result", "state"] {
+            assert!(run.get(key).is_some(), "missing key {}", key);
+        }
+
+        assert_eq!(run["input"]["bytecode"], "0x602a60015560aa5f5260205fa000");
+        assert_eq!(run["input"]["caller"], format!("0x{}", "aa".repeat(20)));
+        assert_eq!(run["input"]["block"]["chain_id"], 1);
+        assert_eq!(run["result"]["outcome"], "success");
+        assert_eq!(run["result"]["gas_used"], result.gas_used);
+        assert_eq!(run["result"]["stack"], serde_json::json!([]));
+
+        let address = format!("0x{}", hex::encode(runner.address));
+        let slot = format!("0x{}", hex::encode(pad_left(&[0x01])));
+        assert_eq!(
+            run["state"]["accounts"][&address]["storage"][&slot],
+            format!("0x{}", hex::encode(pad_left(&[0x2a])))
+        );
+        assert_eq!(run["state"]["logs"].as_array().unwrap().len(), 1);
+
+        // A halt is exported with its error
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.run(vec![0xfe], None);
+        let run = runner.export_run();
+        assert_eq!(run["result"]["outcome"], "halt");
+        assert_eq!(run["result"]["error"], "Invalid op code 0xFE");
+    }
+
+    #[test]
+    fn test_scan_mode() {
+        // PUSH1 0x01 STOP PUSH1 0x02 ADD INVALID PUSH2 0x5b (truncated)
+        let bytecode = utils::bytes::_hex_string_to_bytes("600100600201fe615b");
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.scan_mode = true;
+        let result = runner.interpret(bytecode.clone(), None, true);
+
+        // Every instruction is visited, nothing is executed
+        assert!(result.is_ok());
+        assert_eq!(runner.scanned_pcs, vec![0, 2, 3, 5, 6, 7]);
+        assert!(runner.stack.stack.is_empty());
+        assert_eq!(runner.gas_used(), 0);
+
+        // The normal execution stops at the STOP
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        assert!(runner.interpret(bytecode, None, true).is_ok());
+        assert!(runner.scanned_pcs.is_empty());
+        assert_eq!(runner.stack.stack, vec![pad_left(&[0x01])]);
+    }
+
+    #[test]
+    fn test_simulate_call() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // PUSH1 0x2a PUSH1 0x01 SSTORE, then return the calldata
+        let contract = [0xbb; 20];
+        init_account(contract, &mut runner).unwrap();
+        runner
+            .state
+            .put_code_at(
+                contract,
+                utils::bytes::_hex_string_to_bytes("602a60015536600060003760206000f3"),
+            )
+            .unwrap();
+
+        let gas = runner.gas;
+        let nonce = runner.state.accounts[&runner.address].nonce;
+        let result = runner.simulate_call(contract, pad_left(&[0x07]).to_vec(), [0u8; 32]);
+
+        assert!(result.outcome.is_success());
+        assert_eq!(result.return_data, pad_left(&[0x07]).to_vec());
+        assert!(result.gas_used > 0);
+        assert!(result.logs.is_empty());
+        assert!(result.balance_changes.is_empty());
+        assert_eq!(
+            result.storage_changes,
+            vec![StorageChange {
+                address: contract,
+                slot: pad_left(&[0x01]),
+                before: [0u8; 32],
+                after: pad_left(&[0x2a]),
+            }]
+        );
+
+        // Nothing was committed
+        let slot = runner.state.sload(contract, pad_left(&[0x01])).unwrap();
+        assert_eq!(slot, [0u8; 32]);
+        assert_eq!(runner.gas, gas);
+        assert_eq!(runner.state.accounts[&runner.address].nonce, nonce);
+        assert!(runner.call_tree.is_empty());
+    }
+
+    #[test]
+    fn test_generated_access_list() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // PUSH1 0x2a PUSH1 0x01 SSTORE PUSH20 0xbb..bb BALANCE POP
+        let mut bytecode = utils::bytes::_hex_string_to_bytes("602a60015573");
+        bytecode.extend([0xbb; 20]);
+        bytecode.extend([0x31, 0x50]);
+        init_account([0xbb; 20], &mut runner).unwrap();
+        runner.interpret(bytecode, None, true).unwrap();
+
+        let access_list = runner.generated_access_list();
+        assert_eq!(access_list.0.len(), 2);
+        assert!(access_list.0.contains(&AccessListItem {
+            address: H160::from(runner.address),
+            storage_keys: vec![H256::from(pad_left(&[0x01]))],
+        }));
+        assert!(access_list.0.contains(&AccessListItem {
+            address: H160::from([0xbb; 20]),
+            storage_keys: vec![],
+        }));
+    }
+
+    #[test]
+    fn test_missing_reads() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // PUSH1 0x01 SLOAD POP PUSH20 0xcc..cc EXTCODESIZE
+        let mut bytecode = utils::bytes::_hex_string_to_bytes("6001545073");
+        bytecode.extend([0xcc; 20]);
+        bytecode.push(0x3b);
+        let result = runner.run(bytecode, None);
+
+        assert!(result.outcome.is_success());
+        assert!(result.has_missing_reads());
+        assert_eq!(
+            result.missing_slots,
+            vec![(runner.address, pad_left(&[0x01]))]
+        );
+        assert_eq!(result.missing_accounts, vec![[0xcc; 20]]);
+
+        // A written slot is not missing
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        // PUSH1 0x2a PUSH1 0x01 SSTORE PUSH1 0x01 SLOAD
+        let result = runner.run(utils::bytes::_hex_string_to_bytes("602a600155600154"), None);
+        assert!(!result.has_missing_reads());
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.enable_trace();
+
+        // PUSH1 0x04 JUMP INVALID JUMPDEST PUSH1 0x2a STOP
+        let bytecode = utils::bytes::_hex_string_to_bytes("600456fe5b602a00");
+        runner.interpret(bytecode, None, true).unwrap();
+
+        let trace = runner.trace();
+        assert_eq!(trace.len(), 5);
+        let pcs: Vec<usize> = trace.iter().map(|step| step.pc).collect();
+        assert_eq!(pcs, vec![0, 2, 4, 5, 7]);
+        assert_eq!(trace[3].stack_top, Some(pad_left(&[0x2a])));
+        assert_eq!(trace[0].gas, 30_000_000);
+        assert!(trace[4].gas < trace[0].gas);
+
+        // PUSH0 x5 PUSH20 0xcc..cc PUSH2 0xffff CALL STOP, the callee runs STOP
+        init_account([0xcc; 20], &mut runner).unwrap();
+        runner.state.put_code_at([0xcc; 20], vec![0x00]).unwrap();
+        let mut bytecode = utils::bytes::_hex_string_to_bytes("5f5f5f5f5f73");
+        bytecode.extend([0xcc; 20]);
+        bytecode.extend(utils::bytes::_hex_string_to_bytes("61fffff100"));
+        runner.pc = 0;
+        runner.interpret(bytecode, None, true).unwrap();
+
+        // The previous run was forgotten
+        let trace = runner.trace();
+        assert_eq!(trace.len(), 10);
+        assert_eq!(trace[7].opcode, 0xf1);
+        assert_eq!(trace[7].stack_top, None);
+        assert_eq!((trace[8].opcode, trace[8].depth), (0x00, 1));
+        assert_eq!(
+            (trace[9].depth, trace[9].stack_top),
+            (0, Some(pad_left(&[0x01])))
+        );
+    }
+
+    #[test]
+    fn test_failure_trace() {
+        use crate::core_module::asm::assemble;
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.enable_failure_trace();
+        let (ok, reverting) = ([0xa1; 20], [0xb2; 20]);
+
+        // The first callee stops, the second one reverts
+        init_account(ok, &mut runner).unwrap();
+        runner.state.put_code_at(ok, vec![0x00]).unwrap();
+        init_account(reverting, &mut runner).unwrap();
+        runner
+            .state
+            .put_code_at(reverting, assemble("PUSH0\nPUSH0\nREVERT").unwrap())
+            .unwrap();
+
+        let call = |to: [u8; 20]| {
+            format!(
+                "PUSH0\nPUSH0\nPUSH0\nPUSH0\nPUSH0\nPUSH20 0x{}\nGAS\nCALL\nPOP\n",
+                hex::encode(to)
+            )
+        };
+        let bytecode = assemble(&(call(ok) + &call(reverting) + "STOP")).unwrap();
+        runner.interpret(bytecode, None, true).unwrap();
+
+        // The steps of the successful callee are dropped, its CALL is kept
+        let trace = runner.trace();
+        let callee_steps: Vec<(u8, u32)> = trace
+            .iter()
+            .filter(|step| step.depth > 0)
+            .map(|step| (step.opcode, step.depth))
+            .collect();
+        assert_eq!(callee_steps, vec![(0x5f, 1), (0x5f, 1), (0xfd, 1)]);
+        assert_eq!(trace.iter().filter(|step| step.opcode == 0xf1).count(), 2);
+        assert_eq!(trace.len(), 2 * 9 + 3 + 1);
+    }
+
+    #[test]
+    fn test_failure_trace_nested_revert() {
+        use crate::core_module::asm::assemble;
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.enable_failure_trace();
+        let (ok, reverting) = ([0xa1; 20], [0xb2; 20]);
+        let call = |to: [u8; 20]| {
+            format!(
+                "PUSH0\nPUSH0\nPUSH0\nPUSH0\nPUSH0\nPUSH20 0x{}\nGAS\nCALL\nPOP\n",
+                hex::encode(to)
+            )
+        };
+
+        // The callee calls a reverting callee, then stops
+        init_account(ok, &mut runner).unwrap();
+        runner
+            .state
+            .put_code_at(ok, assemble(&(call(reverting) + "STOP")).unwrap())
+            .unwrap();
+        init_account(reverting, &mut runner).unwrap();
+        runner
+            .state
+            .put_code_at(reverting, assemble("PUSH0\nPUSH0\nREVERT").unwrap())
+            .unwrap();
+
+        let bytecode = assemble(&(call(ok) + "STOP")).unwrap();
+        runner.interpret(bytecode, None, true).unwrap();
+
+        // The steps of the successful callee are dropped, the ones of its failed callee are kept
+        let callee_steps: Vec<(u8, u32)> = runner
+            .trace()
+            .iter()
+            .filter(|step| step.depth > 0)
+            .map(|step| (step.opcode, step.depth))
+            .collect();
+        assert_eq!(callee_steps, vec![(0x5f, 2), (0x5f, 2), (0xfd, 2)]);
+        assert_eq!(runner.trace().len(), 9 + 3 + 1);
+    }
+
+    #[test]
+    fn test_run_until_first_log() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        // PUSH0 PUSH0 LOG0 PUSH1 0x2a PUSH0 SSTORE PUSH0 PUSH0 LOG0
+        runner.load_bytecode(utils::bytes::_hex_string_to_bytes("5f5fa0602a5f555f5fa0"));
+
+        let stopped = runner.run_until(|runner| !runner.state.logs.is_empty());
+        assert_eq!(stopped, Ok(true));
+
+        // Stopped right after the first LOG0
+        assert_eq!(runner.pc, 3);
+        assert_eq!(runner.state.logs.len(), 1);
+        assert_eq!(
+            runner.state.sload(runner.address, [0u8; 32]).unwrap(),
+            [0u8; 32]
+        );
+
+        // The rest of the program still runs
+        assert_eq!(runner.run_until(|_| false), Ok(false));
+        assert_eq!(runner.state.logs.len(), 2);
+        assert_eq!(
+            runner.state.sload(runner.address, [0u8; 32]).unwrap(),
+            pad_left(&[0x2a])
+        );
+    }
+
+    #[test]
+    fn test_call_fn() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // Getter whose runtime code returns 42: PUSH1 0x2a PUSH0 MSTORE PUSH1 0x20 PUSH0 RETURN
+        let init_code = utils::bytes::_hex_string_to_bytes("67602a5f5260205ff35f5260086018f3");
+        let getter = runner.deploy(init_code).unwrap();
+        let output = runner
+            .call_fn(getter, "function get() returns (uint256)", &[], [0u8; 32])
+            .unwrap();
+        assert_eq!(output, vec![Token::Uint(42.into())]);
+
+        // Return the first argument: PUSH1 0x04 CALLDATALOAD PUSH0 MSTORE PUSH1 0x20 PUSH0 RETURN
+        let echo = [0xbb; 20];
+        init_account(echo, &mut runner).unwrap();
+        runner
+            .state
+            .put_code_at(
+                echo,
+                utils::bytes::_hex_string_to_bytes("6004355f5260205ff3"),
+            )
+            .unwrap();
+        let output = runner
+            .call_fn(
+                echo,
+                "echo(uint256)(uint256)",
+                &[Token::Uint(7.into())],
+                [0u8; 32],
+            )
+            .unwrap();
+        assert_eq!(output, vec![Token::Uint(7.into())]);
+
+        // Arguments must match the signature
+        let result = runner.call_fn(echo, "echo(uint256)(uint256)", &[], [0u8; 32]);
+        assert!(matches!(result, Err(ExecutionError::AbiError(_))));
+    }
+
+    #[test]
+    fn test_interpret_with_calldata() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+
+        // Return CALLDATALOAD(0) * 2
+        let bytecode = utils::bytes::_hex_string_to_bytes("5f356002025f5260205ff3");
+
+        runner
+            .interpret_with_calldata(bytecode.clone(), pad_left(&[0x03]).to_vec(), None)
+            .unwrap();
+        assert_eq!(runner.returndata.heap, pad_left(&[0x06]).to_vec());
+        let gas_used = runner.gas_used();
+
+        runner
+            .interpret_with_calldata(bytecode, pad_left(&[0x05]).to_vec(), None)
+            .unwrap();
+        assert_eq!(runner.returndata.heap, pad_left(&[0x0a]).to_vec());
+
+        // The second run started from a clean frame
+        assert!(runner.stack.stack.is_empty());
+        assert_eq!(runner.gas_used(), gas_used);
+    }
+
+    #[test]
+    fn test_interpret_with_calldata_cold_accesses() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        init_account([0xcc; 20], &mut runner).unwrap();
+        runner.state.put_code_at([0xcc; 20], vec![0x00]).unwrap();
+
+        // SLOAD(1) POP, then STATICCALL 0xcc..cc
+        let bytecode = utils::bytes::_hex_string_to_bytes(
+            "60015450600060006000600073cccccccccccccccccccccccccccccccccccccccc61fffffa",
+        );
+
+        runner
+            .interpret_with_calldata(bytecode.clone(), Vec::new(), None)
+            .unwrap();
+        let gas_used = runner.gas_used();
+
+        // The slot and the callee are cold again in the second run
+        runner
+            .interpret_with_calldata(bytecode, Vec::new(), None)
+            .unwrap();
+        assert_eq!(runner.gas_used(), gas_used);
+        assert!(gas_used > 2100 + 2600);
+    }
+
+    #[test]
+    fn test_with_config() {
+        let config = EvmConfig {
+            block: BlockEnv {
+                chain_id: 10,
+                number: U256::from(1234),
+                basefee: 100,
+                coinbase: [0xc1; 20],
+                ..BlockEnv::default()
+            },
+            tx: TxEnv {
+                max_fee_per_gas: 150,
+                max_priority_fee_per_gas: 20,
+            },
+            hardfork: Hardfork::London,
+            gas_limit: 1_000_000,
+            fork_url: None,
+        };
+        let mut runner = Runner::with_config([0xaa; 20], config);
+        assert_eq!(runner.hardfork, Hardfork::London);
+        assert_eq!(runner.gas, 1_000_000);
+
+        // CHAINID NUMBER GASPRICE STOP
+        let bytecode = utils::bytes::_hex_string_to_bytes("46433a00");
+        runner.interpret(bytecode, None, true).unwrap();
+
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[120]));
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0x04, 0xd2]));
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[10]));
+    }
+
+    #[test]
+    fn test_custom_coinbase() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.block.coinbase = [0xc1; 20];
+        runner.tx = TxEnv {
+            max_fee_per_gas: 20,
+            max_priority_fee_per_gas: 2,
+        };
+
+        let balance = |runner: &Runner, address: [u8; 20]| {
+            runner
+                .state
+                .accounts
+                .get(&address)
+                .map_or(U256::zero(), |account| {
+                    U256::from_big_endian(&account.balance)
+                })
+        };
+        let origin_before = balance(&runner, runner.origin);
+
+        // PUSH1 0x01 POP COINBASE
+        runner
+            .interpret(vec![0x60, 0x01, 0x50, 0x41], None, true)
+            .unwrap();
+        assert_eq!(runner.stack.pop().unwrap(), pad_left(&[0xc1; 20]));
+        let gas_used = U256::from(runner.gas_used());
+        assert!(!gas_used.is_zero());
+
+        // The coinbase received the priority fee of the gas used
+        assert_eq!(balance(&runner, [0xc1; 20]), gas_used * U256::from(2));
+
+        // The origin paid exactly the effective gas price of the gas used
+        let price = runner.tx.effective_gas_price(runner.block.basefee);
+        assert_eq!(
+            origin_before - balance(&runner, runner.origin),
+            gas_used * U256::from(price)
+        );
+    }
+
+    #[test]
+    fn test_gas_fee() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.block.basefee = 7;
+        runner.block.coinbase = [0xc1; 20];
+        runner.tx = TxEnv {
+            max_fee_per_gas: 20,
+            max_priority_fee_per_gas: 2,
+        };
+        let balance = |runner: &Runner, address: [u8; 20]| {
+            runner
+                .state
+                .accounts
+                .get(&address)
+                .map_or(U256::zero(), |account| {
+                    U256::from_big_endian(&account.balance)
+                })
+        };
+        let origin_before = balance(&runner, runner.origin);
+
+        // PUSH1 0x01 POP
+        runner
+            .interpret(vec![0x60, 0x01, 0x50], None, true)
+            .unwrap();
+
+        // The origin paid the effective gas price, the coinbase got the priority fee and the
+        // base fee was burnt
+        let gas_used = U256::from(runner.gas_used());
+        let burnt = gas_used * U256::from(7);
+        assert_eq!(
+            origin_before - balance(&runner, runner.origin),
+            gas_used * U256::from(9)
+        );
+        assert_eq!(balance(&runner, [0xc1; 20]), gas_used * U256::from(2));
+        assert_eq!(
+            balance(&runner, runner.origin) + balance(&runner, [0xc1; 20]) + burnt,
+            origin_before
+        );
+
+        // An origin that can't pay for the gas limit fails the transaction before it runs
+        let origin = runner.origin;
+        runner.state.accounts.get_mut(&origin).unwrap().balance = pad_left(&[0x64]);
+        runner.pc = 0;
+        let result = runner.interpret(vec![0x60, 0x01, 0x50], None, true);
+        assert_eq!(result.unwrap_err(), ExecutionError::InsufficientBalance);
+        assert_eq!(runner.pc, 0);
+    }
+
+    #[test]
+    fn test_print_errors_disabled() {
+        // Run the test again in a child process to capture its output
+        if std::env::var_os("EVM_RS_PRINT_ERRORS_CHILD").is_none() {
+            let test_name = module_path!().split_once("::").unwrap().1.to_owned()
+                + "::test_print_errors_disabled";
+            let output = std::process::Command::new(std::env::current_exe().unwrap())
+                .args([&test_name, "--exact", "--nocapture", "--test-threads=1"])
+                .env("EVM_RS_PRINT_ERRORS_CHILD", "1")
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            assert!(output.status.success(), "{}", stdout);
+
+            let section = |name: &str| {
+                let start = stdout.find(&format!("<{}>", name)).unwrap() + name.len() + 3;
+                let end = stdout.find(&format!("</{}>", name)).unwrap();
+                stdout[start..end].to_owned()
+            };
+            assert!(section("enabled").contains("Invalid jump destination"));
+            assert_eq!(section("disabled"), "");
+            return;
+        }
+
+        // The errors are printed by default
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        assert!(runner.print_errors);
+        println!("<enabled>");
+        let _ = runner.interpret(vec![0x60, 0x01, 0x56], None, true);
+        println!("</enabled>");
+
+        // The errors are still returned without being printed
+        println!("<disabled>");
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.print_errors = false;
+        assert_eq!(
+            runner.interpret(Vec::new(), None, true).unwrap_err(),
+            ExecutionError::EmptyByteCode
+        );
+        // PUSH1 0x01 JUMP
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.print_errors = false;
+        assert_eq!(
+            runner
+                .interpret(vec![0x60, 0x01, 0x56], None, true)
+                .unwrap_err(),
+            ExecutionError::InvalidJumpDestination
+        );
+        println!("</disabled>");
+    }
+
+    #[test]
+    fn test_stack_check_underflow() {
+        // PUSH1 0x01 ADD
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.check_stack_io = true;
+        let result = runner.interpret(utils::bytes::_hex_string_to_bytes("600101"), None, true);
+        assert_eq!(result, Err(ExecutionError::StackTooSmall));
+        // ADD didn't pop its only input
+        assert_eq!(runner.stack.stack, vec![pad_left(&[0x01])]);
+
+        // PUSH0 x16 SWAP16
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.check_stack_io = true;
+        let bytecode = "5f".repeat(16) + "9f";
+        let result = runner.interpret(utils::bytes::_hex_string_to_bytes(&bytecode), None, true);
+        assert_eq!(result, Err(ExecutionError::StackTooSmall));
+
+        // Without the check, ADD pops its input before failing
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.check_stack_io = false;
+        let result = runner.interpret(utils::bytes::_hex_string_to_bytes("600101"), None, true);
+        assert_eq!(result, Err(ExecutionError::StackTooSmall));
+        assert!(runner.stack.stack.is_empty());
+    }
+
+    #[test]
+    fn test_stack_check_overflow() {
+        // PUSH0 x1024 DUP1
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.check_stack_io = true;
+        let bytecode = "5f".repeat(1024) + "80";
+        let result = runner.interpret(utils::bytes::_hex_string_to_bytes(&bytecode), None, true);
+        assert_eq!(result, Err(ExecutionError::StackTooDeep));
+        assert_eq!(runner.pc, 1024);
+        assert_eq!(runner.stack.stack.len(), 1024);
+
+        // DUP1 was not charged
+        assert_eq!(runner.gas_used(), 1024 * 2);
+
+        // SWAP1 doesn't grow a full stack
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.check_stack_io = true;
+        let bytecode = "5f".repeat(1024) + "90";
+        let result = runner.interpret(utils::bytes::_hex_string_to_bytes(&bytecode), None, true);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_lenient_mode() {
+        // PUSH1 0x01 0x0c (undefined) PUSH1 0x02
+        let bytecode = vec![0x60, 0x01, 0x0c, 0x60, 0x02];
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        assert_eq!(
+            runner.interpret(bytecode.clone(), None, true).unwrap_err(),
+            ExecutionError::InvalidOpcode(0x0c)
+        );
+
+        // The undefined byte stops the execution
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.lenient_mode = true;
+        assert!(runner.interpret(bytecode, None, true).is_ok());
+        assert_eq!(runner.stack.stack, vec![pad_left(&[0x01])]);
+
+        // INVALID still aborts
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.lenient_mode = true;
+        assert_eq!(
+            runner.interpret(vec![0xfe], None, true).unwrap_err(),
+            ExecutionError::InvalidOpcode(0xfe)
+        );
+    }
+
+    #[test]
+    fn test_current_op() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        // PUSH1 0x01 PUSH1 0x02 ADD STOP
+        runner.load_bytecode(vec![0x60, 0x01, 0x60, 0x02, 0x01, 0x00]);
+        assert_eq!(runner.current_op(), Some(0x60));
+
+        runner.step().unwrap();
+        runner.step().unwrap();
+        assert_eq!(runner.pc, 4);
+        assert_eq!(runner.current_op(), Some(0x01));
+        assert_eq!(
+            runner.disassemble_around(1),
+            vec![
+                (2, "PUSH1 0x02".to_string()),
+                (4, "ADD".to_string()),
+                (5, "STOP".to_string())
+            ]
+        );
+
+        runner.step().unwrap();
+        runner.step().unwrap();
+        assert_eq!(runner.current_op(), None);
+        assert_eq!(runner.disassemble_around(1), vec![(5, "STOP".to_string())]);
+    }
+
+    #[test]
+    fn test_max_steps() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.max_steps = Some(1000);
+
+        // JUMPDEST PUSH0 JUMP loops forever
+        let result = runner.interpret(vec![0x5b, 0x5f, 0x56], None, true);
+        assert_eq!(result.unwrap_err(), ExecutionError::StepLimitReached);
+        assert_eq!(runner.steps, 1000);
+        assert!(runner.gas > 0);
+
+        // A run within the limit completes
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.max_steps = Some(2);
+        assert!(runner.interpret(vec![0x5f, 0x5f], None, true).is_ok());
+        assert_eq!(runner.steps, 2);
+    }
+
+    #[test]
+    fn test_run_on_another_thread() {
+        use crate::core_module::provider::MockProvider;
+        use std::sync::Arc;
+
+        // A forked runner can be moved to a worker thread
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        runner.state.provider = Some(Arc::new(MockProvider::default()));
+
+        let runner = std::thread::spawn(move || {
+            runner.interpret(vec![0x60, 0x01], None, true).unwrap();
+            runner
+        })
+        .join()
+        .unwrap();
+        assert_eq!(runner.pc, 2);
+    }
 }
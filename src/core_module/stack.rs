@@ -5,13 +5,34 @@ use super::utils::errors::ExecutionError;
 pub struct Stack {
     /// The stack itself
     pub stack: Vec<[u8; 32]>,
+    /// The maximum depth reached by the stack
+    pub max_depth: usize,
 }
 
 /// Implements a stack data structure for the EVM emulator.
 impl Stack {
     /// Creates a new stack instance.
     pub fn new() -> Self {
-        Self { stack: vec![] }
+        Self {
+            stack: vec![],
+            max_depth: 0,
+        }
+    }
+
+    /// Returns the words of the stack, from the bottom to the top.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::Stack;
+    ///
+    /// let mut stack = Stack::new();
+    /// stack.push([0x01; 32]).unwrap();
+    /// stack.push([0x02; 32]).unwrap();
+    /// assert_eq!(stack.as_slice(), &[[0x01; 32], [0x02; 32]]);
+    /// ```
+    pub fn as_slice(&self) -> &[[u8; 32]] {
+        &self.stack
     }
 
     /// Pushes a 32-byte word onto the stack.
@@ -30,7 +51,9 @@ impl Stack {
             return Err(ExecutionError::StackTooDeep);
         }
 
-        Ok(self.stack.push(word))
+        self.stack.push(word);
+        self.update_max_depth();
+        Ok(())
     }
 
     /// Pop a word off the stack
@@ -69,6 +92,7 @@ impl Stack {
 
         let word = self.stack[self.stack.len() - index];
         self.stack.push(word);
+        self.update_max_depth();
 
         Ok(word)
     }
@@ -102,6 +126,13 @@ impl Stack {
 
         Ok([word1, word2])
     }
+
+    /// Records the current stack depth if it is the deepest reached so far.
+    fn update_max_depth(&mut self) {
+        if self.stack.len() > self.max_depth {
+            self.max_depth = self.stack.len();
+        }
+    }
 }
 
 /// Implements the `Clone` trait for the `Stack` struct.
@@ -110,6 +141,27 @@ impl Clone for Stack {
     fn clone(&self) -> Self {
         Self {
             stack: self.stack.clone(),
+            max_depth: self.max_depth,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_as_slice() {
+        let mut stack = Stack::new();
+        assert!(stack.as_slice().is_empty());
+
+        stack.push([0x01; 32]).unwrap();
+        stack.push([0x02; 32]).unwrap();
+        stack.push([0x03; 32]).unwrap();
+        assert_eq!(stack.as_slice(), &[[0x01; 32], [0x02; 32], [0x03; 32]]);
+
+        stack.pop().unwrap();
+        stack.swap(1).unwrap();
+        assert_eq!(stack.as_slice(), &[[0x02; 32], [0x01; 32]]);
+    }
+}
@@ -1,20 +1,44 @@
-use std::{collections::HashMap, fmt};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, fs,
+};
 
+#[cfg(feature = "alloy")]
+use super::utils::primitives::U256Compat;
+use super::utils::primitives::{keccak256, U256};
 use ethers::prelude::*;
-use ethers::{types::U256, utils::keccak256};
 
 use crate::core_module::utils;
 
+use super::provider::StateProvider;
 use super::utils::errors::ExecutionError;
 
 // Colored output
 use colored::*;
 
+/// The code of an account without code.
+static EMPTY_CODE: Vec<u8> = Vec::new();
+
+/// The magic bytes starting a state cache file, followed by the format version.
+const CACHE_MAGIC: &[u8; 4] = b"EVMC";
+const CACHE_VERSION: u8 = 2;
+
+/// The keccak256 hash of an empty code (and of any empty input).
+pub(crate) const EMPTY_CODE_HASH: [u8; 32] = [
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+    0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+];
+
 /* -------------------------------------------------------------------------- */
 /*                             AccountState struct                            */
 /* -------------------------------------------------------------------------- */
 
 /// Represents the state of an account on the Ethereum Virtual Machine.
+#[derive(Clone)]
 pub struct AccountState {
     /// The account's nonce, which is incremented each time a transaction is sent from the account.
     pub nonce: u64,
@@ -29,7 +53,7 @@ pub struct AccountState {
 /// Implements the Debug trait for the AccountState struct, which allows for the struct to be printed in a formatted way.
 /// The function prints the nonce, balance, code hash, and storage of the account state.
 /// If the code hash is empty, it prints "Empty code" instead of the hash.
-/// For each storage slot and value, it prints them in a formatted way.
+/// For each storage slot and value, sorted by slot, it prints them in a formatted way.
 /// If the storage is empty, it prints "Empty storage".
 impl fmt::Debug for AccountState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -43,21 +67,26 @@ impl fmt::Debug for AccountState {
             f,
             "  {}: {}",
             "Balance".magenta(),
-            U256::from(self.balance).to_string()
+            U256::from_big_endian(&self.balance).to_string()
         )?;
         writeln!(f, "  {}: {}", "Code Hash".magenta(), code_hash)?;
         write!(f, "  {}: ", "Storage".magenta())?;
-        for (slot, value) in &self.storage {
-            println!("\n┌────────────────────────────────────────────────────────────────────────────────────────────────────────┐");
+
+        // Sort the slots so the output is reproducible
+        let mut storage: Vec<_> = self.storage.iter().collect();
+        storage.sort();
+
+        for (slot, value) in storage {
+            writeln!(f, "\n┌────────────────────────────────────────────────────────────────────────────────────────────────────────┐")?;
             // Print the slot
             let hex: String = utils::debug::to_hex_string(slot.to_owned());
-            println!("│ {}:  {} │", "Slot".bright_blue(), hex);
+            writeln!(f, "│ {}:  {} │", "Slot".bright_blue(), hex)?;
 
             // Print the value
             let hex: String = utils::debug::to_hex_string(value.to_owned());
-            println!("│ {}: {} │", "Value".blue(), hex);
+            writeln!(f, "│ {}: {} │", "Value".blue(), hex)?;
 
-            println!("└────────────────────────────────────────────────────────────────────────────────────────────────────────┘");
+            write!(f, "└────────────────────────────────────────────────────────────────────────────────────────────────────────┘")?;
         }
         if self.storage.is_empty() {
             write!(f, "  {}", "Empty storage".red())?;
@@ -71,6 +100,7 @@ impl fmt::Debug for AccountState {
 /* -------------------------------------------------------------------------- */
 
 /// Represents a log entry in the Ethereum Virtual Machine (EVM) state.
+#[derive(Clone)]
 pub struct Log {
     /// The address of the contract that generated the log.
     pub address: [u8; 20],
@@ -80,6 +110,26 @@ pub struct Log {
     pub data: Vec<u8>,
 }
 
+/// Implementation of the Log struct.
+impl Log {
+    /// Returns the data of the log as a `0x`-prefixed hex string.
+    pub fn data_hex(&self) -> String {
+        utils::debug::to_prefixed_hex(&self.data)
+    }
+
+    /// Returns a topic of the log as a `0x`-prefixed hex string, or `None` if the log has
+    /// fewer topics.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the topic.
+    pub fn topic_hex(&self, index: usize) -> Option<String> {
+        self.topics
+            .get(index)
+            .map(|topic| utils::debug::to_prefixed_hex(topic))
+    }
+}
+
 /// Implements the Debug trait for the Log struct, which allows for the struct to be printed in a formatted way.
 /// The function writes the address, topics, and data of the Log struct to the provided formatter.
 /// If the topics vector is not empty, it prints each topic in a formatted way.
@@ -116,23 +166,98 @@ impl fmt::Debug for Log {
     }
 }
 
+/* -------------------------------------------------------------------------- */
+/*                           StateCheckpoint struct                           */
+/* -------------------------------------------------------------------------- */
+
+/// The accounts and logs of an `EvmState` when a call is made, restored by `revert_to` if the
+/// call fails. The transient storage has its own checkpoint, see `transient_checkpoint`.
+#[derive(Debug, Clone)]
+pub struct StateCheckpoint {
+    accounts: HashMap<[u8; 20], AccountState>,
+    code_overrides: HashMap<[u8; 20], [u8; 32]>,
+    storage_overrides: HashMap<([u8; 20], [u8; 32]), [u8; 32]>,
+    forked_accounts: HashSet<[u8; 20]>,
+    destroyed_accounts: HashSet<[u8; 20]>,
+    logs_len: usize,
+}
+
 /* -------------------------------------------------------------------------- */
 /*                              EVM state struct                              */
 /* -------------------------------------------------------------------------- */
 
+/// A transient storage write: the account and slot written, and the value they held before.
+pub type TransientWrite = (([u8; 20], [u8; 32]), Option<[u8; 32]>);
+
+/// A callback invoked with the address of an account. It is shared with the clones of the
+/// state and may be called from another thread, as the state can be moved to one.
+pub type AccountHook = Arc<dyn Fn([u8; 20]) + Send + Sync>;
+
 /// Represents the state of the Ethereum Virtual Machine (EVM).
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct EvmState {
     /// A mapping of account addresses to their respective account states.
     pub accounts: HashMap<[u8; 20], AccountState>,
     /// A mapping of code hashes to their respective code.
     pub codes: HashMap<[u8; 32], Vec<u8>>,
+    /// The code hashes overriding the code of forked accounts unknown to the local state.
+    pub code_overrides: HashMap<[u8; 20], [u8; 32]>,
+    /// The storage slots overridden on forked accounts unknown to the local state.
+    pub storage_overrides: HashMap<([u8; 20], [u8; 32]), [u8; 32]>,
     /// A vector of logs generated during the execution of the EVM.
     pub logs: Vec<Log>,
+    /// The transient storage (EIP-1153), keyed by account address and slot.
+    pub transient_storage: HashMap<([u8; 20], [u8; 32]), [u8; 32]>,
+    /// The transient storage writes, undone by `revert_transient` when a call fails.
+    pub transient_journal: Vec<TransientWrite>,
     /// A flag indicating whether the EVM is in static mode or not.
     pub static_mode: bool,
-    /// An optional provider for interacting with the Ethereum network.
-    pub provider: Option<Provider<Http>>,
+    /// An optional provider for fetching the state of unknown accounts (forking).
+    pub provider: Option<Arc<dyn StateProvider + Send + Sync>>,
+    /// An optional callback invoked when an account is created.
+    pub on_account_created: Option<AccountHook>,
+    /// An optional callback invoked when an account is destroyed.
+    pub on_account_destroyed: Option<AccountHook>,
+    /// The accounts copied from the fork provider, whose storage slots unset locally are
+    /// still fetched from it.
+    pub forked_accounts: HashSet<[u8; 20]>,
+    /// The unknown accounts whose balance or code was read without a provider.
+    pub missing_accounts: Vec<[u8; 20]>,
+    /// The unset storage slots read without a provider, defaulted to zero.
+    pub missing_slots: Vec<([u8; 20], [u8; 32])>,
+    /// The read-only state overlaid by this one, see `overlay`.
+    pub base: Option<Arc<EvmState>>,
+    /// The accounts of the base destroyed in this overlay, whose reads no longer fall through
+    /// to the base.
+    pub destroyed_accounts: HashSet<[u8; 20]>,
+    /// An optional channel streaming the logs as they are emitted, see `stream_logs`.
+    pub log_sink: Option<SyncSender<Log>>,
+}
+
+/// Implements the Debug trait for the EvmState struct.
+/// The provider and the hooks can't be printed, only their presence is.
+impl fmt::Debug for EvmState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EvmState")
+            .field("accounts", &self.accounts)
+            .field("codes", &self.codes)
+            .field("code_overrides", &self.code_overrides)
+            .field("storage_overrides", &self.storage_overrides)
+            .field("logs", &self.logs)
+            .field("transient_storage", &self.transient_storage)
+            .field("transient_journal", &self.transient_journal)
+            .field("static_mode", &self.static_mode)
+            .field("provider", &self.provider.is_some())
+            .field("on_account_created", &self.on_account_created.is_some())
+            .field("on_account_destroyed", &self.on_account_destroyed.is_some())
+            .field("forked_accounts", &self.forked_accounts)
+            .field("missing_accounts", &self.missing_accounts)
+            .field("missing_slots", &self.missing_slots)
+            .field("base", &self.base.is_some())
+            .field("destroyed_accounts", &self.destroyed_accounts)
+            .field("log_sink", &self.log_sink.is_some())
+            .finish()
+    }
 }
 
 /// Implementation of the EVM state.
@@ -150,13 +275,308 @@ impl EvmState {
         Self {
             accounts: HashMap::new(),
             codes: HashMap::new(),
+            code_overrides: HashMap::new(),
+            storage_overrides: HashMap::new(),
             logs: Vec::new(),
+            transient_storage: HashMap::new(),
+            transient_journal: Vec::new(),
             static_mode: false,
             provider: if fork_url.is_some() {
-                Some(Provider::<Http>::try_from(fork_url.unwrap()).unwrap())
+                Some(Arc::new(
+                    Provider::<Http>::try_from(fork_url.unwrap()).unwrap(),
+                ))
             } else {
                 None
             },
+            on_account_created: None,
+            on_account_destroyed: None,
+            forked_accounts: HashSet::new(),
+            missing_accounts: Vec::new(),
+            missing_slots: Vec::new(),
+            base: None,
+            destroyed_accounts: HashSet::new(),
+            log_sink: None,
+        }
+    }
+
+    /// Creates a state overlaying a read-only base state, to branch from a prepared state
+    /// without cloning it.
+    ///
+    /// Reads fall through to the base (then to the base provider) for the accounts unknown to
+    /// the overlay. An account of the base is copied to the overlay the first time it is
+    /// written, so writes never reach the base. An account of the base destroyed in the
+    /// overlay reads as empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - The state to overlay, shared with the other branches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use evm_rs_emulator::EvmState;
+    ///
+    /// let mut base = EvmState::new(None);
+    /// base.override_storage([0x11; 20], [0x01; 32], [0x2a; 32]);
+    /// let base = Arc::new(base);
+    ///
+    /// let mut branch = EvmState::overlay(base.clone());
+    /// branch.sstore([0x11; 20], [0x01; 32], [0x07; 32]).unwrap();
+    /// assert_eq!(branch.sload([0x11; 20], [0x01; 32]).unwrap(), [0x07; 32]);
+    /// assert_eq!(base.accounts[&[0x11; 20]].storage[&[0x01; 32]], [0x2a; 32]);
+    /// ```
+    pub fn overlay(base: Arc<EvmState>) -> Self {
+        let mut state = Self::new(None);
+        state.provider = Some(base.clone());
+        state.base = Some(base);
+        state
+    }
+
+    /// Streams the logs emitted from now on through a bounded channel, so that a consumer
+    /// thread can process them while the execution runs. The logs are still collected in
+    /// `logs`. Logs of calls that revert later are streamed too.
+    ///
+    /// Emitting a log blocks while the channel is full, and the stream stops once the
+    /// receiver is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The number of logs the channel holds before blocking the execution.
+    ///
+    /// # Returns
+    ///
+    /// Returns the receiving end of the channel. It is disconnected once the state is dropped
+    /// or `log_sink` is reset.
+    pub fn stream_logs(&mut self, capacity: usize) -> Receiver<Log> {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        self.log_sink = Some(sender);
+        receiver
+    }
+
+    /// Collects a log emitted by a LOG opcode and sends it to the log stream, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `log` - The emitted log.
+    pub fn emit_log(&mut self, log: Log) {
+        if let Some(sink) = &self.log_sink {
+            // Stop streaming once nobody listens
+            if sink.send(log.clone()).is_err() {
+                self.log_sink = None;
+            }
+        }
+
+        self.logs.push(log);
+    }
+
+    /// Copies an account of the base state to the overlay before it is written (copy on
+    /// write). Does nothing if the account is already known, destroyed or if there is no base.
+    ///
+    /// An account only overridden in a forked base is copied with the balance and nonce served
+    /// by the base and the overridden code and slots. Its other slots keep falling back to the
+    /// base, as do the unset slots of an account the base fetched from its provider.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the account about to be written.
+    pub(crate) fn copy_base_account(&mut self, address: [u8; 20]) {
+        if self.accounts.contains_key(&address) || self.destroyed_accounts.contains(&address) {
+            return;
+        }
+        let base = match self.base.clone() {
+            Some(base) => base,
+            None => return,
+        };
+
+        // Look the account up in the chain of base states
+        let mut code_override = None;
+        let mut storage = HashMap::new();
+        let mut state = Some(&base);
+        while let Some(current) = state {
+            if let Some(account) = current.accounts.get(&address) {
+                if let Some(code) = current.codes.get(&account.code_hash) {
+                    self.codes.insert(account.code_hash, code.clone());
+                }
+                self.accounts.insert(address, account.clone());
+                // The unset slots of an account fetched by the base still come from the fork
+                if current.forked_accounts.contains(&address) {
+                    self.forked_accounts.insert(address);
+                }
+                return;
+            }
+            if current.destroyed_accounts.contains(&address) {
+                break;
+            }
+
+            // The nearest overrides of a forked account win
+            if code_override.is_none() {
+                code_override = current.code_overrides.get(&address).copied();
+            }
+            for ((account, slot), value) in &current.storage_overrides {
+                if *account == address {
+                    storage.entry(*slot).or_insert(*value);
+                }
+            }
+            state = current.base.as_ref();
+        }
+
+        if code_override.is_none() && storage.is_empty() {
+            return;
+        }
+        let code_hash = match StateProvider::get_code(base.as_ref(), address) {
+            Ok(code) if !code.is_empty() => {
+                let code_hash = keccak256(&code);
+                self.codes.insert(code_hash, code);
+                code_hash
+            }
+            _ => [0u8; 32],
+        };
+        self.accounts.insert(
+            address,
+            AccountState {
+                nonce: StateProvider::get_nonce(base.as_ref(), address).unwrap_or(0),
+                balance: StateProvider::get_balance(base.as_ref(), address).unwrap_or([0u8; 32]),
+                storage,
+                code_hash,
+            },
+        );
+        self.forked_accounts.insert(address);
+    }
+
+    /// Removes an account from the state. The account of an overlay base is marked as
+    /// destroyed, so that it no longer falls through to the base.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the account to remove.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the account existed, locally or in the base.
+    pub(crate) fn destroy_account(&mut self, address: [u8; 20]) -> bool {
+        // An account created again at the address doesn't have the forked storage
+        self.forked_accounts.remove(&address);
+        self.code_overrides.remove(&address);
+        self.storage_overrides
+            .retain(|(account, _), _| *account != address);
+
+        let existed = self.accounts.remove(&address).is_some();
+        match self.base.as_ref() {
+            Some(base) => {
+                let in_base =
+                    !self.destroyed_accounts.contains(&address) && base.has_account(address);
+                self.destroyed_accounts.insert(address);
+                existed || in_base
+            }
+            None => existed,
+        }
+    }
+
+    /// Returns `true` if the account is known to the state or to its chain of base states.
+    fn has_account(&self, address: [u8; 20]) -> bool {
+        if self.accounts.contains_key(&address) {
+            return true;
+        }
+        if self.destroyed_accounts.contains(&address) {
+            return false;
+        }
+        self.code_overrides.contains_key(&address)
+            || self
+                .storage_overrides
+                .keys()
+                .any(|(account, _)| *account == address)
+            || self
+                .base
+                .as_ref()
+                .is_some_and(|base| base.has_account(address))
+    }
+
+    /// Returns `true` if the account was destroyed in this overlay and not created again.
+    pub(crate) fn is_destroyed(&self, address: [u8; 20]) -> bool {
+        !self.accounts.contains_key(&address) && self.destroyed_accounts.contains(&address)
+    }
+
+    /// Copies an account unknown to the local state from the fork provider on its first read,
+    /// so that its balance, nonce and code are fetched once and saved by `save_cache`. Its
+    /// storage slots are fetched and saved as they are read. The overrides of the account are
+    /// applied to the copy.
+    ///
+    /// Does nothing without provider, or if the provider is the base of an overlay, which is
+    /// already local.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the account about to be read.
+    pub(crate) fn fetch_account(&mut self, address: [u8; 20]) {
+        if self.accounts.contains_key(&address) || self.base.is_some() {
+            return;
+        }
+        let provider = match self.provider.as_ref() {
+            Some(provider) => provider.clone(),
+            None => return,
+        };
+
+        // Leave the account unknown if the provider fails, so that reads still fall back to it
+        let (balance, nonce) = match (provider.get_balance(address), provider.get_nonce(address)) {
+            (Ok(balance), Ok(nonce)) => (balance, nonce),
+            _ => return,
+        };
+        let code_hash = match self.code_overrides.get(&address) {
+            Some(code_hash) => *code_hash,
+            None => match provider.get_code(address) {
+                Ok(code) => {
+                    let code_hash = keccak256(&code);
+                    self.codes.insert(code_hash, code);
+                    code_hash
+                }
+                Err(_) => return,
+            },
+        };
+
+        // Move the overrides of the account to the copy
+        self.code_overrides.remove(&address);
+        let storage = self
+            .storage_overrides
+            .iter()
+            .filter(|((account, _), _)| *account == address)
+            .map(|((_, slot), value)| (*slot, *value))
+            .collect();
+        self.storage_overrides
+            .retain(|(account, _), _| *account != address);
+
+        self.accounts.insert(
+            address,
+            AccountState {
+                nonce,
+                balance,
+                storage,
+                code_hash,
+            },
+        );
+        self.forked_accounts.insert(address);
+    }
+
+    /// Records an unknown account read without a provider, once.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the account.
+    pub(crate) fn record_missing_account(&mut self, address: [u8; 20]) {
+        if !self.missing_accounts.contains(&address) {
+            self.missing_accounts.push(address);
+        }
+    }
+
+    /// Records an unset storage slot read without a provider, once.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The address of the account.
+    /// * `slot` - The slot read.
+    fn record_missing_slot(&mut self, account: [u8; 20], slot: [u8; 32]) {
+        if !self.missing_slots.contains(&(account, slot)) {
+            self.missing_slots.push((account, slot));
         }
     }
 
@@ -192,6 +612,9 @@ impl EvmState {
             return Err(ExecutionError::StaticCallStateChanged);
         }
 
+        self.copy_base_account(from);
+        self.copy_base_account(to);
+
         let value_u256 = U256::from_big_endian(&value);
 
         let from_balance = U256::from_big_endian(
@@ -234,6 +657,61 @@ impl EvmState {
         Ok(())
     }
 
+    /// Returns the nonce of an account. Unknown accounts are read from the provider, or have
+    /// a nonce of 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the account.
+    pub fn nonce(&self, address: [u8; 20]) -> u64 {
+        StateProvider::get_nonce(self, address).unwrap_or(0)
+    }
+
+    /// Sets the nonce of an account before an execution, e.g. to predict a CREATE address.
+    /// The static mode is bypassed and an unknown account is created. A forked account is
+    /// fetched first, so that it keeps its balance, code and storage.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the account.
+    /// * `nonce` - The new nonce of the account.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::EvmState;
+    ///
+    /// let mut state = EvmState::new(None);
+    /// assert_eq!(state.nonce([0x11; 20]), 0);
+    /// state.set_nonce([0x11; 20], 7);
+    /// assert_eq!(state.nonce([0x11; 20]), 7);
+    /// ```
+    pub fn set_nonce(&mut self, address: [u8; 20], nonce: u64) {
+        self.fetch_account(address);
+        self.copy_base_account(address);
+
+        if let Some(account_state) = self.accounts.get_mut(&address) {
+            account_state.nonce = nonce;
+        } else {
+            self.init_override_account(address).nonce = nonce;
+        }
+    }
+
+    /// Returns `true` if the account has code. Unknown accounts are read from the provider, or
+    /// have no code.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the account.
+    pub fn is_contract(&self, address: [u8; 20]) -> bool {
+        match self.accounts.get(&address) {
+            Some(account_state) => {
+                account_state.code_hash != [0u8; 32] && account_state.code_hash != EMPTY_CODE_HASH
+            }
+            None => StateProvider::get_code(self, address).is_ok_and(|code| !code.is_empty()),
+        }
+    }
+
     /// Loads a 256-bit value from the storage of the given account at the given slot.
     /// If the account is not found in the emulator's local state, the storage value is fetched from the provider.
     /// If the provider is not set, or if the storage fetch fails, the function returns a zero-filled 256-bit value.
@@ -251,43 +729,48 @@ impl EvmState {
     ///
     /// Returns a 32-byte array representing the value at the given slot.
     pub fn sload(&mut self, account: [u8; 20], slot: [u8; 32]) -> Result<[u8; 32], ExecutionError> {
-        match self.accounts.get(&account) {
-            Some(account_state) => match account_state.storage.get(&slot) {
-                Some(value) => Ok(*value),
-                None => Ok([0u8; 32]),
-            },
-            None => {
-                let provider = self.provider.as_ref();
+        self.fetch_account(account);
 
-                if provider.is_none() {
+        let forked = match self.accounts.get(&account) {
+            Some(account_state) => {
+                if let Some(value) = account_state.storage.get(&slot) {
+                    return Ok(*value);
+                }
+                self.forked_accounts.contains(&account)
+            }
+            None => {
+                // Destroyed accounts of an overlay base have an empty storage
+                if self.destroyed_accounts.contains(&account) {
                     return Ok([0u8; 32]);
                 }
+                // Overridden slots of the accounts of an overlay base
+                if let Some(value) = self.storage_overrides.get(&(account, slot)) {
+                    return Ok(*value);
+                }
+                true
+            }
+        };
 
-                let contract_address = Address::from(account);
-                let future =
-                    provider
-                        .unwrap()
-                        .get_storage_at(contract_address, H256::from(&slot), None);
-
-                // Block on the future and get the result
-                let storage_result = tokio::runtime::Runtime::new()
-                    .expect("Could not create a Runtime")
-                    .block_on(future);
-
-                match storage_result {
-                    Ok(storage) => {
-                        let storage_bytes = storage.to_fixed_bytes();
-
-                        // Save the fetched storage data locally
-                        if let Some(account_state) = self.accounts.get_mut(&account) {
-                            account_state.storage.insert(slot, storage_bytes);
-                        }
+        // Slots of local accounts are never fetched
+        let provider = match self.provider.as_ref() {
+            Some(provider) if forked => provider,
+            Some(_) => return Ok([0u8; 32]),
+            None => {
+                self.record_missing_slot(account, slot);
+                return Ok([0u8; 32]);
+            }
+        };
 
-                        Ok(storage_bytes)
-                    }
-                    Err(_) => Ok([0u8; 32]),
+        match provider.get_storage_at(account, slot) {
+            Ok(storage_bytes) => {
+                // Save the fetched slot on the forked account
+                if let Some(account_state) = self.accounts.get_mut(&account) {
+                    account_state.storage.insert(slot, storage_bytes);
                 }
+
+                Ok(storage_bytes)
             }
+            Err(_) => Ok([0u8; 32]),
         }
     }
 
@@ -314,6 +797,8 @@ impl EvmState {
             return Err(ExecutionError::StaticCallStateChanged);
         }
 
+        self.copy_base_account(account);
+
         match self.accounts.get_mut(&account) {
             Some(account_state) => {
                 account_state.storage.insert(slot, value);
@@ -323,7 +808,104 @@ impl EvmState {
         }
     }
 
+    /// Loads a 256-bit value from the transient storage of the given account at the given slot.
+    /// Unset slots read as zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - An array of 20 bytes representing the address of the account to load from.
+    /// * `slot` - An array of 32 bytes representing the slot to load from.
+    ///
+    /// # Returns
+    ///
+    /// Returns a 32-byte array representing the value at the given slot.
+    pub fn tload(&self, account: [u8; 20], slot: [u8; 32]) -> [u8; 32] {
+        match self.transient_storage.get(&(account, slot)) {
+            Some(value) => *value,
+            None => [0u8; 32],
+        }
+    }
+
+    /// Stores a value in the transient storage of an account.
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The address of the account to store the value in.
+    /// * `slot` - The slot in the transient storage to store the value in.
+    /// * `value` - The value to store in the specified slot.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `ExecutionError` if the static mode is enabled.
+    pub fn tstore(
+        &mut self,
+        account: [u8; 20],
+        slot: [u8; 32],
+        value: [u8; 32],
+    ) -> Result<(), ExecutionError> {
+        // Check if static mode is enabled
+        if self.static_mode {
+            return Err(ExecutionError::StaticCallStateChanged);
+        }
+
+        let previous = self.transient_storage.insert((account, slot), value);
+        self.transient_journal.push(((account, slot), previous));
+        Ok(())
+    }
+
+    /// Returns a checkpoint of the transient storage, to undo the writes made after it with
+    /// `revert_transient`.
+    pub fn transient_checkpoint(&self) -> usize {
+        self.transient_journal.len()
+    }
+
+    /// Undoes the transient storage writes made since the given checkpoint, e.g. those of a
+    /// failed call (EIP-1153).
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoint` - The checkpoint returned by `transient_checkpoint`.
+    pub fn revert_transient(&mut self, checkpoint: usize) {
+        while self.transient_journal.len() > checkpoint {
+            let (key, previous) = self.transient_journal.pop().unwrap();
+            match previous {
+                Some(value) => self.transient_storage.insert(key, value),
+                None => self.transient_storage.remove(&key),
+            };
+        }
+    }
+
+    /// Returns a checkpoint of the accounts and logs, to undo the changes made after it with
+    /// `revert_to`.
+    pub fn checkpoint(&self) -> StateCheckpoint {
+        StateCheckpoint {
+            accounts: self.accounts.clone(),
+            code_overrides: self.code_overrides.clone(),
+            storage_overrides: self.storage_overrides.clone(),
+            forked_accounts: self.forked_accounts.clone(),
+            destroyed_accounts: self.destroyed_accounts.clone(),
+            logs_len: self.logs.len(),
+        }
+    }
+
+    /// Undoes the changes made to the accounts since the given checkpoint and drops the logs
+    /// emitted since then, e.g. those of a failed call: storage writes, value transfers,
+    /// nonces, and created or destroyed accounts. The codes stay known by their hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoint` - The checkpoint returned by `checkpoint`.
+    pub fn revert_to(&mut self, checkpoint: StateCheckpoint) {
+        self.accounts = checkpoint.accounts;
+        self.code_overrides = checkpoint.code_overrides;
+        self.storage_overrides = checkpoint.storage_overrides;
+        self.forked_accounts = checkpoint.forked_accounts;
+        self.destroyed_accounts = checkpoint.destroyed_accounts;
+        self.logs.truncate(checkpoint.logs_len);
+    }
+
     /// Returns the code at the given address. If the code is not already in the state, it will be fetched from the blockchain using the provider.
+    /// An existing account without code (zero or empty code hash) has an empty code.
     ///
     /// # Arguments
     ///
@@ -331,41 +913,48 @@ impl EvmState {
     ///
     /// # Errors
     ///
-    /// Returns an `ExecutionError` if the code is not found.
+    /// Returns an `ExecutionError::CodeNotFound` if the address is unknown and can't be fetched,
+    /// or if the account code hash has no matching code.
     ///
     /// # Returns
     ///
     /// Returns a reference to the Vec<u8> of the code.
     pub fn get_code_at(&mut self, address: [u8; 20]) -> Result<&Vec<u8>, ExecutionError> {
+        self.fetch_account(address);
+
         match self.accounts.get(&address) {
             Some(account_state) => {
                 let code_hash = account_state.code_hash;
+
+                // Accounts without code
+                if code_hash == [0u8; 32] || code_hash == EMPTY_CODE_HASH {
+                    return Ok(&EMPTY_CODE);
+                }
+
                 self.get_code(code_hash)
             }
             None => {
-                let provider = self.provider.as_ref();
-
-                if provider.is_none() {
-                    return Err(ExecutionError::CodeNotFound);
+                // Destroyed accounts of an overlay base have no code
+                if self.destroyed_accounts.contains(&address) {
+                    return Ok(&EMPTY_CODE);
+                }
+                // Overridden accounts of an overlay base
+                if let Some(code_hash) = self.code_overrides.get(&address) {
+                    return self.get_code(*code_hash);
                 }
 
-                // Asynchronously fetch the code from the blockchain
-                let contract_address = Address::from(address);
-
-                let future = provider.unwrap().get_code(contract_address, None);
-
-                // Block on the future and get the result
-                let code_result = tokio::runtime::Runtime::new()
-                    .expect("Could not create a Runtime")
-                    .block_on(future);
+                let provider = match self.provider.as_ref() {
+                    Some(provider) => provider,
+                    None => {
+                        self.record_missing_account(address);
+                        return Err(ExecutionError::CodeNotFound);
+                    }
+                };
 
-                match code_result {
+                match provider.get_code(address) {
                     Ok(code) => {
-                        let code_hash = keccak256(&code.0);
-                        if let Some(account) = self.accounts.get_mut(&address) {
-                            account.code_hash = code_hash;
-                        }
-                        self.codes.insert(code_hash, code.to_vec());
+                        let code_hash = keccak256(&code);
+                        self.codes.insert(code_hash, code);
                         Ok(&self.codes[&code_hash])
                     }
                     Err(_) => Err(ExecutionError::CodeNotFound),
@@ -391,6 +980,8 @@ impl EvmState {
     pub fn put_code_at(&mut self, address: [u8; 20], code: Vec<u8>) -> Result<(), ExecutionError> {
         let code_hash = self.put_code(code)?;
 
+        self.copy_base_account(address);
+
         match self.accounts.get_mut(&address) {
             Some(account_state) => {
                 account_state.code_hash = code_hash.to_owned();
@@ -400,6 +991,146 @@ impl EvmState {
         }
     }
 
+    /// Overrides the code of an account before an execution, like Foundry's `vm.etch`.
+    /// The static mode is bypassed.
+    ///
+    /// The code of a known account is replaced. An unknown account of a forked state stays
+    /// unknown, so that its balance and storage are still fetched from the provider, and only
+    /// its code is overridden. Without provider, the account is initialized with the code.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the account to override.
+    /// * `code` - The new code of the account.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::EvmState;
+    ///
+    /// let mut state = EvmState::new(None);
+    /// state.set_code([0x11; 20], vec![0x60, 0x01]);
+    /// assert_eq!(state.get_code_at([0x11; 20]).unwrap(), &vec![0x60, 0x01]);
+    /// ```
+    pub fn set_code(&mut self, address: [u8; 20], code: Vec<u8>) {
+        let code_hash = keccak256(&code);
+        self.codes.insert(code_hash, code);
+        self.copy_base_account(address);
+
+        if let Some(account_state) = self.accounts.get_mut(&address) {
+            account_state.code_hash = code_hash;
+        } else if self.provider.is_some() && !self.destroyed_accounts.contains(&address) {
+            self.code_overrides.insert(address, code_hash);
+        } else {
+            self.init_override_account(address).code_hash = code_hash;
+        }
+    }
+
+    /// Overrides a storage slot of an account before an execution, like Foundry's `vm.store`.
+    /// The static mode and the SSTORE path are bypassed.
+    ///
+    /// The slot of a known account is set. An unknown account of a forked state stays unknown,
+    /// so that its other slots are still fetched from the provider, and only this slot is
+    /// overridden. Without provider, the account is initialized with the slot.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the account to override.
+    /// * `slot` - The storage slot to override.
+    /// * `value` - The new value of the slot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::EvmState;
+    ///
+    /// let mut state = EvmState::new(None);
+    /// state.override_storage([0x11; 20], [0x01; 32], [0x2a; 32]);
+    /// assert_eq!(state.sload([0x11; 20], [0x01; 32]).unwrap(), [0x2a; 32]);
+    /// ```
+    pub fn override_storage(&mut self, address: [u8; 20], slot: [u8; 32], value: [u8; 32]) {
+        self.copy_base_account(address);
+        if let Some(account_state) = self.accounts.get_mut(&address) {
+            account_state.storage.insert(slot, value);
+        } else if self.provider.is_some() && !self.destroyed_accounts.contains(&address) {
+            self.storage_overrides.insert((address, slot), value);
+        } else {
+            self.init_override_account(address)
+                .storage
+                .insert(slot, value);
+        }
+    }
+
+    /// Returns the storage of an account known locally, sorted by slot. For a forked account,
+    /// only the overridden slots are returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the account.
+    pub fn export_account_storage(&self, address: [u8; 20]) -> BTreeMap<[u8; 32], [u8; 32]> {
+        if let Some(account_state) = self.accounts.get(&address) {
+            return account_state.storage.clone().into_iter().collect();
+        }
+
+        let mut storage = self
+            .base
+            .as_ref()
+            .map(|base| base.export_account_storage(address))
+            .unwrap_or_default();
+        storage.extend(
+            self.storage_overrides
+                .iter()
+                .filter(|((account, _), _)| *account == address)
+                .map(|((_, slot), value)| (*slot, *value)),
+        );
+        storage
+    }
+
+    /// Writes the given slots to the storage of an account, e.g. to restore the storage
+    /// exported by `export_account_storage`. The other slots are left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the account.
+    /// * `storage` - The slots to write with their values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use evm_rs_emulator::EvmState;
+    ///
+    /// let mut state = EvmState::new(None);
+    /// state.override_storage([0x11; 20], [0x01; 32], [0x2a; 32]);
+    ///
+    /// let storage = state.export_account_storage([0x11; 20]);
+    /// let mut other = EvmState::new(None);
+    /// other.import_account_storage([0x11; 20], storage);
+    /// assert_eq!(other.sload([0x11; 20], [0x01; 32]).unwrap(), [0x2a; 32]);
+    /// ```
+    pub fn import_account_storage(
+        &mut self,
+        address: [u8; 20],
+        storage: BTreeMap<[u8; 32], [u8; 32]>,
+    ) {
+        for (slot, value) in storage {
+            self.override_storage(address, slot, value);
+        }
+    }
+
+    /// Creates an empty account for an override and invokes the `on_account_created` hook.
+    fn init_override_account(&mut self, address: [u8; 20]) -> &mut AccountState {
+        if let Some(hook) = &self.on_account_created {
+            hook(address);
+        }
+
+        self.accounts.entry(address).or_insert(AccountState {
+            nonce: 0,
+            balance: [0u8; 32],
+            storage: HashMap::new(),
+            code_hash: [0u8; 32],
+        })
+    }
+
     /// Returns a reference to the code associated with the given code hash.
     ///
     /// # Arguments
@@ -431,6 +1162,245 @@ impl EvmState {
         Ok(code_hash)
     }
 
+    /// Saves the accounts and codes of the state, including what was fetched from the fork
+    /// provider, to a binary cache file that `load_cache` can read back in a later run.
+    ///
+    /// The file starts with `EVMC` and a version byte, followed by the accounts sorted by
+    /// address (with a byte flagging the forked accounts, and their storage sorted by slot) and
+    /// the codes sorted by hash. Integers are
+    /// big-endian and every list is prefixed with its length.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the cache file, overwritten if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the file can't be written.
+    pub fn save_cache<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(CACHE_MAGIC);
+        bytes.push(CACHE_VERSION);
+
+        let mut accounts: Vec<_> = self.accounts.iter().collect();
+        accounts.sort_by_key(|(address, _)| **address);
+        bytes.extend_from_slice(&(accounts.len() as u32).to_be_bytes());
+        for (address, account) in accounts {
+            bytes.extend_from_slice(address);
+            bytes.extend_from_slice(&account.nonce.to_be_bytes());
+            bytes.extend_from_slice(&account.balance);
+            bytes.extend_from_slice(&account.code_hash);
+            bytes.push(self.forked_accounts.contains(address) as u8);
+
+            let mut storage: Vec<_> = account.storage.iter().collect();
+            storage.sort();
+            bytes.extend_from_slice(&(storage.len() as u32).to_be_bytes());
+            for (slot, value) in storage {
+                bytes.extend_from_slice(slot);
+                bytes.extend_from_slice(value);
+            }
+        }
+
+        let mut codes: Vec<_> = self.codes.iter().collect();
+        codes.sort_by_key(|(code_hash, _)| **code_hash);
+        bytes.extend_from_slice(&(codes.len() as u32).to_be_bytes());
+        for (code_hash, code) in codes {
+            bytes.extend_from_slice(code_hash);
+            bytes.extend_from_slice(&(code.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(code);
+        }
+
+        fs::File::create(path)?.write_all(&bytes)
+    }
+
+    /// Loads a cache file written by `save_cache` into the state. The cached accounts and codes
+    /// are served without querying the fork provider, and replace the ones already known.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the cache file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the file can't be read, or with the `InvalidData` kind if it
+    /// is not a state cache file. The state is left untouched on error.
+    pub fn load_cache<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let mut reader = io::BufReader::new(fs::File::open(path)?);
+
+        let header: [u8; 5] = read_array(&mut reader)?;
+        if &header[..4] != CACHE_MAGIC || header[4] != CACHE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a state cache file",
+            ));
+        }
+
+        let mut accounts = Vec::new();
+        for _ in 0..u32::from_be_bytes(read_array(&mut reader)?) {
+            let address: [u8; 20] = read_array(&mut reader)?;
+            let nonce = u64::from_be_bytes(read_array(&mut reader)?);
+            let balance = read_array(&mut reader)?;
+            let code_hash = read_array(&mut reader)?;
+            let [forked] = read_array(&mut reader)?;
+
+            let mut storage = HashMap::new();
+            for _ in 0..u32::from_be_bytes(read_array(&mut reader)?) {
+                storage.insert(read_array(&mut reader)?, read_array(&mut reader)?);
+            }
+
+            accounts.push((
+                address,
+                AccountState {
+                    nonce,
+                    balance,
+                    storage,
+                    code_hash,
+                },
+                forked == 1,
+            ));
+        }
+
+        let mut codes = Vec::new();
+        for _ in 0..u32::from_be_bytes(read_array(&mut reader)?) {
+            let code_hash: [u8; 32] = read_array(&mut reader)?;
+            let mut code = vec![0u8; u32::from_be_bytes(read_array(&mut reader)?) as usize];
+            reader.read_exact(&mut code)?;
+            codes.push((code_hash, code));
+        }
+
+        for (address, account, forked) in accounts {
+            if forked {
+                self.forked_accounts.insert(address);
+            } else {
+                self.forked_accounts.remove(&address);
+            }
+            self.accounts.insert(address, account);
+        }
+        self.codes.extend(codes);
+
+        Ok(())
+    }
+
+    /// Compares the accounts of the state with the expected ones.
+    /// Balances, nonces, codes and storage slots are compared for every account of both states,
+    /// a storage slot missing on one side being considered as zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `expected` - The expected state.
+    ///
+    /// # Returns
+    ///
+    /// Returns one readable line per difference, or an empty vector if the states match.
+    pub fn diff(&self, expected: &EvmState) -> Vec<String> {
+        let mut differences = Vec::new();
+
+        // Sort the addresses to get a stable output
+        let mut addresses: Vec<[u8; 20]> = self
+            .accounts
+            .keys()
+            .chain(expected.accounts.keys())
+            .copied()
+            .collect();
+        addresses.sort();
+        addresses.dedup();
+
+        for address in addresses {
+            let hex_address = utils::debug::to_hex_address(address);
+
+            let (actual_account, expected_account) =
+                match (self.accounts.get(&address), expected.accounts.get(&address)) {
+                    (Some(actual), Some(expected)) => (actual, expected),
+                    (Some(_), None) => {
+                        differences.push(format!("{}: unexpected account", hex_address));
+                        continue;
+                    }
+                    _ => {
+                        differences.push(format!("{}: missing account", hex_address));
+                        continue;
+                    }
+                };
+
+            if actual_account.nonce != expected_account.nonce {
+                differences.push(format!(
+                    "{}: nonce is {} but expected {}",
+                    hex_address, actual_account.nonce, expected_account.nonce
+                ));
+            }
+
+            if actual_account.balance != expected_account.balance {
+                differences.push(format!(
+                    "{}: balance is {} but expected {}",
+                    hex_address,
+                    U256::from_big_endian(&actual_account.balance),
+                    U256::from_big_endian(&expected_account.balance)
+                ));
+            }
+
+            let actual_code = self.code_or_empty(actual_account.code_hash);
+            let expected_code = expected.code_or_empty(expected_account.code_hash);
+            if actual_code != expected_code {
+                differences.push(format!(
+                    "{}: code is 0x{} but expected 0x{}",
+                    hex_address,
+                    hex::encode(actual_code),
+                    hex::encode(expected_code)
+                ));
+            }
+
+            let mut slots: Vec<[u8; 32]> = actual_account
+                .storage
+                .keys()
+                .chain(expected_account.storage.keys())
+                .copied()
+                .collect();
+            slots.sort();
+            slots.dedup();
+
+            for slot in slots {
+                let actual_value = actual_account.storage.get(&slot).unwrap_or(&[0u8; 32]);
+                let expected_value = expected_account.storage.get(&slot).unwrap_or(&[0u8; 32]);
+
+                if actual_value != expected_value {
+                    differences.push(format!(
+                        "{}: storage slot 0x{} is 0x{} but expected 0x{}",
+                        hex_address,
+                        hex::encode(slot),
+                        hex::encode(actual_value),
+                        hex::encode(expected_value)
+                    ));
+                }
+            }
+        }
+
+        differences
+    }
+
+    /// Asserts that the state matches the expected one (see `diff`).
+    ///
+    /// # Arguments
+    ///
+    /// * `expected` - The expected state.
+    ///
+    /// # Panics
+    ///
+    /// Panics with the list of differences if the states don't match.
+    pub fn assert_matches(&self, expected: &EvmState) {
+        let differences = self.diff(expected);
+
+        if !differences.is_empty() {
+            panic!("State mismatch:\n  {}", differences.join("\n  "));
+        }
+    }
+
+    /// Returns the code associated with the given code hash, or an empty code if it is unknown.
+    fn code_or_empty(&self, code_hash: [u8; 32]) -> &[u8] {
+        self.codes
+            .get(&code_hash)
+            .map(|code| code.as_slice())
+            .unwrap_or(&[])
+    }
+
     /// Print the state of the EVM
     /// This function is used for debugging purposes.
     pub fn debug_state(&mut self) {
@@ -449,10 +1419,9 @@ impl EvmState {
         );
         println!("{}", footer_line.clone().red());
 
-        // ... other code ...
-
-        // Create a vector of all addresses
-        let addresses: Vec<_> = self.accounts.keys().cloned().collect();
+        // Create a sorted vector of all addresses
+        let mut addresses: Vec<_> = self.accounts.keys().cloned().collect();
+        addresses.sort();
 
         // Iterate over the vector of addresses
         for address in addresses {
@@ -474,3 +1443,585 @@ impl EvmState {
         }
     }
 }
+
+/* -------------------------------------------------------------------------- */
+/*                            Base state provider                             */
+/* -------------------------------------------------------------------------- */
+
+/// Serves the reads of an overlay state (see `EvmState::overlay`) from its base state, then
+/// from the base provider. Like a node, unknown accounts and slots are empty.
+impl StateProvider for EvmState {
+    fn get_code(&self, address: [u8; 20]) -> Result<Vec<u8>, ExecutionError> {
+        if self.is_destroyed(address) {
+            return Ok(Vec::new());
+        }
+        let code_hash = match self.accounts.get(&address) {
+            Some(account_state) => Some(account_state.code_hash),
+            None => self.code_overrides.get(&address).copied(),
+        };
+
+        match (code_hash, self.provider.as_ref()) {
+            (Some(code_hash), _) => Ok(self.codes.get(&code_hash).cloned().unwrap_or_default()),
+            (None, Some(provider)) => provider.get_code(address),
+            (None, None) => Ok(Vec::new()),
+        }
+    }
+
+    fn get_balance(&self, address: [u8; 20]) -> Result<[u8; 32], ExecutionError> {
+        if self.is_destroyed(address) {
+            return Ok([0u8; 32]);
+        }
+        match (self.accounts.get(&address), self.provider.as_ref()) {
+            (Some(account_state), _) => Ok(account_state.balance),
+            (None, Some(provider)) => provider.get_balance(address),
+            (None, None) => Ok([0u8; 32]),
+        }
+    }
+
+    fn get_nonce(&self, address: [u8; 20]) -> Result<u64, ExecutionError> {
+        if self.is_destroyed(address) {
+            return Ok(0);
+        }
+        match (self.accounts.get(&address), self.provider.as_ref()) {
+            (Some(account_state), _) => Ok(account_state.nonce),
+            (None, Some(provider)) => provider.get_nonce(address),
+            (None, None) => Ok(0),
+        }
+    }
+
+    fn get_storage_at(
+        &self,
+        address: [u8; 20],
+        slot: [u8; 32],
+    ) -> Result<[u8; 32], ExecutionError> {
+        // Local accounts don't fall back to the provider
+        if let Some(account_state) = self.accounts.get(&address) {
+            if let Some(value) = account_state.storage.get(&slot) {
+                return Ok(*value);
+            }
+            if !self.forked_accounts.contains(&address) {
+                return Ok([0u8; 32]);
+            }
+        } else if self.destroyed_accounts.contains(&address) {
+            return Ok([0u8; 32]);
+        } else if let Some(value) = self.storage_overrides.get(&(address, slot)) {
+            return Ok(*value);
+        }
+
+        match self.provider.as_ref() {
+            Some(provider) => provider.get_storage_at(address, slot),
+            None => Ok([0u8; 32]),
+        }
+    }
+
+    fn get_block_hash(&self, number: u64) -> Result<[u8; 32], ExecutionError> {
+        match self.provider.as_ref() {
+            Some(provider) => provider.get_block_hash(number),
+            None => Ok([0u8; 32]),
+        }
+    }
+}
+
+/// Reads a fixed number of bytes from a state cache file.
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_module::provider::MockProvider;
+    use crate::core_module::runner::Runner;
+    use crate::core_module::utils::bytes::{_hex_string_to_bytes, pad_left};
+    use crate::core_module::utils::environment::{get_balance, init_account};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_account_debug_is_sorted() {
+        let account = |slots: &[u8]| AccountState {
+            nonce: 1,
+            balance: [0u8; 32],
+            storage: slots.iter().map(|&slot| ([slot; 32], [slot; 32])).collect(),
+            code_hash: [0u8; 32],
+        };
+
+        // Same storage built in different insertion orders
+        let first = format!("{:?}", account(&[1, 2, 3, 4, 5, 6, 7, 8]));
+        let second = format!("{:?}", account(&[8, 7, 6, 5, 4, 3, 2, 1]));
+
+        assert_eq!(first, second);
+        let slot_1 = utils::debug::to_hex_string([1; 32]);
+        let slot_8 = utils::debug::to_hex_string([8; 32]);
+        assert!(first.find(&slot_1).unwrap() < first.find(&slot_8).unwrap());
+    }
+
+    #[test]
+    fn test_overlay() {
+        let contract = [0x11; 20];
+        let slot = [0x01; 32];
+
+        let mut base = EvmState::new(None);
+        base.set_code(contract, vec![0x60, 0x01]);
+        base.override_storage(contract, slot, [0x22; 32]);
+        let base = Arc::new(base);
+
+        // Reads fall through to the base
+        let mut branch = EvmState::overlay(base.clone());
+        assert_eq!(branch.sload(contract, slot).unwrap(), [0x22; 32]);
+        assert_eq!(branch.get_code_at(contract).unwrap(), &vec![0x60, 0x01]);
+        assert!(branch.accounts.is_empty());
+
+        // Writes go to the overlay only
+        branch.sstore(contract, [0x02; 32], [0x33; 32]).unwrap();
+        assert_eq!(branch.sload(contract, slot).unwrap(), [0x22; 32]);
+        assert_eq!(branch.sload(contract, [0x02; 32]).unwrap(), [0x33; 32]);
+        assert_eq!(branch.get_code_at(contract).unwrap(), &vec![0x60, 0x01]);
+        assert!(!base.accounts[&contract].storage.contains_key(&[0x02; 32]));
+
+        // Other branches still see the untouched base
+        let mut other = EvmState::overlay(base.clone());
+        assert_eq!(other.sload(contract, [0x02; 32]).unwrap(), [0u8; 32]);
+
+        // Branches can be run by a runner
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, Some(other));
+        init_account(contract, &mut runner).unwrap();
+        assert_eq!(runner.state.accounts[&contract].storage[&slot], [0x22; 32]);
+    }
+
+    #[test]
+    fn test_overlay_selfdestruct() {
+        let contract = [0x11; 20];
+        let slot = [0x01; 32];
+
+        // CALLER SELFDESTRUCT, with some balance and storage
+        let mut base = EvmState::new(None);
+        base.set_code(contract, _hex_string_to_bytes("33ff"));
+        base.override_storage(contract, slot, [0x22; 32]);
+        base.accounts.get_mut(&contract).unwrap().balance = pad_left(&[0x05]);
+        let base = Arc::new(base);
+
+        let branch = EvmState::overlay(base.clone());
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, Some(branch));
+        let destroyed = Arc::new(Mutex::new(Vec::new()));
+        let hook_destroyed = destroyed.clone();
+        runner.state.on_account_destroyed = Some(Arc::new(move |address| {
+            hook_destroyed.lock().unwrap().push(address)
+        }));
+
+        // CALL the contract
+        let mut bytecode = _hex_string_to_bytes("6000600060006000600073");
+        bytecode.extend(contract);
+        bytecode.extend(_hex_string_to_bytes("61fffff1"));
+        runner.interpret(bytecode, None, true).unwrap();
+        assert_eq!(*destroyed.lock().unwrap(), vec![contract]);
+
+        // The destroyed account no longer falls through to the base
+        let state = &mut runner.state;
+        assert_eq!(state.sload(contract, slot).unwrap(), [0u8; 32]);
+        assert!(state.get_code_at(contract).unwrap().is_empty());
+        assert!(!state.is_contract(contract));
+        assert_eq!(state.nonce(contract), 0);
+        assert_eq!(get_balance(contract, &mut runner).unwrap(), [0u8; 32]);
+        let beneficiary = runner.address;
+        assert_eq!(
+            get_balance(beneficiary, &mut runner).unwrap(),
+            pad_left(&[0x05])
+        );
+
+        // The base is untouched
+        assert_eq!(base.accounts[&contract].storage[&slot], [0x22; 32]);
+        assert_eq!(base.accounts[&contract].balance, pad_left(&[0x05]));
+
+        // Nor do overlays of the branch
+        let nested = EvmState::overlay(Arc::new(runner.state));
+        assert_eq!(
+            StateProvider::get_code(&nested, contract).unwrap(),
+            Vec::<u8>::new()
+        );
+        assert_eq!(
+            StateProvider::get_storage_at(&nested, contract, slot).unwrap(),
+            [0u8; 32]
+        );
+    }
+
+    #[test]
+    fn test_overlay_write_overridden_forked_account() {
+        let forked = [0x33; 20];
+        let mut provider = MockProvider::default();
+        provider.balances.insert(forked, pad_left(&[0x07]));
+        provider.storage.insert((forked, [0x03; 32]), [0x55; 32]);
+
+        // The account is only overridden in the forked base
+        let mut base = EvmState::new(None);
+        base.provider = Some(Arc::new(provider));
+        base.set_code(forked, vec![0x60, 0x01]);
+        base.override_storage(forked, [0x01; 32], [0x22; 32]);
+        assert!(!base.accounts.contains_key(&forked));
+        let base = Arc::new(base);
+
+        // Writing copies the account with the overrides of the base
+        let mut branch = EvmState::overlay(base.clone());
+        branch.sstore(forked, [0x02; 32], [0x44; 32]).unwrap();
+        assert_eq!(branch.accounts[&forked].balance, pad_left(&[0x07]));
+        assert_eq!(branch.get_code_at(forked).unwrap(), &vec![0x60, 0x01]);
+        assert_eq!(branch.sload(forked, [0x01; 32]).unwrap(), [0x22; 32]);
+        assert_eq!(branch.sload(forked, [0x02; 32]).unwrap(), [0x44; 32]);
+
+        // The other slots still fall back to the base provider
+        assert_eq!(branch.sload(forked, [0x03; 32]).unwrap(), [0x55; 32]);
+        assert!(!base.accounts.contains_key(&forked));
+    }
+
+    #[test]
+    fn test_overlay_write_fetched_forked_account() {
+        let forked = [0x33; 20];
+        let mut provider = MockProvider::default();
+        provider.balances.insert(forked, pad_left(&[0x07]));
+        provider.storage.insert((forked, [0x01; 32]), [0x22; 32]);
+        provider.storage.insert((forked, [0x02; 32]), [0x55; 32]);
+
+        // The base fetched the account and one of its slots from the provider
+        let mut base = EvmState::new(None);
+        base.provider = Some(Arc::new(provider));
+        assert_eq!(base.sload(forked, [0x01; 32]).unwrap(), [0x22; 32]);
+        assert!(base.forked_accounts.contains(&forked));
+        let base = Arc::new(base);
+
+        // Writing copies the account, whose unfetched slots still come from the provider
+        let mut branch = EvmState::overlay(base);
+        branch.sstore(forked, [0x03; 32], [0x44; 32]).unwrap();
+        assert!(branch.forked_accounts.contains(&forked));
+        assert_eq!(branch.sload(forked, [0x01; 32]).unwrap(), [0x22; 32]);
+        assert_eq!(branch.sload(forked, [0x02; 32]).unwrap(), [0x55; 32]);
+        assert_eq!(branch.sload(forked, [0x03; 32]).unwrap(), [0x44; 32]);
+    }
+
+    #[test]
+    fn test_is_contract() {
+        let mut state = EvmState::new(None);
+        let (contract, eoa) = ([0x11; 20], [0x22; 20]);
+
+        state.set_code(contract, vec![0x60, 0x01]);
+        state.set_nonce(eoa, 1);
+
+        assert!(state.is_contract(contract));
+        assert!(!state.is_contract(eoa));
+        assert!(!state.is_contract([0x33; 20]));
+
+        // Code served by the base of an overlay
+        let branch = EvmState::overlay(Arc::new(state));
+        assert!(branch.is_contract(contract));
+        assert!(!branch.is_contract(eoa));
+    }
+
+    #[test]
+    fn test_export_and_import_account_storage() {
+        let mut state = EvmState::new(None);
+        let contract = [0x11; 20];
+        for slot in 0u8..3 {
+            state.override_storage(contract, [slot; 32], [slot + 0x10; 32]);
+        }
+        state.override_storage([0x22; 20], [0x01; 32], [0xff; 32]);
+
+        let storage = state.export_account_storage(contract);
+        let slots: Vec<[u8; 32]> = storage.keys().copied().collect();
+        assert_eq!(slots, vec![[0x00; 32], [0x01; 32], [0x02; 32]]);
+
+        state.accounts.get_mut(&contract).unwrap().storage.clear();
+        assert_eq!(state.sload(contract, [0x02; 32]).unwrap(), [0u8; 32]);
+
+        state.import_account_storage(contract, storage.clone());
+        assert_eq!(state.export_account_storage(contract), storage);
+        assert_eq!(state.sload(contract, [0x02; 32]).unwrap(), [0x12; 32]);
+    }
+
+    #[test]
+    fn test_stream_logs() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        let receiver = runner.state.stream_logs(1);
+
+        // The consumer drains the logs while the execution runs
+        let consumer = std::thread::spawn(move || {
+            receiver
+                .iter()
+                .map(|log| log.topics[0][31])
+                .collect::<Vec<u8>>()
+        });
+
+        // PUSH1 i PUSH0 PUSH0 LOG1, 5 times
+        let bytecode = (1..=5u8)
+            .map(|topic| format!("60{:02x}5f5fa1", topic))
+            .collect::<String>();
+        runner
+            .interpret(_hex_string_to_bytes(&bytecode), None, true)
+            .unwrap();
+        assert_eq!(runner.state.logs.len(), 5);
+
+        // Disconnect the stream
+        runner.state.log_sink = None;
+        assert_eq!(consumer.join().unwrap(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_provider_fallbacks() {
+        let forked = [0x11; 20];
+        let slot = [0x01; 32];
+
+        let mut provider = MockProvider::default();
+        provider.codes.insert(forked, vec![0x60, 0x01]);
+        provider.storage.insert((forked, slot), [0x22; 32]);
+
+        let mut state = EvmState::new(None);
+
+        // Without provider, unknown accounts have no code and an empty storage
+        assert_eq!(
+            state.get_code_at(forked).unwrap_err(),
+            ExecutionError::CodeNotFound
+        );
+        assert_eq!(state.sload(forked, slot).unwrap(), [0u8; 32]);
+
+        state.provider = Some(Arc::new(provider));
+
+        assert_eq!(state.get_code_at(forked).unwrap(), &vec![0x60, 0x01]);
+        assert_eq!(state.sload(forked, slot).unwrap(), [0x22; 32]);
+        assert_eq!(state.sload(forked, [0x02; 32]).unwrap(), [0u8; 32]);
+
+        // The forked account and its slots are saved locally
+        assert!(state.forked_accounts.contains(&forked));
+        assert_eq!(state.accounts[&forked].storage[&slot], [0x22; 32]);
+        assert_eq!(state.accounts[&forked].storage[&[0x02; 32]], [0u8; 32]);
+
+        // Local accounts don't fall back to the provider
+        let mut provider = MockProvider::default();
+        provider.codes.insert([0x33; 20], vec![0x60, 0x01]);
+        provider.storage.insert(([0x33; 20], slot), [0x22; 32]);
+        state.provider = Some(Arc::new(provider));
+        state.accounts.insert(
+            [0x33; 20],
+            AccountState {
+                nonce: 0,
+                balance: [0u8; 32],
+                storage: HashMap::new(),
+                code_hash: [0u8; 32],
+            },
+        );
+        assert_eq!(state.sload([0x33; 20], slot).unwrap(), [0u8; 32]);
+        assert!(state.get_code_at([0x33; 20]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_set_nonce_on_forked_account() {
+        let forked = [0x11; 20];
+        let mut provider = MockProvider::default();
+        provider.balances.insert(forked, pad_left(&[0x07]));
+        provider.nonces.insert(forked, 3);
+        provider.codes.insert(forked, vec![0x60, 0x01]);
+        provider.storage.insert((forked, [0x01; 32]), [0x22; 32]);
+
+        let mut state = EvmState::new(None);
+        state.provider = Some(Arc::new(provider));
+
+        // The account is fetched before its nonce is set
+        state.set_nonce(forked, 9);
+        assert_eq!(state.nonce(forked), 9);
+        assert!(state.forked_accounts.contains(&forked));
+        assert_eq!(state.accounts[&forked].balance, pad_left(&[0x07]));
+        assert_eq!(state.get_code_at(forked).unwrap(), &vec![0x60, 0x01]);
+        assert_eq!(state.sload(forked, [0x01; 32]).unwrap(), [0x22; 32]);
+    }
+
+    #[test]
+    fn test_overrides_on_forked_account() {
+        let forked = [0x11; 20];
+        let slot = [0u8; 32];
+
+        // The forked contract returns 1, its storage holds 0x22
+        let mut provider = MockProvider::default();
+        provider.codes.insert(
+            forked,
+            crate::core_module::asm::assemble(
+                "PUSH1 0x01\nPUSH0\nMSTORE\nPUSH1 0x20\nPUSH0\nRETURN",
+            )
+            .unwrap(),
+        );
+        provider.storage.insert((forked, slot), [0x22; 32]);
+
+        let mut state = EvmState::new(None);
+        state.provider = Some(Arc::new(provider));
+
+        // The patched code returns the forked storage slot 0 instead
+        let patched = crate::core_module::asm::assemble(
+            "PUSH0\nSLOAD\nPUSH0\nMSTORE\nPUSH1 0x20\nPUSH0\nRETURN",
+        )
+        .unwrap();
+        state.set_code(forked, patched.clone());
+        assert!(!state.accounts.contains_key(&forked));
+        assert_eq!(state.get_code_at(forked).unwrap(), &patched);
+
+        // The account read is copied with its override
+        assert!(state.forked_accounts.contains(&forked));
+        assert!(state.code_overrides.is_empty());
+
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, Some(state));
+        runner
+            .call(forked, [0u8; 32], Vec::new(), 0, false)
+            .unwrap();
+        assert_eq!(runner.returndata.heap, vec![0x22; 32]);
+
+        // Overridden slots of a forked account don't hide its other slots
+        runner
+            .state
+            .override_storage(forked, [0x01; 32], [0x33; 32]);
+        assert_eq!(runner.state.sload(forked, [0x01; 32]).unwrap(), [0x33; 32]);
+        assert_eq!(runner.state.sload(forked, slot).unwrap(), [0x22; 32]);
+
+        // Known accounts have their code replaced
+        runner.state.set_code(runner.address, vec![0x00]);
+        assert_eq!(
+            runner.state.get_code_at(runner.address).unwrap(),
+            &vec![0x00]
+        );
+    }
+
+    #[test]
+    fn test_save_and_load_cache() {
+        let forked = [0x11; 20];
+        let slot = [0x01; 32];
+
+        let mut provider = MockProvider::default();
+        provider.codes.insert(forked, vec![0x60, 0x01]);
+        provider.balances.insert(forked, [0x06; 32]);
+        provider.nonces.insert(forked, 7);
+        provider.storage.insert((forked, slot), [0x22; 32]);
+        provider.storage.insert((forked, [0x02; 32]), [0x33; 32]);
+
+        // Seed the cache with a forked code and slot, and a known account
+        let mut state = EvmState::new(None);
+        state.provider = Some(Arc::new(provider));
+        state.get_code_at(forked).unwrap();
+        state.sload(forked, slot).unwrap();
+        state.accounts.insert(
+            [0x22; 20],
+            AccountState {
+                nonce: 3,
+                balance: [0x04; 32],
+                storage: HashMap::from([(slot, [0x05; 32])]),
+                code_hash: keccak256([0x60, 0x01]),
+            },
+        );
+
+        let path = std::env::temp_dir().join(format!("evm_state_cache_{}", std::process::id()));
+        state.save_cache(&path).unwrap();
+
+        // Reload it without provider
+        let mut cached = EvmState::new(None);
+        cached.load_cache(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let account = &cached.accounts[&[0x22; 20]];
+        assert_eq!(account.nonce, 3);
+        assert_eq!(account.balance, [0x04; 32]);
+        assert_eq!(cached.sload([0x22; 20], slot).unwrap(), [0x05; 32]);
+        assert_eq!(cached.get_code_at([0x22; 20]).unwrap(), &vec![0x60, 0x01]);
+        assert!(cached.diff(&state).is_empty());
+
+        // The forked reads are served offline
+        assert_eq!(cached.get_code_at(forked).unwrap(), &vec![0x60, 0x01]);
+        assert_eq!(cached.sload(forked, slot).unwrap(), [0x22; 32]);
+        assert_eq!(cached.accounts[&forked].balance, [0x06; 32]);
+        assert_eq!(cached.accounts[&forked].nonce, 7);
+        assert!(cached.missing_accounts.is_empty());
+        assert!(cached.missing_slots.is_empty());
+
+        // Slots never fetched are reported as missing
+        assert_eq!(cached.sload(forked, [0x02; 32]).unwrap(), [0u8; 32]);
+        assert_eq!(cached.missing_slots, vec![(forked, [0x02; 32])]);
+        assert!(cached.forked_accounts.contains(&forked));
+
+        // Other files are rejected
+        let path = std::env::temp_dir().join(format!("evm_not_cache_{}", std::process::id()));
+        std::fs::write(&path, b"not a cache").unwrap();
+        let error = cached.load_cache(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_get_code_at_empty_code() {
+        let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+        init_account([0xbb; 20], &mut runner).unwrap();
+
+        let code = runner.state.get_code_at([0xbb; 20]).unwrap();
+        assert!(code.is_empty());
+
+        // An empty code stored explicitly is empty too
+        runner.state.put_code_at([0xbb; 20], Vec::new()).unwrap();
+        assert_eq!(
+            runner.state.accounts[&[0xbb; 20]].code_hash,
+            EMPTY_CODE_HASH
+        );
+        let code = runner.state.get_code_at([0xbb; 20]).unwrap();
+        assert!(code.is_empty());
+    }
+
+    #[test]
+    fn test_get_code_at_unknown_address() {
+        let mut state = EvmState::new(None);
+
+        let result = state.get_code_at([0xcc; 20]);
+        assert_eq!(result.unwrap_err(), ExecutionError::CodeNotFound);
+    }
+
+    fn account(nonce: u64, storage: Vec<([u8; 32], [u8; 32])>) -> AccountState {
+        AccountState {
+            nonce,
+            balance: [0u8; 32],
+            storage: storage.into_iter().collect(),
+            code_hash: [0u8; 32],
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut slot = [0u8; 32];
+        slot[31] = 0x2a;
+
+        let mut state = EvmState::new(None);
+        state
+            .accounts
+            .insert([0xaa; 20], account(1, vec![(slot, [0x01; 32])]));
+
+        let mut expected = EvmState::new(None);
+        expected
+            .accounts
+            .insert([0xaa; 20], account(1, vec![(slot, [0x01; 32])]));
+        assert!(state.diff(&expected).is_empty());
+        state.assert_matches(&expected);
+
+        // Diverge on the slot value and on an account
+        expected
+            .accounts
+            .insert([0xaa; 20], account(1, vec![(slot, [0x02; 32])]));
+        expected.accounts.insert([0xbb; 20], account(0, vec![]));
+
+        let differences = state.diff(&expected);
+        assert_eq!(differences.len(), 2);
+        assert!(differences[0].contains(&format!("storage slot 0x{}", hex::encode(slot))));
+        assert!(differences[0].contains(&hex::encode([0x02; 32])));
+        assert!(differences[1].contains("missing account"));
+    }
+
+    #[test]
+    #[should_panic(expected = "nonce is 1 but expected 2")]
+    fn test_assert_matches_mismatch() {
+        let mut state = EvmState::new(None);
+        state.accounts.insert([0xaa; 20], account(1, vec![]));
+
+        let mut expected = EvmState::new(None);
+        expected.accounts.insert([0xaa; 20], account(2, vec![]));
+
+        state.assert_matches(&expected);
+    }
+}
@@ -0,0 +1,621 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use ethers::signers::{LocalWallet, Signer};
+use serde_json::Value;
+
+use super::env::{BlockEnv, Hardfork};
+use super::runner::Runner;
+use super::state::EvmState;
+use super::utils::bytes::pad_left;
+#[cfg(feature = "alloy")]
+use super::utils::primitives::U256Compat;
+use super::utils::primitives::U256;
+
+/* -------------------------------------------------------------------------- */
+/*                             StateTestError enum                            */
+/* -------------------------------------------------------------------------- */
+
+/// An error found while loading a state test, with the name of the test.
+#[derive(Debug, PartialEq)]
+pub enum StateTestError {
+    InvalidJson(String),
+    MissingField { test: String, field: String },
+    InvalidValue { test: String, value: String },
+    UnsupportedCode { test: String, code: String },
+}
+
+impl fmt::Display for StateTestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateTestError::InvalidJson(error) => write!(f, "Invalid JSON: {}", error),
+            StateTestError::MissingField { test, field } => {
+                write!(f, "{}: missing field {}", test, field)
+            }
+            StateTestError::InvalidValue { test, value } => {
+                write!(f, "{}: invalid value {}", test, value)
+            }
+            StateTestError::UnsupportedCode { test, code } => {
+                write!(f, "{}: unsupported code {}", test, code)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StateTestError {}
+
+/* -------------------------------------------------------------------------- */
+/*                              StateTest struct                              */
+/* -------------------------------------------------------------------------- */
+
+/// The expected state of an account after a state test. Only the given fields are checked.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpectedAccount {
+    pub balance: Option<[u8; 32]>,
+    pub nonce: Option<u64>,
+    pub code: Option<Vec<u8>>,
+    pub storage: BTreeMap<[u8; 32], [u8; 32]>,
+}
+
+/// An expected post-state, for the transactions whose indexes match and on the given networks.
+/// A `None` index matches every data, gas limit or value of the transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expectation {
+    pub data_indexes: Option<Vec<usize>>,
+    pub gas_indexes: Option<Vec<usize>>,
+    pub value_indexes: Option<Vec<usize>>,
+    pub networks: Vec<String>,
+    pub result: BTreeMap<[u8; 20], ExpectedAccount>,
+}
+
+/// A test of the `ethereum/tests` GeneralStateTests, read from its filler.
+///
+/// The filled tests only give the state root of the post-state, which the emulator can't
+/// compute, so the fillers and their expected accounts are used instead. The fillers must
+/// use raw bytecode (`:raw 0x..` or `0x..`), LLL and Yul sources are not compiled.
+///
+/// The transaction is run as a plain call from the sender: the intrinsic gas, the fees and
+/// the value transfer of the transaction are not applied. Expectations on the balance or
+/// nonce of the sender, or on the balance of the recipient, don't hold.
+#[derive(Debug, Clone)]
+pub struct StateTest {
+    pub name: String,
+    pub pre: EvmState,
+    pub block: BlockEnv,
+    pub sender: [u8; 20],
+    pub to: [u8; 20],
+    pub data: Vec<Vec<u8>>,
+    pub gas_limit: Vec<u64>,
+    pub value: Vec<[u8; 32]>,
+    pub expect: Vec<Expectation>,
+}
+
+/// Loads the state tests of a GeneralStateTests filler.
+///
+/// # Arguments
+///
+/// * `json` - The content of the filler, mapping each test name to its test.
+///
+/// # Errors
+///
+/// Returns a `StateTestError` if the JSON is invalid, or if a field is missing, invalid or
+/// uses a code source that is not raw bytecode.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::state_test::load_state_tests;
+///
+/// let json = r#"{
+///     "add": {
+///         "env": { "currentNumber": "1", "currentTimestamp": "1000" },
+///         "pre": {
+///             "0x0000000000000000000000000000000000001000": {
+///                 "balance": "0", "nonce": "0", "storage": {},
+///                 "code": ":raw 0x6001600201600055"
+///             }
+///         },
+///         "transaction": {
+///             "data": ["0x"], "gasLimit": ["100000"], "value": ["0"],
+///             "to": "0x0000000000000000000000000000000000001000",
+///             "sender": "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b"
+///         },
+///         "expect": [{
+///             "network": [">=Cancun"],
+///             "result": {
+///                 "0x0000000000000000000000000000000000001000": { "storage": { "0x00": "0x03" } }
+///             }
+///         }]
+///     }
+/// }"#;
+///
+/// let tests = load_state_tests(json).unwrap();
+/// assert_eq!(tests.len(), 1);
+/// assert!(tests[0].run().is_empty());
+/// ```
+pub fn load_state_tests(json: &str) -> Result<Vec<StateTest>, StateTestError> {
+    let fillers: Value = serde_json::from_str(json)
+        .map_err(|error| StateTestError::InvalidJson(error.to_string()))?;
+    let fillers = fillers
+        .as_object()
+        .ok_or_else(|| StateTestError::InvalidJson("expected an object of tests".to_string()))?;
+
+    fillers
+        .iter()
+        .map(|(name, filler)| StateTest::from_filler(name, filler))
+        .collect()
+}
+
+/// Implementation of the StateTest struct.
+impl StateTest {
+    /// Reads a state test from its filler.
+    fn from_filler(name: &str, filler: &Value) -> Result<Self, StateTestError> {
+        let parser = Parser { test: name };
+
+        let env = parser.field(filler, "env")?;
+        let mut block = BlockEnv::default();
+        if let Some(number) = env.get("currentNumber") {
+            block.number = parser.u256(number)?;
+        }
+        if let Some(timestamp) = env.get("currentTimestamp") {
+            block.timestamp = Some(parser.u256(timestamp)?);
+        }
+        if let Some(coinbase) = env.get("currentCoinbase") {
+            block.coinbase = parser.address(coinbase)?;
+        }
+        if let Some(basefee) = env.get("currentBaseFee") {
+            block.basefee = parser.u256(basefee)?.low_u64();
+        }
+
+        let mut pre = EvmState::new(None);
+        for (address, account) in parser.object(parser.field(filler, "pre")?)? {
+            let address = parser.address(&Value::String(address.clone()))?;
+
+            pre.set_nonce(
+                address,
+                parser.u256(parser.field(account, "nonce")?)?.low_u64(),
+            );
+            pre.set_code(address, parser.code(parser.field(account, "code")?)?);
+            if let Some(account_state) = pre.accounts.get_mut(&address) {
+                account_state.balance = parser.word(parser.field(account, "balance")?)?;
+            }
+            for (slot, value) in parser.storage(account.get("storage"))? {
+                pre.override_storage(address, slot, value);
+            }
+        }
+
+        let transaction = parser.field(filler, "transaction")?;
+        let sender = match transaction.get("secretKey") {
+            Some(secret_key) => parser.sender(secret_key)?,
+            None => parser.address(parser.field(transaction, "sender")?)?,
+        };
+
+        let mut expect = Vec::new();
+        for expectation in parser.array(parser.field(filler, "expect")?)? {
+            let indexes = expectation.get("indexes");
+            let mut result = BTreeMap::new();
+            for (address, account) in parser.object(parser.field(expectation, "result")?)? {
+                let expected_account = ExpectedAccount {
+                    balance: account.get("balance").map(|b| parser.word(b)).transpose()?,
+                    nonce: account
+                        .get("nonce")
+                        .map(|n| parser.u256(n).map(|n| n.low_u64()))
+                        .transpose()?,
+                    code: account.get("code").map(|c| parser.code(c)).transpose()?,
+                    storage: parser.storage(account.get("storage"))?,
+                };
+                result.insert(
+                    parser.address(&Value::String(address.clone()))?,
+                    expected_account,
+                );
+            }
+
+            expect.push(Expectation {
+                data_indexes: parser.indexes(indexes.and_then(|i| i.get("data")))?,
+                gas_indexes: parser.indexes(indexes.and_then(|i| i.get("gas")))?,
+                value_indexes: parser.indexes(indexes.and_then(|i| i.get("value")))?,
+                networks: match parser.field(expectation, "network")? {
+                    Value::String(network) => vec![network.clone()],
+                    networks => parser
+                        .array(networks)?
+                        .iter()
+                        .map(|network| parser.string(network).map(str::to_string))
+                        .collect::<Result<_, _>>()?,
+                },
+                result,
+            });
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            pre,
+            block,
+            sender,
+            to: parser.address(parser.field(transaction, "to")?)?,
+            data: parser
+                .array(parser.field(transaction, "data")?)?
+                .iter()
+                .map(|data| parser.code(data))
+                .collect::<Result<_, _>>()?,
+            gas_limit: parser
+                .array(parser.field(transaction, "gasLimit")?)?
+                .iter()
+                .map(|gas| parser.u256(gas).map(|gas| gas.low_u64()))
+                .collect::<Result<_, _>>()?,
+            value: parser
+                .array(parser.field(transaction, "value")?)?
+                .iter()
+                .map(|value| parser.word(value))
+                .collect::<Result<_, _>>()?,
+            expect,
+        })
+    }
+
+    /// Runs the transaction of every expectation that applies to the given hardfork, and
+    /// compares the accounts with the expected ones.
+    ///
+    /// The transaction is run as a call from the sender, with the gas limit of the transaction.
+    /// The intrinsic gas, the fees and the value transfer of the transaction are not modelled.
+    ///
+    /// # Arguments
+    ///
+    /// * `hardfork` - The hardfork to run the transactions on.
+    ///
+    /// # Returns
+    ///
+    /// Returns one readable line per difference, or an empty vector if the test passes.
+    pub fn run_on(&self, hardfork: Hardfork) -> Vec<String> {
+        let mut differences = Vec::new();
+
+        for expectation in self
+            .expect
+            .iter()
+            .filter(|e| e.networks.iter().any(|n| network_includes(n, hardfork)))
+        {
+            for data in matching_indexes(&expectation.data_indexes, self.data.len()) {
+                for gas in matching_indexes(&expectation.gas_indexes, self.gas_limit.len()) {
+                    for value in matching_indexes(&expectation.value_indexes, self.value.len()) {
+                        let mut runner = self.execute(hardfork, data, gas, value);
+
+                        for difference in compare_accounts(&mut runner.state, &expectation.result) {
+                            differences.push(format!(
+                                "{} (data {}, gas {}, value {}): {}",
+                                self.name, data, gas, value, difference
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        differences
+    }
+
+    /// Runs the state test on the default hardfork.
+    ///
+    /// # Returns
+    ///
+    /// Returns one readable line per difference, or an empty vector if the test passes.
+    pub fn run(&self) -> Vec<String> {
+        self.run_on(Hardfork::default())
+    }
+
+    /// Runs the transaction with the given data, gas limit and value on the pre-state.
+    fn execute(&self, hardfork: Hardfork, data: usize, gas: usize, value: usize) -> Runner {
+        let mut runner = Runner::new(self.sender, None, Some(self.to), None, None, None);
+        runner.print_errors = false;
+
+        // Drop the accounts initialized by the runner, e.g. the funded sender
+        runner.state = self.pre.clone();
+        runner.hardfork = hardfork;
+        runner.block = self.block.clone();
+        runner.gas_limit = self.gas_limit[gas];
+        runner.gas = self.gas_limit[gas];
+
+        // A reverted or halted transaction is an expected outcome
+        let _ = runner.call(
+            self.to,
+            self.value[value],
+            self.data[data].clone(),
+            self.gas_limit[gas],
+            false,
+        );
+
+        runner
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                  Functions                                 */
+/* -------------------------------------------------------------------------- */
+
+/// Returns `true` if a network of an expectation (e.g. `Cancun`, `>=Berlin` or `<London`)
+/// includes the hardfork.
+fn network_includes(network: &str, hardfork: Hardfork) -> bool {
+    let (name, includes): (&str, fn(Hardfork, Hardfork) -> bool) =
+        if let Some(name) = network.strip_prefix(">=") {
+            (name, |hardfork, fork| hardfork >= fork)
+        } else if let Some(name) = network.strip_prefix('<') {
+            (name, |hardfork, fork| hardfork < fork)
+        } else {
+            (network, |hardfork, fork| hardfork == fork)
+        };
+
+    // The state tests name Tangerine Whistle and Spurious Dragon after their main EIP
+    let name = match name.trim() {
+        "EIP150" => "TangerineWhistle",
+        "EIP158" => "SpuriousDragon",
+        name => name,
+    };
+    HARDFORKS
+        .iter()
+        .find(|fork| format!("{:?}", fork) == name)
+        .is_some_and(|fork| includes(hardfork, *fork))
+}
+
+/// The hardforks a network name can refer to.
+const HARDFORKS: [Hardfork; 13] = [
+    Hardfork::Frontier,
+    Hardfork::Homestead,
+    Hardfork::TangerineWhistle,
+    Hardfork::SpuriousDragon,
+    Hardfork::Byzantium,
+    Hardfork::Constantinople,
+    Hardfork::Petersburg,
+    Hardfork::Istanbul,
+    Hardfork::Berlin,
+    Hardfork::London,
+    Hardfork::Paris,
+    Hardfork::Shanghai,
+    Hardfork::Cancun,
+];
+
+/// Returns the transaction indexes matched by the indexes of an expectation.
+fn matching_indexes(indexes: &Option<Vec<usize>>, count: usize) -> Vec<usize> {
+    match indexes {
+        Some(indexes) => indexes.iter().copied().filter(|i| *i < count).collect(),
+        None => (0..count).collect(),
+    }
+}
+
+/// Compares the given fields of the expected accounts with the state.
+fn compare_accounts(
+    state: &mut EvmState,
+    expected: &BTreeMap<[u8; 20], ExpectedAccount>,
+) -> Vec<String> {
+    let mut differences = Vec::new();
+
+    for (address, expected_account) in expected {
+        let hex_address = format!("0x{}", hex::encode(address));
+
+        if let Some(balance) = expected_account.balance {
+            let actual = state
+                .accounts
+                .get(address)
+                .map_or([0u8; 32], |account| account.balance);
+            if actual != balance {
+                differences.push(format!(
+                    "{}: balance is {} but expected {}",
+                    hex_address,
+                    U256::from_big_endian(&actual),
+                    U256::from_big_endian(&balance)
+                ));
+            }
+        }
+
+        if let Some(nonce) = expected_account.nonce {
+            let actual = state.nonce(*address);
+            if actual != nonce {
+                differences.push(format!(
+                    "{}: nonce is {} but expected {}",
+                    hex_address, actual, nonce
+                ));
+            }
+        }
+
+        if let Some(code) = &expected_account.code {
+            let actual = state.get_code_at(*address).cloned().unwrap_or_default();
+            if &actual != code {
+                differences.push(format!(
+                    "{}: code is 0x{} but expected 0x{}",
+                    hex_address,
+                    hex::encode(actual),
+                    hex::encode(code)
+                ));
+            }
+        }
+
+        for (slot, value) in &expected_account.storage {
+            let actual = state.sload(*address, *slot).unwrap_or([0u8; 32]);
+            if actual != *value {
+                differences.push(format!(
+                    "{}: slot {} is {} but expected {}",
+                    hex_address,
+                    U256::from_big_endian(slot),
+                    U256::from_big_endian(&actual),
+                    U256::from_big_endian(value)
+                ));
+            }
+        }
+    }
+
+    differences
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                Parser struct                               */
+/* -------------------------------------------------------------------------- */
+
+/// Reads the values of a filler, naming the test in its errors.
+struct Parser<'a> {
+    test: &'a str,
+}
+
+impl Parser<'_> {
+    fn invalid(&self, value: &Value) -> StateTestError {
+        StateTestError::InvalidValue {
+            test: self.test.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn field<'v>(&self, value: &'v Value, field: &str) -> Result<&'v Value, StateTestError> {
+        value
+            .get(field)
+            .ok_or_else(|| StateTestError::MissingField {
+                test: self.test.to_string(),
+                field: field.to_string(),
+            })
+    }
+
+    fn object<'v>(
+        &self,
+        value: &'v Value,
+    ) -> Result<&'v serde_json::Map<String, Value>, StateTestError> {
+        value.as_object().ok_or_else(|| self.invalid(value))
+    }
+
+    fn array<'v>(&self, value: &'v Value) -> Result<&'v Vec<Value>, StateTestError> {
+        value.as_array().ok_or_else(|| self.invalid(value))
+    }
+
+    fn string<'v>(&self, value: &'v Value) -> Result<&'v str, StateTestError> {
+        value.as_str().ok_or_else(|| self.invalid(value))
+    }
+
+    /// Reads a hex (`0x` prefixed) or decimal number, given as a string or a JSON number.
+    fn u256(&self, value: &Value) -> Result<U256, StateTestError> {
+        if let Some(number) = value.as_u64() {
+            return Ok(U256::from(number));
+        }
+
+        let number = self.string(value)?.trim();
+        match number.strip_prefix("0x") {
+            Some("") => Ok(U256::zero()),
+            Some(hex) => U256::from_str_radix(hex, 16).map_err(|_| self.invalid(value)),
+            None => U256::from_dec_str(number).map_err(|_| self.invalid(value)),
+        }
+    }
+
+    fn word(&self, value: &Value) -> Result<[u8; 32], StateTestError> {
+        let mut word = [0u8; 32];
+        self.u256(value)?.to_big_endian(&mut word);
+        Ok(word)
+    }
+
+    fn bytes(&self, value: &Value) -> Result<Vec<u8>, StateTestError> {
+        let hex = self.string(value)?.trim();
+        hex::decode(hex.strip_prefix("0x").unwrap_or(hex)).map_err(|_| self.invalid(value))
+    }
+
+    fn address(&self, value: &Value) -> Result<[u8; 20], StateTestError> {
+        let bytes = self.bytes(value)?;
+        if bytes.len() != 20 {
+            return Err(self.invalid(value));
+        }
+        Ok(pad_left(&bytes)[12..].try_into().unwrap())
+    }
+
+    /// Reads a raw bytecode, `:raw 0x..` or `0x..`, or an empty code.
+    fn code(&self, value: &Value) -> Result<Vec<u8>, StateTestError> {
+        let code = self.string(value)?.trim();
+        let raw = code.strip_prefix(":raw").map_or(code, str::trim);
+
+        if raw.is_empty() || raw.starts_with("0x") {
+            self.bytes(&Value::String(raw.to_string()))
+        } else {
+            Err(StateTestError::UnsupportedCode {
+                test: self.test.to_string(),
+                code: code.to_string(),
+            })
+        }
+    }
+
+    fn storage(
+        &self,
+        value: Option<&Value>,
+    ) -> Result<BTreeMap<[u8; 32], [u8; 32]>, StateTestError> {
+        match value {
+            Some(storage) => self
+                .object(storage)?
+                .iter()
+                .map(|(slot, value)| {
+                    Ok((self.word(&Value::String(slot.clone()))?, self.word(value)?))
+                })
+                .collect(),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+
+    /// Reads the indexes of an expectation, `-1` or a missing index matching every index.
+    fn indexes(&self, value: Option<&Value>) -> Result<Option<Vec<usize>>, StateTestError> {
+        let values = match value {
+            None => return Ok(None),
+            Some(Value::Array(values)) => values.clone(),
+            Some(value) => vec![value.clone()],
+        };
+
+        let mut indexes = Vec::new();
+        for value in values {
+            match value.as_i64() {
+                Some(-1) => return Ok(None),
+                Some(index) if index >= 0 => indexes.push(index as usize),
+                _ => return Err(self.invalid(&value)),
+            }
+        }
+        Ok(Some(indexes))
+    }
+
+    /// Derives the sender address from its secret key.
+    fn sender(&self, secret_key: &Value) -> Result<[u8; 20], StateTestError> {
+        let key = self.string(secret_key)?.trim();
+        let wallet: LocalWallet = key
+            .strip_prefix("0x")
+            .unwrap_or(key)
+            .parse()
+            .map_err(|_| self.invalid(secret_key))?;
+        Ok(wallet.address().0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sender_from_secret_key() {
+        let parser = Parser { test: "sender" };
+        let secret_key = Value::String(
+            "0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8".to_string(),
+        );
+
+        // The sender of the ethereum/tests transactions
+        assert_eq!(
+            parser.sender(&secret_key).unwrap().to_vec(),
+            hex::decode("a94f5374fce5edbc8e2a8697c15331677e6ebf0b").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_network_includes() {
+        assert!(network_includes(">=Cancun", Hardfork::Cancun));
+        assert!(network_includes(">=Berlin", Hardfork::London));
+        assert!(!network_includes("<London", Hardfork::London));
+        assert!(network_includes("Shanghai", Hardfork::Shanghai));
+        assert!(!network_includes("Unknown", Hardfork::Cancun));
+        assert!(network_includes("EIP150", Hardfork::TangerineWhistle));
+        assert!(network_includes(">=EIP158", Hardfork::Byzantium));
+        assert!(!network_includes(">=EIP158", Hardfork::TangerineWhistle));
+    }
+
+    #[test]
+    fn test_unsupported_code() {
+        let parser = Parser { test: "lll" };
+        let code = Value::String("{ [[0]] (ADD 1 2) }".to_string());
+
+        assert!(matches!(
+            parser.code(&code),
+            Err(StateTestError::UnsupportedCode { .. })
+        ));
+    }
+}
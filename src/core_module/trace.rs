@@ -0,0 +1,18 @@
+/* -------------------------------------------------------------------------- */
+/*                              TraceStep struct                              */
+/* -------------------------------------------------------------------------- */
+
+/// One executed opcode of the in-memory execution trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    /// The program counter of the opcode.
+    pub pc: usize,
+    /// The executed opcode.
+    pub opcode: u8,
+    /// The remaining gas before the opcode.
+    pub gas: u64,
+    /// The call depth of the frame running the opcode.
+    pub depth: u32,
+    /// The top of the stack after the opcode, if the opcode didn't switch to another frame.
+    pub stack_top: Option<[u8; 32]>,
+}
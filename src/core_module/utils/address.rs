@@ -0,0 +1,147 @@
+use super::primitives::keccak256;
+
+/// Computes the address of a contract created with CREATE.
+///
+/// # Arguments
+///
+/// * `sender` - The address of the account creating the contract.
+/// * `nonce` - The nonce of the sender at the time of the creation.
+///
+/// # Returns
+///
+/// Returns the last 20 bytes of `keccak256(rlp([sender, nonce]))`.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::address::compute_create_address;
+///
+/// let sender = [0xaa; 20];
+/// assert_ne!(compute_create_address(sender, 0), compute_create_address(sender, 1));
+/// ```
+pub fn compute_create_address(sender: [u8; 20], nonce: u64) -> [u8; 20] {
+    // RLP encode the nonce
+    let nonce_bytes = nonce.to_be_bytes();
+    let start = nonce_bytes.iter().position(|&x| x != 0).unwrap_or(8);
+    let encoded_nonce = match nonce {
+        0 => vec![0x80],
+        1..=0x7f => vec![nonce as u8],
+        _ => {
+            let mut encoded = vec![0x80 + (8 - start) as u8];
+            encoded.extend_from_slice(&nonce_bytes[start..]);
+            encoded
+        }
+    };
+
+    // RLP encode the [sender, nonce] list
+    let mut input = vec![0xc0 + 21 + encoded_nonce.len() as u8, 0x94];
+    input.extend_from_slice(&sender);
+    input.extend_from_slice(&encoded_nonce);
+
+    let hash = keccak256(input);
+    hash[12..].try_into().unwrap()
+}
+
+/// Computes the address of a contract created with CREATE2.
+///
+/// # Arguments
+///
+/// * `sender` - The address of the account creating the contract.
+/// * `salt` - The salt given to CREATE2.
+/// * `init_code_hash` - The keccak256 hash of the init code.
+///
+/// # Returns
+///
+/// Returns the last 20 bytes of `keccak256(0xff ++ sender ++ salt ++ init_code_hash)`.
+pub fn compute_create2_address(
+    sender: [u8; 20],
+    salt: [u8; 32],
+    init_code_hash: [u8; 32],
+) -> [u8; 20] {
+    let mut input = vec![0xff];
+    input.extend_from_slice(&sender);
+    input.extend_from_slice(&salt);
+    input.extend_from_slice(&init_code_hash);
+
+    let hash = keccak256(input);
+    hash[12..].try_into().unwrap()
+}
+
+/// Tells whether an address is one of the precompiled contracts (0x01 to 0x0a).
+///
+/// # Arguments
+///
+/// * `address` - The address to check.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::address::is_precompile;
+///
+/// let mut address = [0u8; 20];
+/// address[19] = 0x01;
+/// assert!(is_precompile(address));
+/// assert!(!is_precompile([0u8; 20]));
+/// ```
+pub fn is_precompile(address: [u8; 20]) -> bool {
+    address[..19] == [0u8; 19] && (0x01..=0x0a).contains(&address[19])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(hex: &str) -> [u8; 20] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_compute_create_address() {
+        let sender = address("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+
+        assert_eq!(
+            compute_create_address(sender, 0),
+            address("cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d")
+        );
+        assert_eq!(
+            compute_create_address(sender, 1),
+            address("343c43a37d37dff08ae8c4a11544c718abb4fcf8")
+        );
+        assert_eq!(
+            compute_create_address(sender, 2),
+            address("f778b86fa74e846c4f0a1fbd1335fe81c00a0c91")
+        );
+        assert_eq!(
+            compute_create_address(sender, 3),
+            address("fffd933a0bc612844eaf0c6fe3e5b8e9b6c1d19c")
+        );
+    }
+
+    #[test]
+    fn test_compute_create2_address() {
+        // EIP-1014 examples
+        assert_eq!(
+            compute_create2_address([0u8; 20], [0u8; 32], keccak256([0x00])),
+            address("4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38")
+        );
+        assert_eq!(
+            compute_create2_address(
+                address("deadbeef00000000000000000000000000000000"),
+                [0u8; 32],
+                keccak256([0x00])
+            ),
+            address("b928f69bb1d91cd65274e3c79d8986362984fda3")
+        );
+
+        let mut salt = [0u8; 32];
+        salt[28..].copy_from_slice(&[0xca, 0xfe, 0xba, 0xbe]);
+        assert_eq!(
+            compute_create2_address(
+                address("00000000000000000000000000000000deadbeef"),
+                salt,
+                keccak256(hex::decode("deadbeef").unwrap())
+            ),
+            address("60f3f640a8508fc6a86d45df051962668e1e8ac7")
+        );
+    }
+}
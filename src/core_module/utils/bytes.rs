@@ -1,4 +1,8 @@
-use ethers::types::U256;
+#[cfg(feature = "alloy")]
+use super::primitives::U256Compat;
+use super::primitives::U256;
+
+use super::errors::ExecutionError;
 
 // Colored output
 use colored::*;
@@ -81,6 +85,105 @@ pub fn u64_to_u256_array(n: u64) -> [u8; 32] {
     bytes
 }
 
+/// Convert a [u8; 32] word, as stored on the stack, to a U256
+///
+/// # Arguments
+///
+/// * `word` - The big-endian [u8; 32] to convert
+///
+/// # Returns
+///
+/// Returns the U256 value of the word
+///
+/// # Example
+///
+/// ```
+/// use evm_rs_emulator::bytes::{pad_left, word_to_u256};
+/// use evm_rs_emulator::primitives::U256;
+///
+/// assert_eq!(word_to_u256(&pad_left(&[0x01, 0x00])), U256::from(256));
+/// ```
+pub fn word_to_u256(word: &[u8; 32]) -> U256 {
+    U256::from_big_endian(word)
+}
+
+/// Convert a U256 to a [u8; 32] word, as stored on the stack
+///
+/// # Arguments
+///
+/// * `value` - The U256 to convert
+///
+/// # Returns
+///
+/// Returns the big-endian [u8; 32] of the value
+///
+/// # Example
+///
+/// ```
+/// use evm_rs_emulator::bytes::{pad_left, u256_to_word};
+/// use evm_rs_emulator::primitives::U256;
+///
+/// assert_eq!(u256_to_word(U256::from(256)), pad_left(&[0x01, 0x00]));
+/// ```
+pub fn u256_to_word(value: U256) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    value.to_big_endian(&mut word);
+    word
+}
+
+/// Convert a [u8; 32] to a usize by reading its low bytes directly, without building a U256
+///
+/// # Arguments
+///
+/// * `word` - The [u8; 32] to convert
+///
+/// # Errors
+///
+/// Returns an `ExecutionError::OutOfGas` if the word doesn't fit in a usize. The word is a
+/// memory offset or size, and the memory expansion to such an offset costs more gas than
+/// any gas limit, so an EVM client halts with an out of gas error there. Reporting the same
+/// error keeps the outcome of the execution identical to a client's.
+///
+/// # Returns
+///
+/// Returns a usize with the [u8; 32] converted to a usize
+pub fn word_to_usize(word: &[u8; 32]) -> Result<usize, ExecutionError> {
+    const USIZE_BYTES: usize = std::mem::size_of::<usize>();
+
+    // Check that the high bytes are empty
+    if word[..32 - USIZE_BYTES].iter().any(|&b| b != 0) {
+        return Err(ExecutionError::OutOfGas);
+    }
+
+    let mut bytes = [0u8; USIZE_BYTES];
+    bytes.copy_from_slice(&word[32 - USIZE_BYTES..]);
+    Ok(usize::from_be_bytes(bytes))
+}
+
+/// Convert a U256 read from the stack to a usize, e.g. a memory offset or size
+///
+/// # Arguments
+///
+/// * `value` - The U256 to convert
+///
+/// # Errors
+///
+/// Returns an `ExecutionError::OutOfGas` if the value doesn't fit in a usize, like
+/// `word_to_usize`.
+///
+/// # Example
+///
+/// ```
+/// use evm_rs_emulator::bytes::u256_to_usize;
+/// use evm_rs_emulator::primitives::U256;
+///
+/// assert_eq!(u256_to_usize(U256::from(256)).unwrap(), 256);
+/// assert!(u256_to_usize(U256::MAX).is_err());
+/// ```
+pub fn u256_to_usize(value: U256) -> Result<usize, ExecutionError> {
+    word_to_usize(&u256_to_word(value))
+}
+
 /// Convert a [u8; 32] to a u64
 ///
 /// # Arguments
@@ -127,3 +230,37 @@ pub fn _add(arr: [u8; 32], number: u64) -> [u8; 32] {
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_u256_round_trip() {
+        for value in [
+            U256::zero(),
+            U256::from(0x2a),
+            U256::one() << 128,
+            U256::MAX,
+        ] {
+            assert_eq!(word_to_u256(&u256_to_word(value)), value);
+        }
+
+        let word = [0xab; 32];
+        assert_eq!(u256_to_word(word_to_u256(&word)), word);
+        assert_eq!(u256_to_word(U256::from(7)), u64_to_u256_array(7));
+    }
+
+    #[test]
+    fn test_word_to_usize_overflow() {
+        assert_eq!(word_to_usize(&pad_left(&[0x01, 0x00])).unwrap(), 256);
+
+        // The memory expansion to such an offset could never be paid for
+        let mut word = [0u8; 32];
+        word[32 - std::mem::size_of::<usize>() - 1] = 0x01;
+        assert!(matches!(
+            word_to_usize(&word),
+            Err(ExecutionError::OutOfGas)
+        ));
+    }
+}
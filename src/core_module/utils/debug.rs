@@ -64,6 +64,35 @@ pub fn to_hex_address(bytes: [u8; 20]) -> String {
     )
 }
 
+/// Convert a slice of bytes to a string of hex bytes prefixed with a '0x', without colors.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes to convert
+///
+/// # Returns
+///
+/// Returns a String with the hex bytes prefixed with '0x'
+///
+/// # Example
+///
+/// ```
+/// use evm_rs_emulator::debug::to_prefixed_hex;
+///
+/// assert_eq!(to_prefixed_hex(&[0x12, 0xab]), "0x12ab");
+/// assert_eq!(to_prefixed_hex(&[]), "0x");
+/// ```
+pub fn to_prefixed_hex(bytes: &[u8]) -> String {
+    format!(
+        "0x{}",
+        bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<String>>()
+            .join("")
+    )
+}
+
 /// Convert a Vec<u8> to a string of hex bytes separated by spaces, with revelent bytes in green.
 ///
 /// # Arguments
@@ -13,14 +13,26 @@ use super::{bytes::u64_to_u256_array, errors::ExecutionError};
 ///
 /// Returns a `Result` containing an array of 32 bytes representing the balance of the address,
 /// or an `ExecutionError` if the account is not found.
+///
+/// An account unknown to the local state is copied from the fork provider, if any, on its
+/// first read. Its storage keeps falling back to the provider.
 pub fn get_balance(address: [u8; 20], runner: &mut Runner) -> Result<[u8; 32], ExecutionError> {
-    let balance = runner
-        .state
-        .accounts
-        .get(&address)
-        .map(|account| account.balance)
-        .ok_or(ExecutionError::AccountNotFound);
-    Ok(balance?)
+    runner.state.fetch_account(address);
+
+    if let Some(account) = runner.state.accounts.get(&address) {
+        return Ok(account.balance);
+    }
+    if runner.state.is_destroyed(address) {
+        return Ok([0u8; 32]);
+    }
+
+    match runner.state.provider.as_ref() {
+        Some(provider) => provider.get_balance(address),
+        None => {
+            runner.state.record_missing_account(address);
+            Err(ExecutionError::AccountNotFound)
+        }
+    }
 }
 
 /// Get the nonce of an Ethereum address.
@@ -38,6 +50,8 @@ pub fn get_balance(address: [u8; 20], runner: &mut Runner) -> Result<[u8; 32], E
 ///
 /// Returns an `ExecutionError` if the account associated with the address is not found.
 pub fn get_nonce(address: [u8; 20], runner: &mut Runner) -> Result<[u8; 32], ExecutionError> {
+    runner.state.copy_base_account(address);
+
     let nonce = runner
         .state
         .accounts
@@ -52,6 +66,7 @@ pub fn get_nonce(address: [u8; 20], runner: &mut Runner) -> Result<[u8; 32], Exe
 /// If the account already exists, this function does nothing and returns `Ok(())`.
 /// Otherwise, a new account is created with a nonce of 0, a balance of 0, an empty storage,
 /// and a code hash of all zeros. The account is then inserted into the EVM state and its nonce is incremented.
+/// The `on_account_created` hook of the state is invoked for new accounts.
 ///
 /// # Arguments
 ///
@@ -62,6 +77,9 @@ pub fn get_nonce(address: [u8; 20], runner: &mut Runner) -> Result<[u8; 32], Exe
 ///
 /// Returns an `ExecutionError` if there was an error incrementing the account's nonce.
 pub fn init_account(address: [u8; 20], runner: &mut Runner) -> Result<(), ExecutionError> {
+    // Accounts of an overlaid base state already exist
+    runner.state.copy_base_account(address);
+
     let account = runner.state.accounts.get_mut(&address);
     match account {
         Some(_) => Ok(()),
@@ -75,12 +93,18 @@ pub fn init_account(address: [u8; 20], runner: &mut Runner) -> Result<(), Execut
                     code_hash: [0u8; 32],
                 },
             );
+
+            if let Some(hook) = &runner.state.on_account_created {
+                hook(address);
+            }
+
             increment_nonce(address, runner)
         }
     }
 }
 
 /// Deletes the account with the given address from the state.
+/// The `on_account_destroyed` hook of the state is invoked if the account existed.
 ///
 /// # Arguments
 ///
@@ -91,7 +115,11 @@ pub fn init_account(address: [u8; 20], runner: &mut Runner) -> Result<(), Execut
 ///
 /// Returns `Ok(())` if the account was successfully deleted, otherwise returns an `ExecutionError`.
 pub fn delete_account(address: [u8; 20], runner: &mut Runner) -> Result<(), ExecutionError> {
-    runner.state.accounts.remove(&address);
+    if runner.state.destroy_account(address) {
+        if let Some(hook) = &runner.state.on_account_destroyed {
+            hook(address);
+        }
+    }
     Ok(())
 }
 
@@ -106,6 +134,8 @@ pub fn delete_account(address: [u8; 20], runner: &mut Runner) -> Result<(), Exec
 ///
 /// Returns an `ExecutionError` if the account with the given address is not found in the state.
 pub fn increment_nonce(address: [u8; 20], runner: &mut Runner) -> Result<(), ExecutionError> {
+    runner.state.copy_base_account(address);
+
     let result = runner.state.accounts.get_mut(&address);
     let nonce = match result {
         Some(account) => account,
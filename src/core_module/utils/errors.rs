@@ -1,6 +1,6 @@
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ExecutionError {
     // Memory errors
     OutOfBoundsByteCode,
@@ -10,16 +10,31 @@ pub enum ExecutionError {
     CodeNotFound,
     EmptyByteCode,
     InsufficientBalance,
+    InvalidCodePrefix,
 
     // Flow errors
     StaticCallStateChanged,
     InvalidOpcode(u8),
     InvalidJumpDestination,
+    CallTooDeep,
+    StepLimitReached,
 
     // Stack errors
     StackTooSmall,
     StackTooDeep,
 
+    // Gas errors
+    OutOfGas,
+
+    // Copy errors
+    CopySizeTooLarge,
+
+    // Provider errors
+    ProviderError(String),
+
+    // ABI errors
+    AbiError(String),
+
     // General execution errors
     Revert(Vec<u8>),
     RevertWithoutData,
@@ -37,12 +52,23 @@ impl fmt::Display for ExecutionError {
             ExecutionError::StackTooDeep => {
                 write!(f, "Stack too deep. Maximum stack size is 1024 words")
             }
+            ExecutionError::OutOfGas => write!(f, "Out of gas"),
+            ExecutionError::CopySizeTooLarge => {
+                write!(f, "Copy size exceeds the maximum copy size")
+            }
+            ExecutionError::ProviderError(message) => {
+                write!(f, "State provider request failed: {}", message)
+            }
+            ExecutionError::AbiError(message) => write!(f, "ABI coding failed: {}", message),
             ExecutionError::AccountNotFound => {
                 write!(f, "Trying to access non-existent account state")
             }
             ExecutionError::CodeNotFound => write!(f, "Trying to access non-existent account code"),
             ExecutionError::RevertWithoutData => write!(f, "Execution revert without data"),
             ExecutionError::InsufficientBalance => write!(f, "Insufficient balance to transfer"),
+            ExecutionError::InvalidCodePrefix => {
+                write!(f, "Attempted to deploy a code starting with the 0xEF byte")
+            }
             ExecutionError::InvalidOpcode(op_code) => {
                 write!(f, "Invalid op code 0x{:X}", op_code)
             }
@@ -53,6 +79,10 @@ impl fmt::Display for ExecutionError {
                 write!(f, "Op code 0x{:X} not implemented", op_code)
             }
             ExecutionError::InvalidJumpDestination => write!(f, "Invalid jump destination"),
+            ExecutionError::CallTooDeep => {
+                write!(f, "Call depth limit reached. Maximum call depth is 1024")
+            }
+            ExecutionError::StepLimitReached => write!(f, "Maximum number of steps reached"),
             ExecutionError::Revert(data) => {
                 let hex = super::debug::vec_to_hex_string(data.to_owned());
                 write!(f, "Execution revert with data: {}", hex)
@@ -72,14 +102,21 @@ impl PartialEq for ExecutionError {
             | (CodeNotFound, CodeNotFound)
             | (EmptyByteCode, EmptyByteCode)
             | (InsufficientBalance, InsufficientBalance)
+            | (InvalidCodePrefix, InvalidCodePrefix)
             | (StaticCallStateChanged, StaticCallStateChanged)
             | (StackTooSmall, StackTooSmall)
             | (InvalidJumpDestination, InvalidJumpDestination)
+            | (CallTooDeep, CallTooDeep)
+            | (StepLimitReached, StepLimitReached)
             | (StackTooDeep, StackTooDeep)
+            | (OutOfGas, OutOfGas)
+            | (CopySizeTooLarge, CopySizeTooLarge)
             | (RevertWithoutData, RevertWithoutData) => true,
             (InvalidOpcode(a), InvalidOpcode(b)) => a == b,
             (NotImplemented(a), NotImplemented(b)) => a == b,
             (Revert(a), Revert(b)) => a == b,
+            (ProviderError(a), ProviderError(b)) => a == b,
+            (AbiError(a), AbiError(b)) => a == b,
             _ => false,
         }
     }
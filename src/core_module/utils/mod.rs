@@ -1,4 +1,7 @@
+pub mod address;
 pub mod bytes;
 pub mod debug;
 pub mod environment;
 pub mod errors;
+pub mod primitives;
+pub mod slots;
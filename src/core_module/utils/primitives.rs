@@ -0,0 +1,287 @@
+//! The primitive types and hash function used by the interpreter.
+//!
+//! Every opcode goes through this module instead of importing `ethers` directly, so the
+//! hash function can be switched with a cargo feature:
+//!
+//! * default: `keccak256` from `ethers`.
+//! * `tiny-keccak`: `keccak256` from `tiny-keccak`.
+//!
+//! The word type can be switched too:
+//!
+//! * default: `U256` from `primitive-types`, which `ethers` re-exports, so the words stay
+//!   interchangeable with the `ethers` types.
+//! * `alloy`: `U256` from `alloy-primitives`. The `primitive-types` methods used by the
+//!   emulator are provided by [`U256Compat`].
+//!
+//! Signed words are read as two's complement by [`signed_cmp`], [`signed_div`] and
+//! [`signed_rem`], there is no signed integer type.
+//!
+//! This module doesn't make `ethers` optional: forking, ABI encoding and access lists still
+//! depend on it.
+
+#[cfg(feature = "alloy")]
+pub use alloy_primitives::{U256, U512};
+#[cfg(not(feature = "alloy"))]
+pub use primitive_types::{U256, U512};
+use std::cmp::Ordering;
+
+#[cfg(not(feature = "tiny-keccak"))]
+pub use ethers::utils::keccak256;
+
+/// Computes the keccak256 hash of the given bytes.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes to hash.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::primitives::keccak256;
+///
+/// assert_eq!(
+///     hex::encode(keccak256([])),
+///     "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+/// );
+/// ```
+#[cfg(feature = "tiny-keccak")]
+pub fn keccak256<T: AsRef<[u8]>>(bytes: T) -> [u8; 32] {
+    use tiny_keccak::{Hasher, Keccak};
+
+    let mut output = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(bytes.as_ref());
+    hasher.finalize(&mut output);
+    output
+}
+
+/// The methods of the `primitive-types` words used by the emulator, for the `alloy` words.
+/// They behave like their `primitive-types` counterparts, including their panics.
+#[cfg(feature = "alloy")]
+pub trait U256Compat: Sized {
+    /// Reads a big-endian word of at most 32 bytes.
+    fn from_big_endian(bytes: &[u8]) -> Self;
+    /// Writes the word as 32 big-endian bytes.
+    fn to_big_endian(&self, bytes: &mut [u8]);
+    /// Parses a decimal number.
+    fn from_dec_str(value: &str) -> Result<Self, alloy_primitives::ruint::ParseError>;
+    fn zero() -> Self;
+    fn one() -> Self;
+    /// Converts the word to a `u64`, panicking if it doesn't fit.
+    fn as_u64(&self) -> u64;
+    /// Converts the word to a `usize`, panicking if it doesn't fit.
+    fn as_usize(&self) -> usize;
+    /// Returns the lowest 64 bits of the word.
+    fn low_u64(&self) -> u64;
+    /// Returns the number of bits needed to represent the word.
+    fn bits(&self) -> usize;
+    /// Multiplies two words without overflow.
+    fn full_mul(self, other: Self) -> U512;
+}
+
+#[cfg(feature = "alloy")]
+impl U256Compat for U256 {
+    fn from_big_endian(bytes: &[u8]) -> Self {
+        U256::from_be_slice(bytes)
+    }
+
+    fn to_big_endian(&self, bytes: &mut [u8]) {
+        bytes.copy_from_slice(&self.to_be_bytes::<32>());
+    }
+
+    fn from_dec_str(value: &str) -> Result<Self, alloy_primitives::ruint::ParseError> {
+        U256::from_str_radix(value, 10)
+    }
+
+    fn zero() -> Self {
+        U256::ZERO
+    }
+
+    fn one() -> Self {
+        U256::from(1)
+    }
+
+    fn as_u64(&self) -> u64 {
+        self.to::<u64>()
+    }
+
+    fn as_usize(&self) -> usize {
+        self.to::<usize>()
+    }
+
+    fn low_u64(&self) -> u64 {
+        self.as_limbs()[0]
+    }
+
+    fn bits(&self) -> usize {
+        self.bit_len()
+    }
+
+    fn full_mul(self, other: Self) -> U512 {
+        self.widening_mul(other)
+    }
+}
+
+/// Narrows a 512-bit word to a 256-bit word.
+///
+/// # Arguments
+///
+/// * `value` - The word to narrow.
+///
+/// # Returns
+///
+/// Returns `None` if the word doesn't fit in 256 bits.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::primitives::{narrow_u512, U256, U512};
+///
+/// assert_eq!(narrow_u512(U512::from(U256::MAX)), Some(U256::MAX));
+/// assert_eq!(narrow_u512(U512::MAX), None);
+/// ```
+pub fn narrow_u512(value: U512) -> Option<U256> {
+    #[cfg(feature = "alloy")]
+    return alloy_primitives::ruint::UintTryFrom::uint_try_from(value).ok();
+    #[cfg(not(feature = "alloy"))]
+    return U256::try_from(value).ok();
+}
+
+/// Compares two words read as two's complement signed integers.
+///
+/// # Arguments
+///
+/// * `a` - The left operand.
+/// * `b` - The right operand.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::primitives::{signed_cmp, U256};
+/// use std::cmp::Ordering;
+///
+/// // -1 < 1
+/// assert_eq!(signed_cmp(U256::MAX, U256::from(1)), Ordering::Less);
+/// ```
+pub fn signed_cmp(a: U256, b: U256) -> Ordering {
+    // Flipping the sign bit maps the signed order to the unsigned order
+    let sign = U256::one() << 255usize;
+    (a ^ sign).cmp(&(b ^ sign))
+}
+
+/// Divides two words read as two's complement signed integers, rounding toward zero.
+/// A division by zero returns 0, and `INT_MIN / -1` wraps around to `INT_MIN`.
+///
+/// # Arguments
+///
+/// * `a` - The dividend.
+/// * `b` - The divisor.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::primitives::{signed_div, U256};
+///
+/// let minus_nine = U256::MAX - U256::from(8);
+/// assert_eq!(signed_div(minus_nine, U256::from(3)), U256::MAX - U256::from(2));
+/// ```
+pub fn signed_div(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::zero();
+    }
+
+    let quotient = abs(a) / abs(b);
+    if is_negative(a) != is_negative(b) {
+        negate(quotient)
+    } else {
+        quotient
+    }
+}
+
+/// Computes the remainder of the division of two words read as two's complement signed
+/// integers. The remainder has the sign of the dividend, and a remainder by zero returns 0.
+///
+/// # Arguments
+///
+/// * `a` - The dividend.
+/// * `b` - The divisor.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::primitives::{signed_rem, U256};
+///
+/// let minus_eight = U256::MAX - U256::from(7);
+/// assert_eq!(signed_rem(minus_eight, U256::from(3)), U256::MAX - U256::from(1));
+/// ```
+pub fn signed_rem(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::zero();
+    }
+
+    let remainder = abs(a) % abs(b);
+    if is_negative(a) {
+        negate(remainder)
+    } else {
+        remainder
+    }
+}
+
+fn is_negative(value: U256) -> bool {
+    value.bit(255)
+}
+
+fn negate(value: U256) -> U256 {
+    (!value).overflowing_add(U256::one()).0
+}
+
+fn abs(value: U256) -> U256 {
+    if is_negative(value) {
+        negate(value)
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keccak256() {
+        assert_eq!(
+            hex::encode(keccak256(b"hello")),
+            "1c8aff950685c2ed4bc3174f3472287b56d9517b9c948127319a09a7a36deac8"
+        );
+    }
+
+    #[cfg(feature = "alloy")]
+    #[test]
+    fn test_alloy_word() {
+        let word: alloy_primitives::U256 = U256::from_big_endian(&[0x01, 0x00]);
+        assert_eq!(word, alloy_primitives::U256::from(256));
+        assert_eq!((word.as_u64(), word.low_u64(), word.bits()), (256, 256, 9));
+
+        let mut bytes = [0u8; 32];
+        word.to_big_endian(&mut bytes);
+        assert_eq!(bytes[30..], [0x01, 0x00]);
+
+        let product = U256::MAX.full_mul(U256::MAX);
+        assert_eq!(narrow_u512(product), None);
+        assert_eq!(
+            narrow_u512(product >> 256usize),
+            Some(U256::MAX - U256::one())
+        );
+    }
+
+    #[test]
+    fn test_signed_cmp() {
+        let int_min = U256::one() << 255;
+        let int_max = int_min - U256::one();
+
+        assert_eq!(signed_cmp(int_min, int_max), Ordering::Less);
+        assert_eq!(signed_cmp(U256::MAX, U256::zero()), Ordering::Less);
+        assert_eq!(signed_cmp(U256::one(), U256::MAX), Ordering::Greater);
+        assert_eq!(signed_cmp(int_min, int_min), Ordering::Equal);
+    }
+}
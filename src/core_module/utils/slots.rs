@@ -0,0 +1,96 @@
+#[cfg(feature = "alloy")]
+use super::primitives::U256Compat;
+use super::primitives::{keccak256, U256};
+
+/// Computes the storage slot of a Solidity mapping entry: `keccak256(key . slot)`.
+///
+/// # Arguments
+///
+/// * `key` - The mapping key, ABI encoded to 32 bytes (e.g. a left padded address).
+/// * `slot` - The slot where the mapping is declared.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::bytes::pad_left;
+/// use evm_rs_emulator::slots::mapping_slot;
+///
+/// // The entry of `balances[0xaa..aa]` for a mapping declared at slot 0
+/// let slot = mapping_slot(pad_left(&[0xaa; 20]), [0u8; 32]);
+/// assert_eq!(
+///     hex::encode(slot),
+///     "1bfd945afb434ed902aa821149f45ebda8b1e3eba6797fc235eb1e3fb6674b2a"
+/// );
+/// ```
+pub fn mapping_slot(key: [u8; 32], slot: [u8; 32]) -> [u8; 32] {
+    let mut preimage = [0u8; 64];
+    preimage[..32].copy_from_slice(&key);
+    preimage[32..].copy_from_slice(&slot);
+    keccak256(preimage)
+}
+
+/// Computes the storage slot of an element of a Solidity dynamic array:
+/// `keccak256(base) + index`, the array length being stored at `base`.
+/// Each element is assumed to take a whole slot.
+///
+/// # Arguments
+///
+/// * `base` - The slot where the array is declared.
+/// * `index` - The index of the element.
+pub fn array_element_slot(base: [u8; 32], index: u64) -> [u8; 32] {
+    let first = U256::from_big_endian(&keccak256(base));
+    let (element, _) = first.overflowing_add(U256::from(index));
+
+    let mut slot = [0u8; 32];
+    element.to_big_endian(&mut slot);
+    slot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_module::utils::bytes::{_hex_string_to_bytes, pad_left};
+
+    fn slot(hex: &str) -> [u8; 32] {
+        _hex_string_to_bytes(hex).try_into().unwrap()
+    }
+
+    #[test]
+    fn test_mapping_slot() {
+        // mapping(address => uint256) at slot 0, entry of address(0)
+        assert_eq!(
+            mapping_slot([0u8; 32], [0u8; 32]),
+            slot("ad3228b676f7d3cd4284a5443f17f1962b36e491b30a40b2405849e597ba5fb5")
+        );
+
+        // mapping(address => uint256) at slots 0 and 1, entry of address(0xaa..aa)
+        let holder = pad_left(&[0xaa; 20]);
+        assert_eq!(
+            mapping_slot(holder, [0u8; 32]),
+            slot("1bfd945afb434ed902aa821149f45ebda8b1e3eba6797fc235eb1e3fb6674b2a")
+        );
+        assert_eq!(
+            mapping_slot(holder, pad_left(&[0x01])),
+            slot("f043793b38eda5c51d465f1125f49da5bc0aa0c6d8a90f40e1d84f43afd33c7f")
+        );
+    }
+
+    #[test]
+    fn test_array_element_slot() {
+        // uint256[] at slot 0
+        assert_eq!(
+            array_element_slot([0u8; 32], 0),
+            slot("290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563")
+        );
+        assert_eq!(
+            array_element_slot([0u8; 32], 2),
+            slot("290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e565")
+        );
+
+        // uint256[] at slot 1
+        assert_eq!(
+            array_element_slot(pad_left(&[0x01]), 0),
+            slot("b10e2d527612073b26eecdfd717e6a320cf44b4afac2b0732d9fcbe2b7fa0cf6")
+        );
+    }
+}
@@ -1,14 +1,134 @@
 mod core_module;
 
 /* ---------------------------------- Core ---------------------------------- */
-pub use core_module::memory::Memory;
+pub use core_module::asm;
+pub use core_module::cheatcodes;
+pub use core_module::disassembler;
+pub use core_module::env;
+pub use core_module::frame::{decode_selector, function_signature, CallNode, CallType};
+pub use core_module::journal;
+pub use core_module::memory::{memory_expansion_cost, Memory};
 pub use core_module::op_codes;
+pub use core_module::provider::{MockProvider, StateProvider};
+pub use core_module::result::{
+    decode_revert_reason, diff_runs, BalanceChange, ExecutionOutcome, ExecutionResult, RunDiff,
+    SimulationResult, StorageChange,
+};
 pub use core_module::runner::Runner;
 pub use core_module::stack::Stack;
-pub use core_module::state::EvmState;
+pub use core_module::state::{AccountHook, EvmState, StateCheckpoint};
+#[cfg(feature = "state-tests")]
+pub use core_module::state_test;
+pub use core_module::trace::TraceStep;
 
 /* ---------------------------------- Utils --------------------------------- */
+pub use core_module::utils::address;
 pub use core_module::utils::bytes;
 pub use core_module::utils::debug;
 pub use core_module::utils::environment;
-pub use core_module::utils::errors;
\ No newline at end of file
+pub use core_module::utils::errors;
+pub use core_module::utils::primitives;
+pub use core_module::utils::slots;
+
+/* ---------------------------------- Eval ---------------------------------- */
+/// Runs a bytecode on a fresh runner without a fork and with the default environment, e.g.
+/// to evaluate a few opcodes.
+///
+/// # Arguments
+///
+/// * `bytecode` - The bytecode to run.
+///
+/// # Errors
+///
+/// Returns the `ExecutionError` that halted the execution. A revert is not an error, it is
+/// returned as the outcome of the result.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::eval;
+///
+/// // PUSH1 0x01 PUSH1 0x02 ADD
+/// let result = eval(&[0x60, 0x01, 0x60, 0x02, 0x01]).unwrap();
+///
+/// assert!(result.outcome.is_success());
+/// assert_eq!(result.stack.len(), 1);
+/// assert_eq!(result.stack[0][31], 0x03);
+/// ```
+pub fn eval(bytecode: &[u8]) -> Result<ExecutionResult, errors::ExecutionError> {
+    let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    let result = runner.run(bytecode.to_vec(), None);
+
+    match result.outcome {
+        ExecutionOutcome::Halt(error) => Err(error),
+        _ => Ok(result),
+    }
+}
+
+/* ---------------------------------- Fuzz ---------------------------------- */
+/// The address the code is set at by `fuzz_call`.
+const FUZZ_ADDRESS: [u8; 20] = [0xf0; 20];
+
+/// Sets a code on a fresh runner and calls it with the given calldata, e.g. as a `cargo-fuzz`
+/// target. Every `ExecutionError` is swallowed, so that only a panic of the interpreter is
+/// reported by the fuzzer. The execution is bounded by a step limit.
+///
+/// # Arguments
+///
+/// * `code` - The runtime code of the called contract.
+/// * `calldata` - The calldata of the call.
+///
+/// # Examples
+///
+/// ```
+/// use evm_rs_emulator::fuzz_call;
+///
+/// // PUSH0 PUSH8 0xffffffffffffffff MSTORE: MSTORE at an out of range offset
+/// let mut code = vec![0x5f, 0x67];
+/// code.extend([0xff; 8]);
+/// code.push(0x52);
+/// fuzz_call(&code, &[]);
+/// ```
+pub fn fuzz_call(code: &[u8], calldata: &[u8]) {
+    let mut runner = Runner::new([0xaa; 20], None, None, None, None, None);
+    runner.print_errors = false;
+    runner.max_steps = Some(100_000);
+
+    runner.state.set_code(FUZZ_ADDRESS, code.to_vec());
+    let gas = runner.gas;
+    let _ = runner.call(FUZZ_ADDRESS, [0u8; 32], calldata.to_vec(), gas, false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_module::asm::assemble;
+
+    #[test]
+    fn test_fuzz_call_adversarial_inputs() {
+        let inputs = [
+            // KECCAK256 of a word at a 4 GiB offset
+            "PUSH1 0x20\nPUSH4 0xffffffff\nKECCAK256",
+            // MLOAD at the largest 64-bit offset
+            "PUSH8 0xffffffffffffffff\nMLOAD",
+            // RETURN of more than a word from an empty memory
+            "PUSH1 0x64\nPUSH0\nRETURN",
+            // LOG0 and REVERT of a size not fitting in a usize
+            "PUSH32 0x8000000000000000000000000000000000000000000000000000000000000000\nPUSH0\nLOG0",
+            "PUSH32 0x8000000000000000000000000000000000000000000000000000000000000000\nPUSH0\nREVERT",
+            // CALLDATALOAD and CALLDATACOPY from offsets not fitting in a usize
+            "PUSH32 0x8000000000000000000000000000000000000000000000000000000000000000\nCALLDATALOAD",
+            "PUSH1 0x20\nPUSH32 0x8000000000000000000000000000000000000000000000000000000000000000\nPUSH0\nCALLDATACOPY",
+            // CALL with arguments at an out of range offset
+            "PUSH0\nPUSH0\nPUSH1 0x20\nPUSH8 0xffffffffffffffff\nPUSH0\nPUSH0\nGAS\nCALL",
+        ];
+
+        for input in inputs {
+            fuzz_call(&assemble(input).unwrap(), &[0xff; 36]);
+        }
+
+        // A truncated PUSH and an empty code
+        fuzz_call(&[0x7f, 0x01], &[]);
+        fuzz_call(&[], &[]);
+    }
+}
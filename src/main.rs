@@ -1,6 +1,7 @@
-mod core_module;
-use core_module::state::EvmState;
-use core_module::utils::errors::ExecutionError;
+use evm_rs_emulator::debug::to_hex_address;
+use evm_rs_emulator::disassembler::{self, analyze};
+use evm_rs_emulator::errors::ExecutionError;
+use evm_rs_emulator::{decode_revert_reason, EvmState, Runner};
 use std::{env, fs};
 
 // Colored output
@@ -112,19 +113,13 @@ fn main() -> Result<(), ExecutionError> {
         .map(|p| &args[p + 1]);
 
     if let Some(value_arg) = value_arg {
-        if value_arg.len() < 3 || !value_arg.starts_with("0x") {
-            unexpected_arg_value("Value", "a hex value");
-            return Ok(());
+        match parse_hex_value(value_arg) {
+            Some(parsed) => value = Some(parsed),
+            None => {
+                unexpected_arg_value("Value", "a hex value of at most 32 bytes");
+                return Ok(());
+            }
         }
-
-        let bytes =
-            hex::decode(&value_arg[2..]).expect(&"Invalid argument: --value".red().to_string());
-
-        let mut padded = [0u8; 32];
-        let start_index = 32 - bytes.len();
-        padded[start_index..].copy_from_slice(&bytes);
-
-        value = Some(padded);
     }
 
     /* ----------------------------- Fetch call data ---------------------------- */
@@ -134,18 +129,13 @@ fn main() -> Result<(), ExecutionError> {
         .map(|p| &args[p + 1]);
 
     if let Some(data_arg) = data_arg {
-        if data_arg.len() < 3 || !data_arg.starts_with("0x") {
-            unexpected_arg_value("Data", "a hex value");
-            return Ok(());
+        match parse_hex_data(data_arg) {
+            Some(parsed) => data = Some(parsed),
+            None => {
+                unexpected_arg_value("Data", "a hex value");
+                return Ok(());
+            }
         }
-
-        let bytes =
-            hex::decode(&data_arg[2..]).expect(&"Invalid argument: --data".red().to_string());
-        data = Some(
-            bytes
-                .try_into()
-                .expect(&"Invalid argument: --data".red().to_string()),
-        );
     }
 
     /* --------------------------- Fetch the fork url --------------------------- */
@@ -166,6 +156,28 @@ fn main() -> Result<(), ExecutionError> {
         state = EvmState::new(None);
     }
 
+    /* -------------------------- Fetch the script path ------------------------- */
+    let script_arg = args
+        .iter()
+        .position(|r| r == "--script")
+        .map(|p| &args[p + 1]);
+
+    if let Some(script_arg) = script_arg {
+        let script = match fs::read_to_string(script_arg) {
+            Ok(script) => script,
+            Err(_) => {
+                unexpected_arg_value("Script", "a path to a JSON file");
+                return Ok(());
+            }
+        };
+
+        let mut interpreter = Runner::new(caller, origin, address, value, data, Some(state));
+        if let Err(message) = run_script(&mut interpreter, &script) {
+            println!("{} {}", "Error:".red(), message);
+        }
+        return Ok(());
+    }
+
     /* ------------------------- Fetch the bytecode path ------------------------ */
     // The bytecode path is not an argument, but the last argument
     if args.len() > 1 {
@@ -197,16 +209,26 @@ fn main() -> Result<(), ExecutionError> {
         }
     }
 
+    /* ------------------------------ Analyze mode ------------------------------ */
+    let analyze_mode = args.contains(&"--analyze".to_string());
+
     // Create a new interpreter
-    let mut interpreter =
-        core_module::runner::Runner::new(caller, origin, address, value, data, Some(state));
+    let mut interpreter = Runner::new(caller, origin, address, value, data, Some(state));
 
     // Check if bytecode is an hex value of a file path
     if bytecode.starts_with("0x") {
         let bytecode = hex::decode(&bytecode[2..]).expect("Invalid bytecode");
 
+        if analyze_mode {
+            print_analysis(&mut interpreter, bytecode);
+            return Ok(());
+        }
+
         // Interpret the bytecode
-        let _ = interpreter.interpret(bytecode, debug_level, true);
+        let result = interpreter.interpret(bytecode, debug_level, true);
+        if let Some(summary) = revert_summary(&result) {
+            println!("{}", summary);
+        }
         return Ok(());
     }
 
@@ -216,8 +238,16 @@ fn main() -> Result<(), ExecutionError> {
         Ok(file_content) => {
             let bytecode = hex::decode(file_content.trim()).expect("Decoding failed");
 
+            if analyze_mode {
+                print_analysis(&mut interpreter, bytecode);
+                return Ok(());
+            }
+
             // Interpret the bytecode
-            let _ = interpreter.interpret(bytecode, debug_level, true);
+            let result = interpreter.interpret(bytecode, debug_level, true);
+            if let Some(summary) = revert_summary(&result) {
+                println!("{}", summary);
+            }
         }
         Err(_) => {
             // Print the error
@@ -233,6 +263,110 @@ fn main() -> Result<(), ExecutionError> {
     Ok(())
 }
 
+/// Parses a `0x` prefixed hex argument of any length.
+fn parse_hex_data(arg: &str) -> Option<Vec<u8>> {
+    if arg.len() < 3 || !arg.starts_with("0x") {
+        return None;
+    }
+
+    hex::decode(&arg[2..]).ok()
+}
+
+/// Parses a `0x` prefixed hex argument into a left padded 32 bytes word.
+fn parse_hex_value(arg: &str) -> Option<[u8; 32]> {
+    let bytes = parse_hex_data(arg)?;
+    if bytes.len() > 32 {
+        return None;
+    }
+
+    let mut padded = [0u8; 32];
+    padded[32 - bytes.len()..].copy_from_slice(&bytes);
+
+    Some(padded)
+}
+
+/// Runs a JSON script of deployments and calls against the state of the runner, in order.
+///
+/// The script is an array of steps, each one being either `{"deploy": "0x<init code>"}` or
+/// `{"call": {"to": "0x<address>", "data": "0x<calldata>", "value": "0x<value>"}}` where
+/// `data` and `value` are optional. Every step must succeed for the next one to run.
+fn run_script(interpreter: &mut Runner, script: &str) -> Result<(), String> {
+    let steps: serde_json::Value =
+        serde_json::from_str(script).map_err(|error| format!("invalid script: {}", error))?;
+    let steps = steps
+        .as_array()
+        .ok_or("the script should be an array of steps")?;
+
+    for (index, step) in steps.iter().enumerate() {
+        let hex_field = |value: Option<&serde_json::Value>, name: &str| {
+            value
+                .and_then(|value| value.as_str())
+                .and_then(parse_hex_data)
+                .ok_or(format!("step {}: '{}' should be a hex value", index, name))
+        };
+
+        if let Some(init_code) = step.get("deploy") {
+            let init_code = hex_field(Some(init_code), "deploy")?;
+            let address = interpreter
+                .deploy(init_code)
+                .map_err(|error| format!("step {}: deployment failed: {}", index, error))?;
+
+            println!(
+                "{} {}",
+                "Deployed".green(),
+                to_hex_address(address).magenta()
+            );
+        } else if let Some(call) = step.get("call") {
+            let to: [u8; 20] = hex_field(call.get("to"), "to")?
+                .try_into()
+                .map_err(|_| format!("step {}: 'to' should be an address", index))?;
+            let data = match call.get("data") {
+                Some(_) => hex_field(call.get("data"), "data")?,
+                None => Vec::new(),
+            };
+            let value = match call.get("value").and_then(|value| value.as_str()) {
+                Some(value) => parse_hex_value(value)
+                    .ok_or(format!("step {}: 'value' should be a hex value", index))?,
+                None => [0u8; 32],
+            };
+
+            let gas = interpreter.gas;
+            interpreter
+                .call(to, value, data, gas, false)
+                .map_err(|error| format!("step {}: call failed: {}", index, error))?;
+
+            println!("{} {}", "Called".green(), to_hex_address(to).magenta());
+        } else {
+            return Err(format!(
+                "step {}: expected a 'deploy' or 'call' step",
+                index
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Describes the revert data of a reverted execution, with its reason if it is an
+/// `Error(string)`. Returns `None` if the execution didn't revert with some data.
+fn revert_summary(result: &Result<(), ExecutionError>) -> Option<String> {
+    let data = match result {
+        Err(ExecutionError::Revert(data)) => data,
+        _ => return None,
+    };
+
+    let mut summary = format!("{}: 0x{}", "Revert data".magenta(), hex::encode(data));
+    if let Some(reason) = decode_revert_reason(data) {
+        summary.push_str(&format!(
+            "\n{}: {}",
+            "Revert reason".magenta(),
+            reason.red()
+        ));
+    }
+
+    Some(summary)
+}
+
 fn unexpected_arg_value(arg: &str, arg_type: &str) {
     println!(
         "{} unexpected value for '{}' argument.",
@@ -246,6 +380,44 @@ fn unexpected_arg_value(arg: &str, arg_type: &str) {
     );
 }
 
+fn print_analysis(interpreter: &mut Runner, bytecode: Vec<u8>) {
+    let analysis = analyze(&bytecode);
+
+    println!("{}: {} bytes", "Code size".magenta(), analysis.code_size);
+    println!("{}: {}", "JUMPDESTs".magenta(), analysis.jumpdests);
+    println!(
+        "{}: {}",
+        "Truncated PUSH".magenta(),
+        if analysis.truncated_push {
+            "yes".red()
+        } else {
+            "no".green()
+        }
+    );
+    println!("{}:", "Opcodes".magenta());
+    for (opcode, count) in &analysis.opcode_counts {
+        println!(
+            "  {:<14} 0x{:02X}  {}",
+            disassembler::opcode_name(*opcode).bright_blue(),
+            opcode,
+            count
+        );
+    }
+
+    // Visit every instruction, including the ones after a halting opcode
+    interpreter.scan_mode = true;
+    let _ = interpreter.interpret(bytecode.clone(), None, true);
+
+    println!("{}:", "Instructions".magenta());
+    for &pc in &interpreter.scanned_pcs {
+        println!(
+            "  0x{:04X}  {}",
+            pc,
+            disassembler::opcode_name(bytecode[pc]).bright_blue()
+        );
+    }
+}
+
 fn print_help() {
     println!("Execute arbitrary bytecode on the Ethereum Virtual Machine (EVM)");
     println!(
@@ -293,4 +465,108 @@ fn print_help() {
         "fork".magenta(),
         "RPC_URL".blue()
     );
+    println!(
+        "  --{} <{}>        Run a JSON script of deployments and calls",
+        "script".magenta(),
+        "FILE".blue()
+    );
+    println!(
+        "  --{}                Print a profile and the instructions of the bytecode instead of executing it",
+        "analyze".magenta()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex_data() {
+        // Calldata has no fixed length
+        let long_data = format!("0x{}", "ab".repeat(100));
+        assert_eq!(parse_hex_data(&long_data), Some(vec![0xab; 100]));
+        assert_eq!(parse_hex_data("0x01"), Some(vec![0x01]));
+
+        assert_eq!(parse_hex_data("0x"), None);
+        assert_eq!(parse_hex_data("abcd"), None);
+        assert_eq!(parse_hex_data("0xabc"), None);
+    }
+
+    #[test]
+    fn test_run_script() {
+        let mut interpreter = Runner::new([0xaa; 20], None, None, None, None, None);
+        let nonce =
+            evm_rs_emulator::environment::get_nonce(interpreter.address, &mut interpreter).unwrap();
+        let contract =
+            evm_rs_emulator::address::compute_create_address(interpreter.address, nonce[31] as u64);
+
+        // Deploy a contract storing the first calldata word at slot 0, then call it
+        let script = format!(
+            r#"[
+                {{"deploy": "0x645f355f55005f526005601bf3"}},
+                {{"call": {{"to": "{}", "data": "0x{}2a"}}}}
+            ]"#,
+            to_hex_address(contract),
+            "00".repeat(31)
+        );
+        run_script(&mut interpreter, &script).unwrap();
+
+        assert_eq!(
+            interpreter.state.get_code_at(contract).unwrap(),
+            &vec![0x5f, 0x35, 0x5f, 0x55, 0x00]
+        );
+        let mut expected = [0u8; 32];
+        expected[31] = 0x2a;
+        assert_eq!(
+            interpreter.state.sload(contract, [0u8; 32]).unwrap(),
+            expected
+        );
+
+        assert!(run_script(&mut interpreter, r#"[{"jump": "0x00"}]"#).is_err());
+        assert!(run_script(&mut interpreter, "{}").is_err());
+    }
+
+    #[test]
+    fn test_revert_summary() {
+        use ethers::abi::{encode, Token};
+
+        // Store Error("not enough balance") in memory and revert with it
+        let mut data = vec![0x08, 0xc3, 0x79, 0xa0];
+        data.extend(encode(&[Token::String("not enough balance".to_string())]));
+        data.resize(data.len().div_ceil(32) * 32, 0);
+
+        let mut bytecode = Vec::new();
+        for (index, chunk) in data.chunks(32).enumerate() {
+            bytecode.push(0x7f);
+            bytecode.extend_from_slice(chunk);
+            bytecode.extend_from_slice(&[0x60, (index * 32) as u8, 0x52]);
+        }
+        // PUSH1 100 PUSH0 REVERT
+        bytecode.extend_from_slice(&[0x60, 100, 0x5f, 0xfd]);
+
+        let mut interpreter = Runner::new([0xaa; 20], None, None, None, None, None);
+        let result = interpreter.interpret(bytecode, None, true);
+
+        let summary = revert_summary(&result).unwrap();
+        assert!(summary.contains(&hex::encode(&data[..100])));
+        assert!(summary.contains("not enough balance"));
+
+        // Other outcomes have no summary
+        assert!(revert_summary(&Ok(())).is_none());
+        assert!(revert_summary(&Err(ExecutionError::RevertWithoutData)).is_none());
+    }
+
+    #[test]
+    fn test_parse_hex_value() {
+        let mut expected = [0u8; 32];
+        expected[31] = 0xff;
+        assert_eq!(parse_hex_value("0xff"), Some(expected));
+        assert_eq!(
+            parse_hex_value(&format!("0x{}", "11".repeat(32))),
+            Some([0x11; 32])
+        );
+
+        // Oversized value
+        assert_eq!(parse_hex_value(&format!("0x{}", "11".repeat(33))), None);
+    }
 }
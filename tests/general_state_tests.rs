@@ -0,0 +1,45 @@
+//! Runs the state tests of `tests/state_tests`, written in the format of the ethereum/tests
+//! GeneralStateTests fillers, with `cargo test --features state-tests`.
+#![cfg(feature = "state-tests")]
+
+use std::fs;
+use std::path::Path;
+
+use evm_rs_emulator::state_test::load_state_tests;
+
+/// Runs every filler of a state tests directory and returns the failures.
+fn run_directory(directory: &str) -> Vec<String> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/state_tests")
+        .join(directory);
+
+    let mut fillers: Vec<_> = fs::read_dir(path)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    fillers.sort();
+    assert!(!fillers.is_empty());
+
+    let mut failures = Vec::new();
+    for filler in fillers {
+        let tests = load_state_tests(&fs::read_to_string(&filler).unwrap())
+            .unwrap_or_else(|error| panic!("{}: {}", filler.display(), error));
+
+        for test in tests {
+            failures.extend(test.run());
+        }
+    }
+
+    failures
+}
+
+/// The hand-written arithmetic fillers, see `tests/state_tests/synthetic/README.md`.
+#[test]
+fn synthetic_arithmetic_tests() {
+    let failures = run_directory("synthetic");
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}